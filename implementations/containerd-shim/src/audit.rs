@@ -0,0 +1,632 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Verification audit log (verification-protocol.adoc Section 10.3,
+// runtime-integration.adoc Section 8.2)
+//
+// One JSON record per line. Each record carries the hash of the record
+// before it and its own hash over its canonical form, so an edited, removed
+// or reordered record breaks the chain. `<log>.head` holds the sequence
+// number and hash of the newest record and is what the next record chains
+// from: cutting records off the end of the log leaves a gap that the next
+// append and `verify-audit-log` both expose. Anyone who can rewrite both
+// files can still forge a consistent log; ship head hashes off the host
+// where that matters and check them with `verify-audit-log --head`.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::canonical;
+use crate::log_client::NetworkError;
+use crate::metrics;
+use crate::config::FileSinkConfig;
+use crate::verify::VerificationMode;
+
+/// `prevHash` of the first record
+pub const GENESIS_HASH: &str = "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Error codes from verification-protocol.adoc Section 7 and the
+/// implementation-specific codes the shim reports
+const ERROR_CODES: &[&str] = &[
+    "MISSING_ATTESTATION",
+    "MALFORMED_BUNDLE",
+    "SUBJECT_MISMATCH",
+    "UNKNOWN_KEY",
+    "EXPIRED_KEY",
+    "KEY_NOT_YET_VALID",
+    "INVALID_SIGNATURE",
+    "INSUFFICIENT_LOG_COVERAGE",
+    "LOG_PROOF_INVALID",
+    "THRESHOLD_NOT_MET",
+    "REVOKED",
+    "REVOCATION_STALE",
+    "STH_INVALID",
+    "SET_INVALID",
+    "LOG_EQUIVOCATION",
+    "LOG_STALE",
+    "QUORUM_NOT_MET",
+    "CACHE_INVALID",
+];
+
+/// The first error code named in an error message
+pub fn error_code(message: &str) -> Option<String> {
+    ERROR_CODES.iter()
+        .filter_map(|code| message.find(code).map(|at| (at, *code)))
+        .min()
+        .map(|(_, code)| code.to_string())
+}
+
+/// The error code of a failure. An unreachable log is NETWORK_ERROR
+/// whatever step it failed, since that decides the exit code.
+pub fn error_code_of(error: &anyhow::Error) -> Option<String> {
+    if error.downcast_ref::<NetworkError>().is_some() {
+        return Some("NETWORK_ERROR".to_string());
+    }
+    error_code(&format!("{:#}", error))
+}
+
+/// The decision a record documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Outcome {
+    /// Verification passed
+    Allow,
+    /// Verification failed and the container was not started
+    Reject,
+    /// Verification failed; permissive mode started the container anyway
+    WarnAllowed,
+    /// Verification failed; audit mode started the container anyway
+    AuditAllowed,
+    /// A cached result failed authentication and was discarded
+    CacheInvalid,
+}
+
+impl Outcome {
+    /// What happens to a container whose verification passed or failed
+    /// under `mode` (runtime-integration.adoc Section 6.3)
+    pub fn decide(passed: bool, mode: VerificationMode) -> Self {
+        match (passed, mode) {
+            (true, _) => Outcome::Allow,
+            (false, VerificationMode::Strict) => Outcome::Reject,
+            (false, VerificationMode::Permissive) => Outcome::WarnAllowed,
+            (false, VerificationMode::Audit) => Outcome::AuditAllowed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    /// Not run, in verification reports; audit records only list steps
+    /// that ran
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StepResult {
+    pub step: String,
+    pub status: StepStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// What a verification saw, collected while it runs
+#[derive(Debug, Default)]
+pub struct AuditTrail {
+    pub steps: Vec<StepResult>,
+    pub keyids: Vec<String>,
+    pub log_ids: Vec<String>,
+    pub trust_store_version: Option<String>,
+    /// The decision came from the verification cache
+    pub cached: bool,
+    /// When the current step started, if steps are being timed
+    pub step_started: Option<std::time::Instant>,
+    // Counts for the verification report; not part of audit records
+    pub attestations_verified: usize,
+    pub log_entries_verified: usize,
+    pub thresholds_met: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl AuditTrail {
+    /// Time each step from here on: a step's latency is the time since the
+    /// previous step, or since this call for the first
+    pub fn start_timing(&mut self) {
+        self.step_started = Some(std::time::Instant::now());
+    }
+
+    /// Record the outcome of `step` and pass its result through
+    pub fn step<T>(&mut self, step: &str, result: Result<T>) -> Result<T> {
+        if let Some(started) = self.step_started.replace(std::time::Instant::now()) {
+            metrics::observe_step(step, started.elapsed());
+        }
+        let (status, detail) = match &result {
+            Ok(_) => (StepStatus::Passed, None),
+            Err(e) => (StepStatus::Failed, Some(format!("{:#}", e))),
+        };
+        self.steps.push(StepResult { step: step.to_string(), status, detail });
+        result
+    }
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub host: String,
+    pub bundle: String,
+    pub digest: String,
+    pub mode: VerificationMode,
+    pub outcome: Outcome,
+    #[serde(rename = "trustStoreVersion", default, skip_serializing_if = "Option::is_none")]
+    pub trust_store_version: Option<String>,
+    #[serde(default)]
+    pub cached: bool,
+    pub steps: Vec<StepResult>,
+    pub keyids: Vec<String>,
+    #[serde(rename = "logIds")]
+    pub log_ids: Vec<String>,
+    #[serde(rename = "errorCode", default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(rename = "prevHash")]
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// An unchained record of a decision; `AuditLog::append` fills in
+    /// `seq`, `prevHash` and `hash`
+    pub fn new(bundle: &str, digest: &str, mode: VerificationMode, outcome: Outcome, trail: AuditTrail) -> Self {
+        Self {
+            seq: 0,
+            timestamp: chrono::Utc::now(),
+            host: host_name(),
+            bundle: bundle.to_string(),
+            digest: digest.to_string(),
+            mode,
+            outcome,
+            trust_store_version: trail.trust_store_version,
+            cached: trail.cached,
+            steps: trail.steps,
+            keyids: trail.keyids,
+            log_ids: trail.log_ids,
+            error_code: None,
+            error: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+        }
+    }
+
+    pub fn with_error(self, error: &anyhow::Error) -> Self {
+        Self {
+            error_code: error_code_of(error),
+            error: Some(format!("{:#}", error)),
+            ..self
+        }
+    }
+
+    /// SHA-256 over the canonical record without its `hash` field
+    pub fn compute_hash(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("hash");
+        }
+        Ok(format!("sha256:{}", hex::encode(Sha256::digest(canonical::canonicalize(&value).as_bytes()))))
+    }
+}
+
+pub fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|n| n.trim().to_string())
+        .ok()
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// `<log>.head`: the newest record in the chain
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ChainHead {
+    pub seq: u64,
+    pub hash: String,
+}
+
+/// Range of the chain a verification covered
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainSummary {
+    /// Sequence number of the oldest record still on disk; records before
+    /// it were rotated out
+    pub first_seq: u64,
+    pub head: ChainHead,
+}
+
+/// A hash-chained audit log file and the files rotated out of it, named
+/// `<log>.<UTC time>-<last seq>` and optionally gzipped
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    rotation: FileSinkConfig,
+}
+
+impl AuditLog {
+    /// Audit log at `path` that is never rotated
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self::new(&FileSinkConfig { path, max_bytes: 0, max_age_secs: 0, ..FileSinkConfig::default() })
+    }
+
+    pub fn new(config: &FileSinkConfig) -> Self {
+        Self { path: config.path.clone(), rotation: config.clone() }
+    }
+
+    /// Default path: AUDIT_LOG_PATH, or /var/log/verified-container/audit.log
+    pub fn default_config() -> FileSinkConfig {
+        let mut config = FileSinkConfig::default();
+        if let Ok(path) = std::env::var("AUDIT_LOG_PATH") {
+            config.path = PathBuf::from(path);
+        }
+        config
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        self.path.with_file_name(name)
+    }
+
+    fn dir(&self) -> &Path {
+        self.path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+    }
+
+    fn head_path(&self) -> PathBuf {
+        self.sibling(".head")
+    }
+
+    fn read_head(&self) -> Result<Option<ChainHead>> {
+        let path = self.head_path();
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)
+                .context(format!("Failed to parse audit chain head {:?}", path))?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context(format!("Failed to read audit chain head {:?}", path)),
+        }
+    }
+
+    /// Rotated files and then the current file: every record, oldest first
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.rotated_files();
+        if self.path.exists() || files.is_empty() {
+            files.push(self.path.clone());
+        }
+        files
+    }
+
+    /// Rotated files, oldest first
+    pub fn rotated_files(&self) -> Vec<PathBuf> {
+        let prefix = format!("{}.", self.path.file_name().unwrap_or_default().to_string_lossy());
+        let Ok(entries) = std::fs::read_dir(self.dir()) else {
+            return vec![];
+        };
+        let mut rotated: Vec<((String, u64), PathBuf)> = entries.flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let suffix = name.strip_prefix(&prefix)?;
+                let (time, seq) = suffix.strip_suffix(".gz").unwrap_or(suffix).split_once('-')?;
+                let seq = seq.parse().ok()?;
+                Some(((time.to_string(), seq), entry.path()))
+            })
+            .collect();
+        rotated.sort();
+        rotated.into_iter().map(|(_, path)| path).collect()
+    }
+
+    /// The chain position the next record follows: the head file, or the
+    /// last record in the log when there is no head yet. A log that does
+    /// not end in a record is broken, not a new chain.
+    fn tip(&self) -> Result<Option<ChainHead>> {
+        if let Some(head) = self.read_head()? {
+            return Ok(Some(head));
+        }
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(format!("Failed to read audit log {:?}", self.path)),
+        };
+        let Some(last) = content.lines().rev().find(|l| !l.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let record: AuditRecord = serde_json::from_str(last)
+            .context(format!("AUDIT_CHAIN_BROKEN: last line of {:?} is not an audit record", self.path))?;
+        Ok(Some(ChainHead { seq: record.seq, hash: record.hash }))
+    }
+
+    /// Chain `record` onto the log and append it, rotating first if due.
+    /// Concurrent shims are serialized by an exclusive lock on `<log>.lock`.
+    pub fn append(&self, mut record: AuditRecord) -> Result<AuditRecord> {
+        std::fs::create_dir_all(self.dir())
+            .context("Failed to create audit log directory")?;
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sibling(".lock"))
+            .context("Failed to open audit log lock")?;
+        lock.lock().context("Failed to lock audit log")?;
+
+        let tip = self.tip()?;
+        record.seq = tip.as_ref().map_or(0, |t| t.seq + 1);
+        record.prev_hash = tip.as_ref().map_or_else(|| GENESIS_HASH.to_string(), |t| t.hash.clone());
+        record.hash = record.compute_hash()?;
+        let line = format!("{}\n", serde_json::to_string(&record)?);
+
+        if let Some(tip) = &tip {
+            if self.rotation_due(line.len() as u64, record.timestamp)? {
+                self.rotate(tip.seq)?;
+            }
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open audit log")?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        let head = ChainHead { seq: record.seq, hash: record.hash.clone() };
+        let mut tmp = tempfile::NamedTempFile::new_in(self.dir())?;
+        tmp.write_all(&serde_json::to_vec(&head)?)?;
+        tmp.persist(self.head_path())
+            .context("Failed to write audit chain head")?;
+        Ok(record)
+    }
+
+    fn rotation_due(&self, incoming: u64, now: chrono::DateTime<chrono::Utc>) -> Result<bool> {
+        let size = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e).context(format!("Failed to stat audit log {:?}", self.path)),
+        };
+        if size == 0 {
+            return Ok(false);
+        }
+        if self.rotation.max_bytes > 0 && size + incoming > self.rotation.max_bytes {
+            return Ok(true);
+        }
+        if self.rotation.max_age_secs > 0 {
+            let mut first = String::new();
+            BufReader::new(std::fs::File::open(&self.path)?).read_line(&mut first)?;
+            if let Ok(oldest) = serde_json::from_str::<AuditRecord>(&first) {
+                let age = now.signed_duration_since(oldest.timestamp);
+                return Ok(age.num_seconds() >= 0 && age.num_seconds() as u64 >= self.rotation.max_age_secs);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Move the current file aside, then delete rotated files beyond `retain`
+    fn rotate(&self, last_seq: u64) -> Result<()> {
+        let time = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+        let rotated = self.sibling(&format!(".{}-{}", time, last_seq));
+        std::fs::rename(&self.path, &rotated)
+            .context(format!("Failed to rotate audit log {:?}", self.path))?;
+
+        if self.rotation.compress {
+            let mut gz = rotated.clone().into_os_string();
+            gz.push(".gz");
+            let mut encoder = flate2::write::GzEncoder::new(
+                std::fs::File::create(&gz)?,
+                flate2::Compression::default(),
+            );
+            std::io::copy(&mut std::fs::File::open(&rotated)?, &mut encoder)?;
+            encoder.finish()?.sync_all()?;
+            std::fs::remove_file(&rotated)?;
+        }
+
+        let rotated = self.rotated_files();
+        let excess = rotated.len().saturating_sub(self.rotation.retain);
+        for old in &rotated[..excess] {
+            std::fs::remove_file(old)
+                .context(format!("Failed to remove old audit log {:?}", old))?;
+        }
+        Ok(())
+    }
+
+    /// Check the chain through the rotated files and the current file, and
+    /// that it ends at the head file. With `expected`, a head hash recorded
+    /// elsewhere must also be in the chain.
+    pub fn verify(&self, expected: Option<&str>) -> Result<Option<ChainSummary>> {
+        let rotated = !self.rotated_files().is_empty();
+        let files = self.files();
+
+        let mut tip: Option<ChainHead> = None;
+        let mut first_seq = 0;
+        let mut found_expected = false;
+        for path in &files {
+            let reader = open_log_file(path)
+                .context(format!("invalid input: failed to read audit log {:?}", path))?;
+            for (i, line) in reader.lines().enumerate() {
+                let line = line.context(format!("Failed to read audit log {:?}", path))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let place = format!("{}:{}", path.display(), i + 1);
+                let record: AuditRecord = serde_json::from_str(&line)
+                    .context(format!("AUDIT_CHAIN_BROKEN: {} is not an audit record", place))?;
+                let (seq, prev_hash) = match &tip {
+                    Some(t) => (t.seq + 1, t.hash.as_str()),
+                    // Retention may have deleted the start of the chain
+                    None if rotated => (record.seq, record.prev_hash.as_str()),
+                    None => (0, GENESIS_HASH),
+                };
+                if record.seq != seq {
+                    bail!("AUDIT_CHAIN_BROKEN: {} is record {}, expected {}", place, record.seq, seq);
+                }
+                if record.prev_hash != prev_hash {
+                    bail!("AUDIT_CHAIN_BROKEN: record {} does not follow the record before it", seq);
+                }
+                if record.compute_hash()? != record.hash {
+                    bail!("AUDIT_CHAIN_BROKEN: record {} was modified", seq);
+                }
+                if tip.is_none() {
+                    first_seq = seq;
+                }
+                found_expected |= expected == Some(record.hash.as_str());
+                tip = Some(ChainHead { seq, hash: record.hash });
+            }
+        }
+
+        if let Some(head) = self.read_head()? {
+            match &tip {
+                Some(t) if *t == head => {}
+                Some(t) if t.seq < head.seq => {
+                    bail!("AUDIT_TRUNCATED: log ends at record {} but its head is record {}", t.seq, head.seq);
+                }
+                None => bail!("AUDIT_TRUNCATED: log is empty but its head is record {}", head.seq),
+                Some(t) => bail!("AUDIT_CHAIN_BROKEN: log ends at record {} but its head is record {} {}",
+                    t.seq, head.seq, head.hash),
+            }
+        }
+        if let Some(expected) = expected {
+            if !found_expected {
+                bail!("AUDIT_TRUNCATED: head {} is not in the log", expected);
+            }
+        }
+        Ok(tip.map(|head| ChainSummary { first_seq, head }))
+    }
+}
+
+/// Reader over a current or rotated log file, decompressing `.gz`
+pub fn open_log_file(path: &Path) -> Result<Box<dyn BufRead>> {
+    let file = std::fs::File::open(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        Ok(Box::new(BufReader::new(flate2::read::GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn record(outcome: Outcome) -> AuditRecord {
+        let trail = AuditTrail {
+            keyids: vec!["builder".to_string()],
+            log_ids: vec!["log-eu".to_string()],
+            trust_store_version: Some("v1".to_string()),
+            ..AuditTrail::default()
+        };
+        AuditRecord::new("nginx \"quoted\"", "sha256:aa", VerificationMode::Strict, outcome, trail)
+    }
+
+    fn lines(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_chain_detects_edits_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let log = AuditLog::open(&path);
+        for _ in 0..3 {
+            log.append(record(Outcome::Allow)).unwrap();
+        }
+        let head = log.verify(None).unwrap().unwrap().head;
+        assert_eq!(head.seq, 2);
+        assert!(log.verify(Some(&head.hash)).is_ok());
+        let original = lines(&path);
+        let parsed: AuditRecord = serde_json::from_str(&original[0]).unwrap();
+        assert_eq!(parsed.bundle, "nginx \"quoted\"");
+
+        // Edited outcome
+        let edited = original[1].replace("\"ALLOW\"", "\"REJECT\"");
+        std::fs::write(&path, [original[0].clone(), edited, original[2].clone()].join("\n")).unwrap();
+        assert!(format!("{:#}", log.verify(None).unwrap_err()).contains("record 1 was modified"));
+
+        // Removed record
+        std::fs::write(&path, [original[0].clone(), original[2].clone()].join("\n")).unwrap();
+        assert!(format!("{:#}", log.verify(None).unwrap_err()).contains("AUDIT_CHAIN_BROKEN"));
+
+        // Cut off the end: caught by the head file, and the next record
+        // does not paper over the gap
+        std::fs::write(&path, format!("{}\n", original[..2].join("\n"))).unwrap();
+        assert!(format!("{:#}", log.verify(None).unwrap_err()).contains("AUDIT_TRUNCATED"));
+        log.append(record(Outcome::Allow)).unwrap();
+        assert!(log.verify(None).is_err());
+
+        // Truncated together with the head file: only an external head helps
+        std::fs::write(&path, format!("{}\n", original[..2].join("\n"))).unwrap();
+        std::fs::remove_file(log.head_path()).unwrap();
+        assert!(log.verify(None).is_ok());
+        assert!(log.verify(Some(&head.hash)).is_err());
+
+        // Without a head file a garbled last line must not restart the chain
+        std::fs::write(&path, format!("{}\n{{\"seq\": 2, \"hash\n", original[..2].join("\n"))).unwrap();
+        let err = log.append(record(Outcome::Allow)).unwrap_err();
+        assert!(format!("{:#}", err).contains("AUDIT_CHAIN_BROKEN"));
+    }
+
+    #[test]
+    fn test_error_code_is_the_first_named() {
+        assert_eq!(error_code("Log eu: SET_INVALID: Signed Entry Timestamp: LOG_PROOF_INVALID").as_deref(), Some("SET_INVALID"));
+        assert_eq!(error_code("Insufficient log coverage (INSUFFICIENT_LOG_COVERAGE)").as_deref(), Some("INSUFFICIENT_LOG_COVERAGE"));
+        assert_eq!(error_code("something else"), None);
+
+        // Only a typed network error counts, not a URL that mentions one
+        let network = anyhow::Error::from(NetworkError("log unavailable".to_string())).context("LOG_PROOF_INVALID: no proof");
+        assert_eq!(error_code_of(&network).as_deref(), Some("NETWORK_ERROR"));
+        assert_eq!(error_code_of(&anyhow::anyhow!("https://network-error.example is down")), None);
+
+        let record = record(Outcome::Reject).with_error(&anyhow::anyhow!("REVOKED: builder was revoked"));
+        assert_eq!(record.error_code.as_deref(), Some("REVOKED"));
+    }
+
+    #[test]
+    fn test_outcome_records_mode_overrides() {
+        assert_eq!(Outcome::decide(true, VerificationMode::Audit), Outcome::Allow);
+        assert_eq!(Outcome::decide(false, VerificationMode::Strict), Outcome::Reject);
+        assert_eq!(Outcome::decide(false, VerificationMode::Permissive), Outcome::WarnAllowed);
+        assert_eq!(Outcome::decide(false, VerificationMode::Audit), Outcome::AuditAllowed);
+        assert_eq!(serde_json::to_value(Outcome::WarnAllowed).unwrap(), "WARN_ALLOWED");
+    }
+
+    #[test]
+    fn test_rotation_keeps_the_chain_verifiable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let config = FileSinkConfig { path: path.clone(), max_bytes: 1500, retain: 2, ..FileSinkConfig::default() };
+        let log = AuditLog::new(&config);
+        for _ in 0..12 {
+            log.append(record(Outcome::Allow)).unwrap();
+        }
+
+        // Each record is around 600 bytes: two per file, at most two rotated files kept
+        let rotated = log.rotated_files();
+        assert_eq!(rotated.len(), 2);
+        assert!(rotated.iter().all(|p| p.extension().unwrap() == "gz"));
+        assert!(std::fs::metadata(&path).unwrap().len() <= 1500);
+
+        let chain = log.verify(None).unwrap().unwrap();
+        assert_eq!(chain.head.seq, 11);
+        assert!(chain.first_seq > 0);
+
+        // A record edited inside a rotated file is still caught
+        let mut content = String::new();
+        open_log_file(&rotated[1]).unwrap().read_to_string(&mut content).unwrap();
+        let content = content.replace("\"ALLOW\"", "\"REJECT\"");
+        std::fs::write(rotated[1].with_extension(""), content).unwrap();
+        std::fs::remove_file(&rotated[1]).unwrap();
+        assert!(format!("{:#}", log.verify(None).unwrap_err()).contains("was modified"));
+    }
+}
@@ -0,0 +1,357 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// `audit query`: filter and summarize audit logs
+//
+// Reads the rotated files (gzipped or not) and then the current file of an
+// audit log, oldest record first. Lines that are not audit records, such as
+// those written before records were structured, are skipped and counted.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+use crate::audit::{self, AuditLog, AuditRecord, Outcome};
+use crate::verify::VerificationMode;
+
+/// Record filters; every filter that is set must match
+#[derive(Debug, Default)]
+pub struct AuditQuery {
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Any of these outcomes
+    pub outcomes: Vec<Outcome>,
+    /// Any of these modes
+    pub modes: Vec<VerificationMode>,
+    /// Image digest, or a prefix of one
+    pub digest: Option<String>,
+    /// A key ID involved in the decision
+    pub keyid: Option<String>,
+    pub error_code: Option<String>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+            && (self.outcomes.is_empty() || self.outcomes.contains(&record.outcome))
+            && (self.modes.is_empty() || self.modes.contains(&record.mode))
+            && self.digest.as_ref().is_none_or(|d| record.digest.starts_with(d.as_str()))
+            && self.keyid.as_ref().is_none_or(|k| record.keyids.contains(k))
+            && self.error_code.as_ref().is_none_or(|c| record.error_code.as_ref() == Some(c))
+    }
+
+    /// Matching records from every file of `log`, oldest first, and the
+    /// number of lines that were not audit records
+    pub fn run(&self, log: &AuditLog) -> Result<(Vec<AuditRecord>, usize)> {
+        let mut records = Vec::new();
+        let mut skipped = 0;
+        for path in log.files() {
+            let reader = audit::open_log_file(&path)
+                .context(format!("invalid input: failed to read audit log {:?}", path))?;
+            for line in reader.lines() {
+                let line = line.context(format!("Failed to read audit log {:?}", path))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<AuditRecord>(&line) {
+                    Ok(record) if self.matches(&record) => records.push(record),
+                    Ok(_) => {}
+                    Err(_) => skipped += 1,
+                }
+            }
+        }
+        Ok((records, skipped))
+    }
+}
+
+/// `--since`/`--until`: RFC 3339, or a duration before now such as `90m`,
+/// `24h` or `7d`
+pub fn parse_time(value: &str, now: chrono::DateTime<chrono::Utc>) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.to_utc());
+    }
+    let invalid = || anyhow!("invalid input: {:?} is neither an RFC 3339 time nor a duration like 7d", value);
+    let (split, _) = value.char_indices().last().ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    // Unsigned, so `-7d` is not read as a week from now
+    let amount: u32 = amount.parse().map_err(|_| invalid())?;
+    let amount = i64::from(amount);
+    let delta = match unit {
+        "s" => chrono::TimeDelta::try_seconds(amount),
+        "m" => chrono::TimeDelta::try_minutes(amount),
+        "h" => chrono::TimeDelta::try_hours(amount),
+        "d" => chrono::TimeDelta::try_days(amount),
+        "w" => chrono::TimeDelta::try_weeks(amount),
+        _ => None,
+    };
+    delta.and_then(|d| now.checked_sub_signed(d))
+        .ok_or_else(|| anyhow!("invalid input: unsupported duration {:?}", value))
+}
+
+/// Parse a comma-separated list of enum values by their JSON names
+pub fn parse_list<T: serde::de::DeserializeOwned>(value: &str, what: &str) -> Result<Vec<T>> {
+    value.split(',')
+        .map(|item| serde_json::from_value(serde_json::Value::String(item.trim().to_string()))
+            .map_err(|_| anyhow!("invalid input: unknown {} {:?}", what, item)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => bail!("invalid input: unknown format {:?} (table, json, csv)", value),
+        }
+    }
+}
+
+/// Field to count records by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Outcome,
+    Mode,
+    Digest,
+    Key,
+    ErrorCode,
+    Host,
+    Day,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "outcome" => Ok(GroupBy::Outcome),
+            "mode" => Ok(GroupBy::Mode),
+            "digest" => Ok(GroupBy::Digest),
+            "key" => Ok(GroupBy::Key),
+            "error-code" => Ok(GroupBy::ErrorCode),
+            "host" => Ok(GroupBy::Host),
+            "day" => Ok(GroupBy::Day),
+            _ => bail!("invalid input: cannot group by {:?} (outcome, mode, digest, key, error-code, host, day)", value),
+        }
+    }
+
+    /// Values of this field in `record`; a decision involving several keys
+    /// counts once for each
+    fn values(self, record: &AuditRecord) -> Vec<String> {
+        let name = |value: serde_json::Result<serde_json::Value>| {
+            value.ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+        };
+        match self {
+            GroupBy::Outcome => vec![name(serde_json::to_value(record.outcome))],
+            GroupBy::Mode => vec![name(serde_json::to_value(record.mode))],
+            GroupBy::Digest => vec![record.digest.clone()],
+            GroupBy::Key if record.keyids.is_empty() => vec!["-".to_string()],
+            GroupBy::Key => record.keyids.clone(),
+            GroupBy::ErrorCode => vec![record.error_code.clone().unwrap_or_else(|| "-".to_string())],
+            GroupBy::Host => vec![record.host.clone()],
+            GroupBy::Day => vec![record.timestamp.format("%Y-%m-%d").to_string()],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Group {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Record counts per value, most frequent first
+pub fn summarize(records: &[AuditRecord], group_by: GroupBy) -> Vec<Group> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for record in records {
+        for value in group_by.values(record) {
+            *counts.entry(value).or_default() += 1;
+        }
+    }
+    let mut groups: Vec<Group> = counts.into_iter().map(|(value, count)| Group { value, count }).collect();
+    groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    groups
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(fields: &[&str]) -> String {
+    fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")
+}
+
+/// Render an aligned table; the last column is not padded
+fn table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let last = cells.len() - 1;
+        cells.iter().enumerate()
+            .map(|(i, cell)| if i == last { cell.to_string() } else { format!("{:<width$}", cell, width = widths[i]) })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    let mut out = line(header.to_vec());
+    out.push('\n');
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn render_records(records: &[AuditRecord], format: Format) -> Result<String> {
+    let name = |value: serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    let rows: Vec<Vec<String>> = records.iter()
+        .map(|r| Ok(vec![
+            r.seq.to_string(),
+            r.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            r.host.clone(),
+            name(serde_json::to_value(r.outcome)?),
+            name(serde_json::to_value(r.mode)?),
+            r.digest.clone(),
+            r.bundle.clone(),
+            r.keyids.join(" "),
+            r.log_ids.join(" "),
+            r.error_code.clone().unwrap_or_default(),
+        ]))
+        .collect::<Result<_>>()?;
+    let header = ["SEQ", "TIME", "HOST", "OUTCOME", "MODE", "DIGEST", "BUNDLE", "KEYIDS", "LOGIDS", "ERROR"];
+
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(records)? + "\n",
+        Format::Csv => {
+            let mut out = csv_row(&header.map(|h| h.to_lowercase()).iter().map(String::as_str).collect::<Vec<_>>());
+            out.push('\n');
+            for row in &rows {
+                out.push_str(&csv_row(&row.iter().map(String::as_str).collect::<Vec<_>>()));
+                out.push('\n');
+            }
+            out
+        }
+        // Host, bundle and log IDs are left to the JSON and CSV output
+        Format::Table => {
+            let keep = [0, 1, 3, 4, 5, 7, 9];
+            let rows: Vec<Vec<String>> = rows.iter()
+                .map(|row| keep.iter().map(|&i| row[i].clone()).collect())
+                .collect();
+            table(&keep.map(|i| header[i]), &rows)
+        }
+    })
+}
+
+pub fn render_summary(groups: &[Group], format: Format) -> Result<String> {
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(groups)? + "\n",
+        Format::Csv => {
+            let mut out = "value,count\n".to_string();
+            for group in groups {
+                out.push_str(&csv_row(&[&group.value, &group.count.to_string()]));
+                out.push('\n');
+            }
+            out
+        }
+        Format::Table => {
+            let rows: Vec<Vec<String>> = groups.iter()
+                .map(|g| vec![g.count.to_string(), g.value.clone()])
+                .collect();
+            table(&["COUNT", "VALUE"], &rows)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditTrail;
+    use crate::config::FileSinkConfig;
+    use std::io::Write;
+
+    fn record(digest: &str, mode: VerificationMode, outcome: Outcome, keyid: &str) -> AuditRecord {
+        let trail = AuditTrail { keyids: vec![keyid.to_string()], ..AuditTrail::default() };
+        AuditRecord::new("app, \"edge\"", digest, mode, outcome, trail)
+    }
+
+    #[test]
+    fn test_query_filters_across_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        std::fs::write(
+            dir.path().join("audit.log.19980101T000000Z-0"),
+            "{\"timestamp\":\"2024-01-01T00:00:00Z\",\"outcome\":\"ALLOW\"}\n",
+        ).unwrap();
+        let log = AuditLog::new(&FileSinkConfig { path: path.clone(), max_bytes: 1200, ..FileSinkConfig::default() });
+        log.append(record("sha256:aa", VerificationMode::Strict, Outcome::Allow, "release")).unwrap();
+        log.append(record("sha256:bb", VerificationMode::Strict, Outcome::Reject, "builder")).unwrap();
+        log.append(record("sha256:bb", VerificationMode::Permissive, Outcome::WarnAllowed, "builder")).unwrap();
+        log.append(record("sha256:cc", VerificationMode::Audit, Outcome::AuditAllowed, "release")).unwrap();
+        assert!(!log.rotated_files().is_empty());
+
+        let (all, skipped) = AuditQuery::default().run(&log).unwrap();
+        assert_eq!((all.len(), skipped), (4, 1));
+
+        let query = AuditQuery {
+            outcomes: parse_list("WARN_ALLOWED,AUDIT_ALLOWED", "outcome").unwrap(),
+            ..AuditQuery::default()
+        };
+        let (overridden, _) = query.run(&log).unwrap();
+        assert_eq!(overridden.iter().map(|r| r.digest.as_str()).collect::<Vec<_>>(), ["sha256:bb", "sha256:cc"]);
+
+        let query = AuditQuery { keyid: Some("builder".to_string()), digest: Some("sha256:b".to_string()), ..AuditQuery::default() };
+        let (builder, _) = query.run(&log).unwrap();
+        assert_eq!(summarize(&builder, GroupBy::Mode), [
+            Group { value: "permissive".to_string(), count: 1 },
+            Group { value: "strict".to_string(), count: 1 },
+        ]);
+
+        let query = AuditQuery { since: Some(chrono::Utc::now() + chrono::TimeDelta::hours(1)), ..AuditQuery::default() };
+        assert!(query.run(&log).unwrap().0.is_empty());
+
+        let csv = render_records(&builder, Format::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("seq,time,host,outcome,mode,digest,bundle,"));
+        assert!(lines.next().unwrap().contains(",\"app, \"\"edge\"\"\",builder,"));
+        let table = render_records(&builder, Format::Table).unwrap();
+        assert!(table.starts_with("SEQ  TIME"));
+        assert_eq!(table.lines().count(), 3);
+
+        // Gzipped rotated files are read too
+        let mut gz = flate2::write::GzEncoder::new(
+            std::fs::File::create(dir.path().join("audit.log.19990101T000000Z-0.gz")).unwrap(),
+            flate2::Compression::default(),
+        );
+        let old = AuditRecord { seq: 0, ..record("sha256:dd", VerificationMode::Strict, Outcome::Reject, "old") };
+        writeln!(gz, "{}", serde_json::to_string(&old).unwrap()).unwrap();
+        gz.finish().unwrap();
+        let query = AuditQuery { keyid: Some("old".to_string()), ..AuditQuery::default() };
+        assert_eq!(query.run(&log).unwrap().0.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_time_accepts_durations() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-12-28T12:00:00Z").unwrap().to_utc();
+        assert_eq!(parse_time("7d", now).unwrap().to_rfc3339(), "2024-12-21T12:00:00+00:00");
+        assert_eq!(parse_time("90m", now).unwrap().to_rfc3339(), "2024-12-28T10:30:00+00:00");
+        assert_eq!(parse_time("2024-01-01T00:00:00Z", now).unwrap().to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert!(parse_time("7y", now).is_err());
+        for bad in ["-7d", "", "7\u{00e9}", "\u{00e9}"] {
+            let err = parse_time(bad, now).unwrap_err();
+            assert!(err.to_string().contains("invalid input"), "{:?}", bad);
+        }
+        assert!(parse_list::<Outcome>("ALLOW,DENY", "outcome").is_err());
+    }
+}
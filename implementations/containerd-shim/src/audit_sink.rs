@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Audit sinks (verification-protocol.adoc Section 10.3)
+//
+// Every record fans out to each configured sink. File sinks chain records
+// (audit.rs); syslog and journald receive each record as chained by the
+// first file sink, so what reaches the collector can be matched against the
+// local chain by its hash.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::audit::{AuditLog, AuditRecord, Outcome};
+use crate::config::{AuditConfig, AuditSinkConfig, JournaldSinkConfig, ShimConfig, SyslogSinkConfig};
+use crate::verify::VerificationMode;
+
+pub enum AuditSink {
+    File(AuditLog),
+    Syslog(SyslogSink),
+    Journald(JournaldSink),
+}
+
+/// Syslog severity of a decision (RFC 5424 Section 6.2.1)
+fn severity(outcome: Outcome) -> u8 {
+    match outcome {
+        Outcome::Allow => 6,
+        Outcome::WarnAllowed | Outcome::AuditAllowed => 5,
+        Outcome::Reject | Outcome::CacheInvalid => 4,
+    }
+}
+
+fn outcome_name(outcome: Outcome) -> String {
+    serde_json::to_value(outcome).ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Writes every record to each sink
+pub struct Auditor {
+    sinks: Vec<AuditSink>,
+    fatal_in_strict: bool,
+}
+
+impl Auditor {
+    pub fn from_config(config: &AuditConfig) -> Result<Self> {
+        let mut sinks = config.sinks.iter()
+            .map(|sink| match sink {
+                AuditSinkConfig::File(file) => Ok(AuditSink::File(AuditLog::new(file))),
+                AuditSinkConfig::Syslog(syslog) => SyslogSink::new(syslog).map(AuditSink::Syslog),
+                AuditSinkConfig::Journald(journald) => Ok(AuditSink::Journald(JournaldSink::new(journald))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if sinks.is_empty() {
+            sinks.push(AuditSink::File(AuditLog::new(&AuditLog::default_config())));
+        }
+        Ok(Self { sinks, fatal_in_strict: config.fatal_in_strict })
+    }
+
+    /// Sinks from the shim config. A config that cannot be used falls back
+    /// to the default file so the decision is still recorded.
+    pub fn load() -> Self {
+        let configured = ShimConfig::load().and_then(|config| Self::from_config(&config.audit));
+        configured.unwrap_or_else(|e| {
+            warn!("Auditing to the default file: {:#}", e);
+            Self::from_config(&AuditConfig::default()).expect("the default audit config is valid")
+        })
+    }
+
+    pub fn file_logs(&self) -> impl Iterator<Item = &AuditLog> {
+        self.sinks.iter().filter_map(|sink| match sink {
+            AuditSink::File(log) => Some(log),
+            _ => None,
+        })
+    }
+
+    /// Whether a sink failure must stop a container from starting
+    pub fn is_fatal(&self, mode: VerificationMode) -> bool {
+        self.fatal_in_strict && mode == VerificationMode::Strict
+    }
+
+    /// Write `record` to every sink, returning an error naming each sink
+    /// that failed. Sinks after a failed one are still written.
+    pub fn record(&self, record: AuditRecord) -> Result<()> {
+        let mut failures = Vec::new();
+
+        let mut chained = None;
+        for log in self.file_logs() {
+            match log.append(record.clone()) {
+                Ok(appended) => {
+                    chained.get_or_insert(appended);
+                }
+                Err(e) => failures.push(format!("file {:?}: {:#}", log.path(), e)),
+            }
+        }
+        let record = chained.unwrap_or(record);
+
+        for sink in &self.sinks {
+            let result = match sink {
+                AuditSink::File(_) => continue,
+                AuditSink::Syslog(syslog) => syslog.send(&record)
+                    .map_err(|e| format!("syslog {}: {:#}", syslog.address, e)),
+                AuditSink::Journald(journald) => journald.send(&record)
+                    .map_err(|e| format!("journald {:?}: {:#}", journald.socket, e)),
+            };
+            if let Err(e) = result {
+                failures.push(e);
+            }
+        }
+
+        if !failures.is_empty() {
+            bail!("Audit sinks failed: {}", failures.join("; "));
+        }
+        Ok(())
+    }
+}
+
+/// RFC 5424 over a unix datagram socket or UDP
+pub struct SyslogSink {
+    address: String,
+    facility: u8,
+    app_name: String,
+}
+
+const FACILITIES: &[&str] = &[
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv", "ftp",
+];
+
+impl SyslogSink {
+    pub fn new(config: &SyslogSinkConfig) -> Result<Self> {
+        let facility = FACILITIES.iter().position(|f| *f == config.facility)
+            .or_else(|| {
+                let local: usize = config.facility.strip_prefix("local")?.parse().ok()?;
+                (local < 8).then_some(16 + local)
+            })
+            .ok_or_else(|| anyhow!("Unknown syslog facility {:?}", config.facility))?;
+        Ok(Self {
+            address: config.address.clone(),
+            facility: facility as u8,
+            app_name: config.app_name.clone(),
+        })
+    }
+
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID - MSG` with the JSON
+    /// record as MSG and the outcome as MSGID
+    fn format(&self, record: &AuditRecord) -> Result<String> {
+        let printable = |s: &str| -> String {
+            let s: String = s.chars().filter(|c| c.is_ascii_graphic()).collect();
+            if s.is_empty() { "-".to_string() } else { s }
+        };
+        Ok(format!(
+            "<{}>1 {} {} {} {} {} - {}",
+            self.facility * 8 + severity(record.outcome),
+            record.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            printable(&record.host),
+            printable(&self.app_name),
+            std::process::id(),
+            outcome_name(record.outcome),
+            serde_json::to_string(record)?
+        ))
+    }
+
+    fn send(&self, record: &AuditRecord) -> Result<()> {
+        let message = self.format(record)?;
+        match self.address.strip_prefix("udp://") {
+            Some(address) => {
+                let target = address.to_socket_addrs()?.next()
+                    .context("Syslog address did not resolve")?;
+                let bind = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                UdpSocket::bind(bind)?.send_to(message.as_bytes(), target)?;
+            }
+            None => {
+                UnixDatagram::unbound()?.send_to(message.as_bytes(), &self.address)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// systemd-journald native protocol over its datagram socket
+pub struct JournaldSink {
+    socket: PathBuf,
+    identifier: String,
+}
+
+impl JournaldSink {
+    pub fn new(config: &JournaldSinkConfig) -> Self {
+        Self { socket: config.socket.clone(), identifier: config.identifier.clone() }
+    }
+
+    /// Append one field. Values containing a newline use the binary form:
+    /// name, newline, little-endian u64 length, value, newline.
+    fn field(payload: &mut Vec<u8>, name: &str, value: &str) {
+        payload.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            payload.push(b'\n');
+            payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            payload.push(b'=');
+        }
+        payload.extend_from_slice(value.as_bytes());
+        payload.push(b'\n');
+    }
+
+    fn payload(&self, record: &AuditRecord) -> Result<Vec<u8>> {
+        let outcome = outcome_name(record.outcome);
+        let subject = if record.digest.is_empty() { &record.bundle } else { &record.digest };
+        let mut message = format!("{} {} ({:?} mode)", outcome, subject, record.mode);
+        if let Some(error) = &record.error {
+            message.push_str(": ");
+            message.push_str(error);
+        }
+
+        let mut payload = Vec::new();
+        Self::field(&mut payload, "MESSAGE", &message);
+        Self::field(&mut payload, "PRIORITY", &severity(record.outcome).to_string());
+        Self::field(&mut payload, "SYSLOG_IDENTIFIER", &self.identifier);
+        Self::field(&mut payload, "VERIFIED_CONTAINER_OUTCOME", &outcome);
+        Self::field(&mut payload, "VERIFIED_CONTAINER_BUNDLE", &record.bundle);
+        Self::field(&mut payload, "VERIFIED_CONTAINER_DIGEST", &record.digest);
+        if let Some(code) = &record.error_code {
+            Self::field(&mut payload, "VERIFIED_CONTAINER_ERROR_CODE", code);
+        }
+        Self::field(&mut payload, "VERIFIED_CONTAINER_HASH", &record.hash);
+        Self::field(&mut payload, "VERIFIED_CONTAINER_RECORD", &serde_json::to_string(record)?);
+        Ok(payload)
+    }
+
+    fn send(&self, record: &AuditRecord) -> Result<()> {
+        UnixDatagram::unbound()?.send_to(&self.payload(record)?, &self.socket)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditTrail;
+    use crate::config::FileSinkConfig;
+
+    #[test]
+    fn test_records_fan_out_to_every_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let journal_path = dir.path().join("journal.socket");
+        let journal = UnixDatagram::bind(&journal_path).unwrap();
+
+        let config = AuditConfig {
+            sinks: vec![
+                AuditSinkConfig::Syslog(SyslogSinkConfig {
+                    address: format!("udp://{}", udp.local_addr().unwrap()),
+                    facility: "local3".to_string(),
+                    ..SyslogSinkConfig::default()
+                }),
+                AuditSinkConfig::File(FileSinkConfig { path: dir.path().join("audit.log"), ..FileSinkConfig::default() }),
+                AuditSinkConfig::Journald(JournaldSinkConfig { socket: journal_path, ..JournaldSinkConfig::default() }),
+                AuditSinkConfig::Journald(JournaldSinkConfig {
+                    socket: dir.path().join("missing.socket"),
+                    ..JournaldSinkConfig::default()
+                }),
+            ],
+            fatal_in_strict: true,
+        };
+        let auditor = Auditor::from_config(&config).unwrap();
+        let record = AuditRecord::new("app", "sha256:aa", VerificationMode::Permissive, Outcome::WarnAllowed, AuditTrail::default())
+            .with_error(&anyhow!("REVOKED: builder was revoked\nby security"));
+
+        // The missing journald socket fails, the other sinks still get the record
+        let err = auditor.record(record).unwrap_err();
+        assert!(format!("{:#}", err).contains("missing.socket"));
+        assert!(auditor.is_fatal(VerificationMode::Strict));
+        assert!(!auditor.is_fatal(VerificationMode::Permissive));
+
+        let summary = auditor.file_logs().next().unwrap().verify(None).unwrap().unwrap();
+
+        let mut buf = [0u8; 8192];
+        let n = udp.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..n]).unwrap();
+        // local3 (19) * 8 + notice (5)
+        assert!(message.starts_with("<157>1 "), "{}", message);
+        assert!(message.contains(" verified-container "));
+        assert!(message.contains(" WARN_ALLOWED - {"));
+        assert!(message.contains(&summary.head.hash));
+
+        let n = journal.recv(&mut buf).unwrap();
+        let payload = &buf[..n];
+        let text = String::from_utf8_lossy(payload);
+        assert!(text.contains("PRIORITY=5\n"));
+        assert!(text.contains("VERIFIED_CONTAINER_ERROR_CODE=REVOKED\n"));
+        assert!(text.contains(&format!("VERIFIED_CONTAINER_HASH={}\n", summary.head.hash)));
+        // The multi-line message uses the length-prefixed form
+        let start = text.find("MESSAGE\n").unwrap() + "MESSAGE\n".len();
+        let len = u64::from_le_bytes(payload[start..start + 8].try_into().unwrap()) as usize;
+        assert!(std::str::from_utf8(&payload[start + 8..start + 8 + len]).unwrap().ends_with("by security"));
+    }
+
+    #[test]
+    fn test_unknown_facility_is_rejected() {
+        let config = SyslogSinkConfig { facility: "local8".to_string(), ..SyslogSinkConfig::default() };
+        assert!(SyslogSink::new(&config).is_err());
+        let config = SyslogSinkConfig { facility: "daemon".to_string(), ..SyslogSinkConfig::default() };
+        assert_eq!(SyslogSink::new(&config).unwrap().facility, 3);
+    }
+}
@@ -7,6 +7,7 @@
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use tar::Archive;
+use tracing::instrument;
 use flate2::read::GzDecoder;
 
 /// CTP Bundle structure (per runtime-integration.adoc Section 5)
@@ -35,6 +36,8 @@ pub struct ManifestMetadata {
 
 impl CtpBundle {
     /// Load a .ctp bundle from the filesystem
+    #[instrument(name = "load_bundle", skip_all, err(level = "debug"),
+        fields(path = %path.display(), digest = tracing::field::Empty))]
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
             bail!("Bundle not found: {:?}", path);
@@ -43,7 +46,7 @@ pub fn load(path: &Path) -> Result<Self> {
         // Create temporary directory for extraction
         let temp_dir = tempfile::tempdir()
             .context("Failed to create temp directory")?
-            .into_path();
+            .keep();
 
         // Extract tarball
         Self::extract_tarball(path, &temp_dir)?;
@@ -55,6 +58,7 @@ pub fn load(path: &Path) -> Result<Self> {
 
         let manifest: Manifest = toml::from_str(&manifest_content)
             .context("Failed to parse manifest.toml")?;
+        tracing::Span::current().record("digest", manifest.image_digest.as_str());
 
         Ok(Self {
             manifest,
@@ -93,10 +97,11 @@ fn is_gzipped(path: &Path) -> Result<bool> {
     }
 
     /// Extract OCI layout to a new temporary directory
+    #[instrument(skip_all, err(level = "debug"))]
     pub fn extract_oci_layout(&self) -> Result<PathBuf> {
         let oci_dest = tempfile::tempdir()
             .context("Failed to create OCI temp directory")?
-            .into_path();
+            .keep();
 
         Self::copy_dir_recursive(&self.oci_layout_path, &oci_dest)?;
 
@@ -128,11 +133,13 @@ pub fn attestation_bundle_path(&self) -> PathBuf {
     }
 
     /// Get SBOM path
+    #[allow(dead_code)]
     pub fn sbom_path(&self) -> PathBuf {
         self.attestations_path.join("sbom.json")
     }
 
     /// Get provenance path
+    #[allow(dead_code)]
     pub fn provenance_path(&self) -> PathBuf {
         self.attestations_path.join("provenance.json")
     }
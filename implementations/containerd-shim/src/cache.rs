@@ -0,0 +1,805 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Verification result cache (verification-protocol.adoc Section 8)
+//
+// Each cached decision is a structured record of what was verified, bound to
+// the image digest and trust store version and authenticated with
+// HMAC-SHA256 under a host-local key. Write access to the cache directory
+// alone is therefore not enough to skip verification: a record that fails
+// authentication is treated as a miss and reported to the caller for audit.
+//
+// `index.json` lists the records with what they depend on, so a trust store
+// update, a revocation or `cache flush` can invalidate exactly the affected
+// records. The index is not authenticated: editing it can only cause
+// records to be dropped, never accepted.
+//
+// ALLOWs are reused for `cache.ttl_secs`. REJECTs are only cached when
+// `cache.negative_ttl_secs` is set, so that an image that keeps failing is
+// not re-verified on every retry. Expired records are collected whenever a
+// record is stored, and the records closest to expiry are evicted while the
+// cache exceeds `max_entries` or `max_bytes`.
+
+use anyhow::{Context, Result, anyhow, bail};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use tracing::{info, warn};
+
+use crate::canonical;
+use crate::checkpoint::is_safe_file_name;
+use crate::config::CacheConfig;
+use crate::dsse::base64_bytes;
+use crate::revocation::RevocationSet;
+
+/// Current cache record format
+const RECORD_VERSION: u32 = 1;
+
+/// The decision a record caches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Decision {
+    #[default]
+    Allow,
+    Reject,
+}
+
+/// A cached verification decision
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CacheRecord {
+    pub version: u32,
+    pub digest: String,
+    #[serde(rename = "trustStoreVersion")]
+    pub trust_store_version: String,
+    #[serde(default)]
+    pub decision: Decision,
+    /// Why verification failed, for a cached REJECT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Key IDs whose signatures were verified
+    pub keyids: Vec<String>,
+    /// Logs that proved inclusion
+    #[serde(rename = "logIds")]
+    pub log_ids: Vec<String>,
+    #[serde(rename = "attestationDigests")]
+    pub attestation_digests: Vec<String>,
+    #[serde(rename = "verifiedAt")]
+    pub verified_at: chrono::DateTime<chrono::Utc>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CacheRecord {
+    fn new(digest: &str, trust_store_version: &str, decision: Decision, ttl: chrono::TimeDelta) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            version: RECORD_VERSION,
+            digest: digest.to_string(),
+            trust_store_version: trust_store_version.to_string(),
+            decision,
+            error: None,
+            keyids: vec![],
+            log_ids: vec![],
+            attestation_digests: vec![],
+            verified_at: now,
+            expires_at: now.checked_add_signed(ttl).unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC),
+        }
+    }
+
+    /// A record for a verification that just succeeded, reused for `ttl`
+    pub fn allow(
+        digest: &str,
+        trust_store_version: &str,
+        keyids: Vec<String>,
+        log_ids: Vec<String>,
+        attestation_digests: Vec<String>,
+        ttl: chrono::TimeDelta,
+    ) -> Self {
+        Self {
+            keyids,
+            log_ids,
+            attestation_digests,
+            ..Self::new(digest, trust_store_version, Decision::Allow, ttl)
+        }
+    }
+
+    /// A record for a verification that failed with `error`, reused for `ttl`
+    pub fn reject(digest: &str, trust_store_version: &str, error: &str, ttl: chrono::TimeDelta) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::new(digest, trust_store_version, Decision::Reject, ttl)
+        }
+    }
+
+    /// Key IDs and attestation digests whose revocation invalidates the record
+    pub fn depends_on(&self) -> impl Iterator<Item = &str> {
+        self.keyids.iter().chain(&self.attestation_digests).map(String::as_str)
+    }
+}
+
+/// On-disk form: the record and its MAC over the canonical record JSON
+#[derive(Debug, Deserialize, Serialize)]
+struct SignedRecord {
+    record: CacheRecord,
+    #[serde(with = "base64_bytes")]
+    mac: Vec<u8>,
+}
+
+/// `index.json`: the trust store version records were written under, and
+/// each record's dependencies keyed by file name
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CacheIndex {
+    #[serde(rename = "trustStoreVersion")]
+    trust_store_version: Option<String>,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/// A cached record as listed in the index
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheEntry {
+    pub digest: String,
+    #[serde(default)]
+    pub decision: Decision,
+    #[serde(rename = "dependsOn")]
+    pub depends_on: Vec<String>,
+    #[serde(rename = "verifiedAt", default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// Size of the record file
+    #[serde(default)]
+    pub bytes: u64,
+}
+
+/// Summary for `cache stats`
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    #[serde(rename = "trustStoreVersion")]
+    pub trust_store_version: Option<String>,
+    pub entries: usize,
+    pub allowed: usize,
+    pub rejected: usize,
+    /// Entries past their expiry that have not been collected yet
+    pub expired: usize,
+    pub bytes: u64,
+    #[serde(rename = "maxEntries")]
+    pub max_entries: usize,
+    #[serde(rename = "maxBytes")]
+    pub max_bytes: u64,
+}
+
+/// Records removed by garbage collection
+#[derive(Debug, Default, PartialEq)]
+pub struct GcReport {
+    pub expired: usize,
+    /// Unexpired records evicted to meet the size bounds
+    pub evicted: usize,
+}
+
+/// Outcome of a cache lookup
+#[derive(Debug)]
+pub enum Lookup {
+    Hit(CacheRecord),
+    Miss,
+    /// A record exists but could not be authenticated or does not match;
+    /// it has been removed and should be audited
+    Invalid(anyhow::Error),
+}
+
+pub struct VerificationCache {
+    dir: PathBuf,
+    /// None when the MAC key is unavailable; caching is then disabled
+    key: Option<Vec<u8>>,
+    policy: CacheConfig,
+}
+
+impl VerificationCache {
+    /// Cache in `dir`, authenticated with the key at `key_path`. The key is
+    /// created on first use; if it cannot be read or created, every lookup
+    /// misses and nothing is stored.
+    pub fn open(dir: impl Into<PathBuf>, key_path: &Path, policy: &CacheConfig) -> Self {
+        let key = load_or_create_key(key_path)
+            .inspect_err(|e| warn!("Verification cache disabled: {:#}", e))
+            .ok();
+        Self { dir: dir.into(), key, policy: policy.clone() }
+    }
+
+    /// Cache at CACHE_DIR (default /var/cache/verified-container) with the
+    /// key at CACHE_KEY_PATH (default /var/lib/verified-container/cache.key)
+    pub fn from_env(policy: &CacheConfig) -> Self {
+        let dir = std::env::var("CACHE_DIR")
+            .unwrap_or_else(|_| "/var/cache/verified-container".to_string());
+        let key_path = std::env::var("CACHE_KEY_PATH")
+            .unwrap_or_else(|_| "/var/lib/verified-container/cache.key".to_string());
+        Self::open(dir, Path::new(&key_path), policy)
+    }
+
+    fn file_name(digest: &str, trust_store_version: &str) -> Option<String> {
+        let name = format!("{}-{}.cache", digest.replace(':', "-"), trust_store_version);
+        is_safe_file_name(&name).then_some(name)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    fn load_index(&self) -> CacheIndex {
+        match std::fs::read(self.index_path()) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt cache index: {}", e);
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        }
+    }
+
+    fn save_index(&self, index: &CacheIndex) -> Result<()> {
+        self.write_atomic(&self.index_path(), &serde_json::to_vec_pretty(index)?)
+            .context("Failed to write cache index")
+    }
+
+    /// Write through a temporary file in the cache directory and rename it
+    /// into place, so readers never see a partial file
+    fn write_atomic(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.dir)?;
+        tmp.write_all(bytes)?;
+        tmp.as_file().sync_all()?;
+        tmp.persist(path)?;
+        Ok(())
+    }
+
+    /// Read-modify-write the index under an exclusive lock on `index.lock`,
+    /// so that shims updating the cache concurrently do not lose entries
+    fn update_index<T>(&self, update: impl FnOnce(&mut CacheIndex) -> T) -> Result<T> {
+        std::fs::create_dir_all(&self.dir)?;
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join("index.lock"))
+            .context("Failed to open cache index lock")?;
+        lock.lock().context("Failed to lock cache index")?;
+
+        let mut index = self.load_index();
+        let result = update(&mut index);
+        self.save_index(&index)?;
+        Ok(result)
+    }
+
+    /// Delete a record file and its index entry
+    fn forget(&self, name: &str) {
+        let result = self.update_index(|index| {
+            std::fs::remove_file(self.dir.join(name)).ok();
+            index.entries.remove(name);
+        });
+        if let Err(e) = result {
+            warn!("{:#}", e);
+        }
+    }
+
+    /// Wait until no other task or process is verifying `digest` under this
+    /// trust store version. The holder of the returned lock verifies and
+    /// caches the result; later holders then find it in the cache.
+    pub async fn lock_digest(&self, digest: &str, trust_store_version: &str) -> VerificationLock {
+        let Some(name) = Self::file_name(digest, trust_store_version).filter(|_| self.key.is_some()) else {
+            return VerificationLock { _file: None, _local: None };
+        };
+
+        let mutex = LOCAL_LOCKS.lock().unwrap().entry(name.clone()).or_default().clone();
+        let guard = mutex.clone().lock_owned().await;
+        let local = Some(LocalLock { name: name.clone(), mutex, _guard: guard });
+
+        let dir = self.dir.join("locks");
+        let file = tokio::task::spawn_blocking(move || -> Result<std::fs::File> {
+            std::fs::create_dir_all(&dir)?;
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(dir.join(format!("{}.lock", name)))?;
+            file.lock()?;
+            Ok(file)
+        }).await;
+        match file {
+            Ok(Ok(file)) => VerificationLock { _file: Some(file), _local: local },
+            Ok(Err(e)) => {
+                warn!("Verifying without the cache lock for {}: {:#}", digest, e);
+                VerificationLock { _file: None, _local: local }
+            }
+            Err(e) => {
+                warn!("Verifying without the cache lock for {}: {}", digest, e);
+                VerificationLock { _file: None, _local: local }
+            }
+        }
+    }
+
+    fn mac(&self, key: &[u8], record: &CacheRecord) -> Result<Hmac<Sha256>> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key)?;
+        mac.update(canonical::canonicalize(&serde_json::to_value(record)?).as_bytes());
+        Ok(mac)
+    }
+
+    /// Authenticate and return a fresh record for `digest` under the given
+    /// trust store version
+    pub fn lookup(&self, digest: &str, trust_store_version: &str) -> Lookup {
+        let (Some(key), Some(name)) = (&self.key, Self::file_name(digest, trust_store_version)) else {
+            return Lookup::Miss;
+        };
+        let path = self.dir.join(&name);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Lookup::Miss,
+            Err(e) => return Lookup::Invalid(anyhow!("Failed to read cache record {:?}: {}", path, e)),
+        };
+
+        match self.authenticate(key, &bytes, digest, trust_store_version) {
+            Ok(record) if record.expires_at > chrono::Utc::now() => Lookup::Hit(record),
+            Ok(_) => {
+                self.forget(&name);
+                Lookup::Miss
+            }
+            Err(e) => {
+                self.forget(&name);
+                Lookup::Invalid(e.context(format!("Rejected cache record {:?}", path)))
+            }
+        }
+    }
+
+    fn authenticate(&self, key: &[u8], bytes: &[u8], digest: &str, trust_store_version: &str) -> Result<CacheRecord> {
+        let signed: SignedRecord = serde_json::from_slice(bytes)
+            .context("Cache record is malformed")?;
+        self.mac(key, &signed.record)?
+            .verify_slice(&signed.mac)
+            .map_err(|_| anyhow!("Cache record MAC verification failed"))?;
+
+        let record = signed.record;
+        if record.version != RECORD_VERSION {
+            bail!("Unsupported cache record version {}", record.version);
+        }
+        if record.digest != digest || record.trust_store_version != trust_store_version {
+            bail!(
+                "Cache record is for {} under trust store {}, not {} under {}",
+                record.digest, record.trust_store_version, digest, trust_store_version
+            );
+        }
+        Ok(record)
+    }
+
+    /// Store an authenticated record
+    pub fn store(&self, record: &CacheRecord) -> Result<()> {
+        let Some(key) = &self.key else {
+            return Ok(());
+        };
+        let Some(name) = Self::file_name(&record.digest, &record.trust_store_version) else {
+            warn!("Not caching result for {}: digest is not a safe file name", record.digest);
+            return Ok(());
+        };
+
+        let signed = SignedRecord {
+            record: record.clone(),
+            mac: self.mac(key, record)?.finalize().into_bytes().to_vec(),
+        };
+        let bytes = serde_json::to_vec(&signed)?;
+        self.write_atomic(&self.dir.join(&name), &bytes)
+            .context(format!("Failed to write cache record {}", name))?;
+
+        let report = self.update_index(|index| {
+            index.entries.insert(name, CacheEntry {
+                digest: record.digest.clone(),
+                decision: record.decision,
+                depends_on: record.depends_on().map(str::to_string).collect(),
+                verified_at: Some(record.verified_at),
+                expires_at: record.expires_at,
+                bytes: bytes.len() as u64,
+            });
+            self.collect_garbage(index, chrono::Utc::now())
+        })?;
+
+        info!("Cached verification result for {}", record.digest);
+        if report != GcReport::default() {
+            info!("Collected {} expired and evicted {} cached results", report.expired, report.evicted);
+        }
+        Ok(())
+    }
+
+    /// Remove expired records, then evict the records closest to expiry
+    /// until the cache is within its size bounds
+    fn collect_garbage(&self, index: &mut CacheIndex, now: chrono::DateTime<chrono::Utc>) -> GcReport {
+        let mut report = GcReport::default();
+        index.entries.retain(|name, entry| {
+            if entry.expires_at > now {
+                return true;
+            }
+            std::fs::remove_file(self.dir.join(name)).ok();
+            report.expired += 1;
+            false
+        });
+
+        let mut entries = index.entries.len();
+        let mut bytes: u64 = index.entries.values().map(|e| e.bytes).sum();
+        let mut by_expiry: Vec<(chrono::DateTime<chrono::Utc>, String)> = index.entries.iter()
+            .map(|(name, entry)| (entry.expires_at, name.clone()))
+            .collect();
+        by_expiry.sort();
+        for (_, name) in by_expiry {
+            if entries <= self.policy.max_entries && bytes <= self.policy.max_bytes {
+                break;
+            }
+            if let Some(entry) = index.entries.remove(&name) {
+                std::fs::remove_file(self.dir.join(&name)).ok();
+                entries -= 1;
+                bytes -= entry.bytes;
+                report.evicted += 1;
+            }
+        }
+        report
+    }
+
+    /// Run garbage collection now
+    pub fn gc(&self) -> Result<GcReport> {
+        self.update_index(|index| self.collect_garbage(index, chrono::Utc::now()))
+    }
+
+    /// Indexed records, ordered by file name
+    pub fn list(&self) -> Vec<CacheEntry> {
+        self.load_index().entries.into_values().collect()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let index = self.load_index();
+        let now = chrono::Utc::now();
+        let count = |decision| index.entries.values().filter(|e| e.decision == decision).count();
+        CacheStats {
+            trust_store_version: index.trust_store_version.clone(),
+            entries: index.entries.len(),
+            allowed: count(Decision::Allow),
+            rejected: count(Decision::Reject),
+            expired: index.entries.values().filter(|e| e.expires_at <= now).count(),
+            bytes: index.entries.values().map(|e| e.bytes).sum(),
+            max_entries: self.policy.max_entries,
+            max_bytes: self.policy.max_bytes,
+        }
+    }
+
+    pub fn remove(&self, digest: &str, trust_store_version: &str) {
+        if let Some(name) = Self::file_name(digest, trust_store_version) {
+            self.forget(&name);
+        }
+    }
+
+    /// Flush every record if the trust store changed since they were written
+    /// (verification-protocol.adoc Section 8)
+    pub fn sync_trust_store(&self, trust_store_version: &str) -> Result<()> {
+        if self.load_index().trust_store_version.as_deref() == Some(trust_store_version) {
+            return Ok(());
+        }
+
+        // Re-checked under the lock: another shim may have synced meanwhile
+        let flushed = self.update_index(|index| {
+            if index.trust_store_version.as_deref() == Some(trust_store_version) {
+                return 0;
+            }
+            index.trust_store_version = Some(trust_store_version.to_string());
+            self.remove_records(index, None)
+        })?;
+        if flushed > 0 {
+            info!("Trust store changed, invalidated {} cached results", flushed);
+        }
+        Ok(())
+    }
+
+    /// Remove the records for `digest`, or all records, returning how many
+    /// were removed
+    pub fn flush(&self, digest: Option<&str>) -> Result<usize> {
+        self.update_index(|index| self.remove_records(index, digest))
+    }
+
+    fn remove_records(&self, index: &mut CacheIndex, digest: Option<&str>) -> usize {
+        let names: Vec<String> = match digest {
+            Some(digest) => index.entries.iter()
+                .filter(|(_, entry)| entry.digest == digest)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            // Unindexed records too, e.g. from before the index existed
+            None => match std::fs::read_dir(&self.dir) {
+                Ok(entries) => entries.flatten()
+                    .filter_map(|e| e.file_name().into_string().ok())
+                    .filter(|name| name.ends_with(".cache"))
+                    .collect(),
+                Err(_) => vec![],
+            },
+        };
+
+        let mut removed = 0;
+        for name in &names {
+            if std::fs::remove_file(self.dir.join(name)).is_ok() {
+                removed += 1;
+            }
+            index.entries.remove(name);
+        }
+        removed
+    }
+
+    /// Remove every record that depends on a revoked key or attestation
+    pub fn purge_revoked(&self, revocations: &RevocationSet) {
+        let result = self.update_index(|index| {
+            index.entries.retain(|name, entry| {
+                let Err(e) = revocations.check(entry.depends_on.iter().map(String::as_str)) else {
+                    return true;
+                };
+                info!("Purging cached result for {}: {:#}", entry.digest, e);
+                std::fs::remove_file(self.dir.join(name)).ok();
+                false
+            });
+        });
+        if let Err(e) = result {
+            warn!("{:#}", e);
+        }
+    }
+}
+
+/// Holds the in-process and cross-process locks on one digest until dropped
+pub struct VerificationLock {
+    /// Exclusively locked `locks/<record>.lock`; closing it releases the lock
+    _file: Option<std::fs::File>,
+    _local: Option<LocalLock>,
+}
+
+struct LocalLock {
+    name: String,
+    mutex: Arc<tokio::sync::Mutex<()>>,
+    _guard: tokio::sync::OwnedMutexGuard<()>,
+}
+
+impl Drop for LocalLock {
+    fn drop(&mut self) {
+        // Only the map, this lock and its guard still refer to the mutex:
+        // nobody is waiting, so the entry can go
+        let mut locks = LOCAL_LOCKS.lock().unwrap();
+        if Arc::strong_count(&self.mutex) <= 3 {
+            locks.remove(&self.name);
+        }
+    }
+}
+
+/// Per-record mutexes coalescing verifications within this process; the
+/// file lock alone would block a runtime thread while waiting
+static LOCAL_LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = LazyLock::new(Default::default);
+
+/// Read the host-local MAC key, creating a random one (mode 0600) if absent
+fn load_or_create_key(path: &Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(key) if key.len() >= 32 => return Ok(key),
+        Ok(_) => bail!("Cache key {:?} is shorter than 32 bytes", path),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).context(format!("Failed to read cache key {:?}", path));
+        }
+        Err(_) => {}
+    }
+
+    let mut key = vec![0u8; 32];
+    getrandom::getrandom(&mut key).map_err(|e| anyhow!("Failed to generate cache key: {}", e))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    match options.open(path) {
+        Ok(mut file) => {
+            file.write_all(&key)?;
+            file.sync_all()?;
+            info!("Created verification cache key {:?}", path);
+            Ok(key)
+        }
+        // Another shim created it first
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(std::fs::read(path)?),
+        Err(e) => Err(e).context(format!("Failed to create cache key {:?}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(digest: &str) -> CacheRecord {
+        CacheRecord::allow(
+            digest,
+            "v1",
+            vec!["builder".to_string()],
+            vec!["log-eu".to_string()],
+            vec!["sha256:aa".to_string()],
+            chrono::TimeDelta::hours(1),
+        )
+    }
+
+    #[test]
+    fn test_records_are_authenticated() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VerificationCache::open(dir.path().join("cache"), &dir.path().join("cache.key"), &CacheConfig::default());
+        let digest = format!("sha256:{}", "ab".repeat(32));
+
+        assert!(matches!(cache.lookup(&digest, "v1"), Lookup::Miss));
+        cache.store(&record(&digest)).unwrap();
+        assert!(matches!(cache.lookup(&digest, "v1"), Lookup::Hit(r) if r.digest == digest));
+        assert!(matches!(cache.lookup(&digest, "v2"), Lookup::Miss));
+
+        // The old marker file and a forged record are both rejected and removed
+        let path = cache.dir.join(VerificationCache::file_name(&digest, "v1").unwrap());
+        std::fs::write(&path, "VERIFIED").unwrap();
+        assert!(matches!(cache.lookup(&digest, "v1"), Lookup::Invalid(_)));
+        assert!(!path.exists());
+
+        let mut forged: serde_json::Value = serde_json::to_value(SignedRecord {
+            record: record(&digest),
+            mac: vec![0; 32],
+        }).unwrap();
+        forged["record"]["expiresAt"] = serde_json::json!("2099-01-01T00:00:00Z");
+        std::fs::write(&path, forged.to_string()).unwrap();
+        assert!(matches!(cache.lookup(&digest, "v1"), Lookup::Invalid(_)));
+
+        // A valid record copied to another digest does not verify that digest
+        let other = format!("sha256:{}", "cd".repeat(32));
+        cache.store(&record(&digest)).unwrap();
+        std::fs::copy(&path, cache.dir.join(VerificationCache::file_name(&other, "v1").unwrap())).unwrap();
+        assert!(matches!(cache.lookup(&other, "v1"), Lookup::Invalid(_)));
+
+        // Records written under another host's key are not accepted
+        let elsewhere = VerificationCache::open(dir.path().join("cache"), &dir.path().join("other.key"), &CacheConfig::default());
+        cache.store(&record(&digest)).unwrap();
+        assert!(matches!(elsewhere.lookup(&digest, "v1"), Lookup::Invalid(_)));
+    }
+
+    #[test]
+    fn test_revocation_purges_dependent_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VerificationCache::open(dir.path(), &dir.path().join("cache.key"), &CacheConfig::default());
+        let mut released = record("sha256:bb");
+        released.keyids = vec!["release".to_string()];
+        released.attestation_digests = vec!["sha256:cc".to_string()];
+        cache.store(&record("sha256:aa")).unwrap();
+        cache.store(&released).unwrap();
+
+        let mut revocations = RevocationSet::default();
+        revocations.add(crate::tlog::RevocationBody {
+            revoked_digest: "builder".to_string(),
+            reason: "key_compromise".to_string(),
+            revoked_by: "security".to_string(),
+        });
+        cache.purge_revoked(&revocations);
+        assert!(matches!(cache.lookup("sha256:aa", "v1"), Lookup::Miss));
+        assert!(matches!(cache.lookup("sha256:bb", "v1"), Lookup::Hit(_)));
+    }
+
+    #[test]
+    fn test_trust_store_change_and_flush_invalidate_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VerificationCache::open(dir.path(), &dir.path().join("cache.key"), &CacheConfig::default());
+        cache.sync_trust_store("v1").unwrap();
+        cache.store(&record("sha256:aa")).unwrap();
+        cache.store(&record("sha256:bb")).unwrap();
+
+        // Unchanged trust store keeps records
+        cache.sync_trust_store("v1").unwrap();
+        assert!(matches!(cache.lookup("sha256:aa", "v1"), Lookup::Hit(_)));
+
+        assert_eq!(cache.flush(Some("sha256:aa")).unwrap(), 1);
+        assert!(matches!(cache.lookup("sha256:aa", "v1"), Lookup::Miss));
+        assert!(matches!(cache.lookup("sha256:bb", "v1"), Lookup::Hit(_)));
+
+        cache.sync_trust_store("v2").unwrap();
+        assert!(!dir.path().join("sha256-bb-v1.cache").exists());
+        assert!(cache.load_index().entries.is_empty());
+        assert_eq!(cache.flush(None).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_expired_and_unsafe_records_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VerificationCache::open(dir.path(), &dir.path().join("cache.key"), &CacheConfig::default());
+
+        let mut expired = record("sha256:aa");
+        expired.expires_at = expired.verified_at - chrono::TimeDelta::seconds(1);
+        cache.store(&expired).unwrap();
+        assert!(matches!(cache.lookup("sha256:aa", "v1"), Lookup::Miss));
+
+        cache.store(&record("../escape")).unwrap();
+        assert!(!dir.path().join("../escape-v1.cache").exists());
+        assert!(matches!(cache.lookup("../escape", "v1"), Lookup::Miss));
+    }
+
+    #[test]
+    fn test_rejects_are_cached_and_size_bounds_evict() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = CacheConfig { max_entries: 2, ..CacheConfig::default() };
+        let cache = VerificationCache::open(dir.path(), &dir.path().join("cache.key"), &policy);
+
+        let rejected = CacheRecord::reject("sha256:aa", "v1", "INVALID_SIGNATURE: bad", chrono::TimeDelta::seconds(30));
+        cache.store(&rejected).unwrap();
+        assert!(matches!(cache.lookup("sha256:aa", "v1"),
+            Lookup::Hit(r) if r.decision == Decision::Reject && r.error.as_deref() == Some("INVALID_SIGNATURE: bad")));
+
+        // The REJECT expires first, so it is evicted once a third record arrives
+        cache.store(&record("sha256:bb")).unwrap();
+        cache.store(&record("sha256:cc")).unwrap();
+        assert!(matches!(cache.lookup("sha256:aa", "v1"), Lookup::Miss));
+        assert!(!dir.path().join("sha256-aa-v1.cache").exists());
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.allowed, stats.rejected), (2, 2, 0));
+        assert_eq!(stats.bytes, cache.list().iter().map(|e| e.bytes).sum::<u64>());
+
+        // An expired entry left behind, e.g. by a crashed shim
+        let mut index = cache.load_index();
+        index.entries.insert("sha256-dd-v1.cache".to_string(), CacheEntry {
+            digest: "sha256:dd".to_string(),
+            decision: Decision::Allow,
+            depends_on: vec![],
+            verified_at: None,
+            expires_at: chrono::Utc::now() - chrono::TimeDelta::seconds(1),
+            bytes: 0,
+        });
+        cache.save_index(&index).unwrap();
+        assert_eq!(cache.stats().expired, 1);
+        assert_eq!(cache.gc().unwrap(), GcReport { expired: 1, evicted: 0 });
+        assert_eq!(cache.list().len(), 2);
+
+        let tight = VerificationCache::open(dir.path(), &dir.path().join("cache.key"),
+            &CacheConfig { max_bytes: 1, ..CacheConfig::default() });
+        assert_eq!(tight.gc().unwrap(), GcReport { expired: 0, evicted: 2 });
+        assert!(tight.list().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_stores_keep_every_index_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = VerificationCache::open(dir.path(), &dir.path().join("cache.key"), &CacheConfig::default());
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let cache = &cache;
+                scope.spawn(move || cache.store(&record(&format!("sha256:{:02x}", i))).unwrap());
+            }
+        });
+        assert_eq!(cache.list().len(), 8);
+        assert!(matches!(cache.lookup("sha256:07", "v1"), Lookup::Hit(_)));
+    }
+
+    #[tokio::test]
+    async fn test_verifications_of_a_digest_are_serialized() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(VerificationCache::open(dir.path(), &dir.path().join("cache.key"), &CacheConfig::default()));
+
+        // Another process holds the lock
+        let path = dir.path().join("locks/sha256-aa-v1.cache.lock");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let other = std::fs::File::create(&path).unwrap();
+        other.lock().unwrap();
+
+        let waiting = {
+            let cache = cache.clone();
+            tokio::spawn(async move { cache.lock_digest("sha256:aa", "v1").await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+        // Other digests are not held up
+        drop(cache.lock_digest("sha256:bb", "v1").await);
+
+        drop(other);
+        let held = waiting.await.unwrap();
+
+        // Tasks in this process queue behind the holder
+        let queued = {
+            let cache = cache.clone();
+            tokio::spawn(async move { cache.lock_digest("sha256:aa", "v1").await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!queued.is_finished());
+        drop(held);
+        drop(queued.await.unwrap());
+        assert!(LOCAL_LOCKS.lock().unwrap().get("sha256-aa-v1.cache").is_none());
+    }
+}
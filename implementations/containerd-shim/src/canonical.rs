@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// JSON canonicalization following canonicalization.adoc
+
+use serde_json::Value;
+
+/// Canonicalize a parsed JSON document
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, &mut out);
+    out
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&format_number(n)),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            // Section 4.6: array order is preserved
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Section 4.1: keys sorted by byte value
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+
+            out.push('{');
+            for (i, key) in keys.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(&map[key.as_str()], out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Section 4.3: minimal escapes, literal UTF-8, unescaped forward slash
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Section 4.4: integers without decimal point; other numbers use whichever of
+/// plain or exponential notation is shorter (`5.0` -> `5`, `1.0E+10` -> `1e10`)
+fn format_number(n: &serde_json::Number) -> String {
+    if let Some(i) = n.as_i64() {
+        return i.to_string();
+    }
+    if let Some(u) = n.as_u64() {
+        return u.to_string();
+    }
+
+    let f = n.as_f64().unwrap_or(0.0);
+    if f == 0.0 {
+        return "0".to_string();
+    }
+
+    let plain = format!("{}", f);
+    let exponential = format!("{:e}", f);
+    if exponential.len() < plain.len() {
+        exponential
+    } else {
+        plain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canon(input: &str) -> String {
+        canonicalize(&serde_json::from_str(input).unwrap())
+    }
+
+    #[test]
+    fn test_key_ordering_and_whitespace() {
+        assert_eq!(canon(r#"{"z": 1, "a": 2, "m": 3}"#), r#"{"a":2,"m":3,"z":1}"#);
+        assert_eq!(
+            canon("{\n  \"key\": \"value\",\n  \"array\": [3, 1, 2]\n}"),
+            r#"{"array":[3,1,2],"key":"value"}"#
+        );
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        assert_eq!(canon("5.0"), "5");
+        assert_eq!(canon("0.50"), "0.5");
+        assert_eq!(canon("1.0E+10"), "1e10");
+        assert_eq!(canon("1.0e-10"), "1e-10");
+        assert_eq!(canon("-0.0"), "0");
+    }
+
+    #[test]
+    fn test_string_escaping() {
+        assert_eq!(canon(r#""Hello World""#), r#""Hello World""#);
+        assert_eq!(canon(r#""a/b\u001f\n""#), r#""a/b\u001f\n""#);
+        assert_eq!(canon(r#""café""#), "\"café\"");
+    }
+}
@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Persisted Signed Tree Head checkpoints (transparency-log.adoc Sections 6.2, 10.3)
+//
+// For each log the store keeps the largest verified STH seen so far in
+// `<dir>/<logId>.json`. A newer tree head only replaces it after an RFC 9162
+// consistency proof shows the log grew append-only; any tree head that
+// contradicts the stored one is reported as LOG_EQUIVOCATION. Each update
+// holds an exclusive lock on `<dir>/<logId>.lock`, so shims starting in
+// parallel cannot move a checkpoint backwards.
+
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::merkle;
+use crate::sth::SignedTreeHead;
+
+/// Two verified tree heads from one log that cannot both be correct
+#[derive(Debug)]
+pub struct Equivocation(pub String);
+
+impl std::fmt::Display for Equivocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LOG_EQUIVOCATION: {}", self.0)
+    }
+}
+
+impl std::error::Error for Equivocation {}
+
+/// Outcome of comparing a verified tree head with the stored checkpoint
+#[derive(Debug, PartialEq)]
+pub enum Observation {
+    /// First tree head seen for this log; stored as the checkpoint
+    New,
+    /// Same tree size and root as the checkpoint
+    Unchanged,
+    /// Larger tree proven consistent with the checkpoint; stored
+    Advanced,
+    /// Smaller tree proven consistent with the checkpoint
+    Older,
+    /// No consistency proof from the checkpoint's tree size to relate the
+    /// two tree heads; the checkpoint is unchanged
+    Unproven,
+}
+
+pub struct CheckpointStore {
+    dir: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Store at CHECKPOINT_DIR (default /var/lib/verified-container/checkpoints)
+    pub fn from_env() -> Self {
+        let dir = std::env::var("CHECKPOINT_DIR")
+            .unwrap_or_else(|_| "/var/lib/verified-container/checkpoints".to_string());
+        Self::open(dir)
+    }
+
+    fn path_for(&self, log_id: &str) -> Result<PathBuf> {
+        if !is_safe_file_name(log_id) {
+            bail!("Log ID {:?} cannot be used as a checkpoint file name", log_id);
+        }
+        Ok(self.dir.join(format!("{}.json", log_id)))
+    }
+
+    /// Every stored checkpoint (signatures are not re-verified here).
+    /// Checkpoints that cannot be read are skipped, as `observe` skips them.
+    pub fn load_all(&self) -> Result<Vec<(String, SignedTreeHead)>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e).context(format!("Failed to list checkpoints in {:?}", self.dir)),
+        };
+
+        let mut heads = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(log_id) = path.file_stem().and_then(|s| s.to_str()).filter(|s| is_safe_file_name(s)) else {
+                continue;
+            };
+            match self.get(log_id) {
+                Ok(Some(head)) => heads.push((log_id.to_string(), head)),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping checkpoint for log {}: {:#}", log_id, e),
+            }
+        }
+        Ok(heads)
+    }
+
+    /// The stored checkpoint for `log_id`, if any
+    pub fn get(&self, log_id: &str) -> Result<Option<SignedTreeHead>> {
+        let path = self.path_for(log_id)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .context(format!("Failed to read checkpoint {:?}", path))?;
+        Ok(Some(serde_json::from_str(&content)
+            .context(format!("Failed to parse checkpoint {:?}", path))?))
+    }
+
+    /// Exclusive lock on the checkpoint for `log_id`, released on drop
+    fn lock(&self, log_id: &str) -> Result<std::fs::File> {
+        std::fs::create_dir_all(&self.dir)
+            .context(format!("Failed to create checkpoint directory {:?}", self.dir))?;
+        let path = self.path_for(log_id)?.with_extension("lock");
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .context(format!("Failed to open checkpoint lock {:?}", path))?;
+        lock.lock().context(format!("Failed to lock checkpoint {:?}", path))?;
+        Ok(lock)
+    }
+
+    fn put(&self, log_id: &str, sth: &SignedTreeHead) -> Result<()> {
+        // Store only the signed fields; proofs are not needed once checked
+        let stored = SignedTreeHead {
+            log_id: Some(log_id.to_string()),
+            consistency: vec![],
+            ..sth.clone()
+        };
+
+        let path = self.path_for(log_id)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.dir)?;
+        tmp.write_all(serde_json::to_string_pretty(&stored)?.as_bytes())?;
+        tmp.persist(&path)
+            .context(format!("Failed to write checkpoint {:?}", path))?;
+        Ok(())
+    }
+
+    /// Compare a tree head whose signature has already been verified against
+    /// the checkpoint for its log, advancing the checkpoint when proven.
+    ///
+    /// `proof` is a consistency proof between the tree head and a checkpoint
+    /// of the given tree size, when one is available. A proof for another
+    /// size, e.g. because a concurrent shim advanced the checkpoint since it
+    /// was read, leaves the tree heads unproven.
+    pub fn observe(&self, log_id: &str, sth: &SignedTreeHead, proof: Option<(u64, &[merkle::Hash])>) -> Result<Observation> {
+        let _lock = self.lock(log_id)?;
+
+        let Some(stored) = self.get(log_id)? else {
+            self.put(log_id, sth)?;
+            info!("Recorded first checkpoint for log {} at tree size {}", log_id, sth.tree_size);
+            return Ok(Observation::New);
+        };
+
+        let stored_root = stored.root()?;
+        let new_root = sth.root()?;
+
+        if sth.tree_size == stored.tree_size {
+            if new_root != stored_root {
+                return Err(Equivocation(format!(
+                    "log {} signed two different roots for tree size {} ({} and {})",
+                    log_id, sth.tree_size, stored.root_hash, sth.root_hash
+                )).into());
+            }
+            return Ok(Observation::Unchanged);
+        }
+
+        let Some(proof) = proof.filter(|(size, _)| *size == stored.tree_size).map(|(_, proof)| proof) else {
+            return Ok(Observation::Unproven);
+        };
+
+        if sth.tree_size > stored.tree_size {
+            merkle::verify_consistency(stored.tree_size, sth.tree_size, &stored_root, &new_root, proof)
+                .context(Equivocation(format!(
+                    "log {} tree head at size {} is inconsistent with checkpoint at size {}",
+                    log_id, sth.tree_size, stored.tree_size
+                )))?;
+            self.put(log_id, sth)?;
+            info!("Advanced checkpoint for log {} from {} to {}", log_id, stored.tree_size, sth.tree_size);
+            Ok(Observation::Advanced)
+        } else {
+            merkle::verify_consistency(sth.tree_size, stored.tree_size, &new_root, &stored_root, proof)
+                .context(Equivocation(format!(
+                    "log {} tree head at size {} is inconsistent with checkpoint at size {}",
+                    log_id, sth.tree_size, stored.tree_size
+                )))?;
+            Ok(Observation::Older)
+        }
+    }
+}
+
+/// Whether a log ID or observer name can be used verbatim as a file name
+pub fn is_safe_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::reference::ReferenceTree;
+    use crate::sth::testing::signed;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_checkpoint_advances_only_with_consistency_proof() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CheckpointStore::open(dir.path());
+        let key = SigningKey::from_bytes(&[1u8; 32]);
+        let tree = ReferenceTree::new(12);
+
+        let old = signed(&key, 5, &tree.root_at(5));
+        let new = signed(&key, 12, &tree.root());
+
+        assert_eq!(store.observe("log-eu", &old, None).unwrap(), Observation::New);
+        assert_eq!(store.observe("log-eu", &old, None).unwrap(), Observation::Unchanged);
+        assert_eq!(store.observe("log-eu", &new, None).unwrap(), Observation::Unproven);
+        assert_eq!(store.get("log-eu").unwrap().unwrap().tree_size, 5);
+
+        // A proof from a size other than the checkpoint's proves nothing
+        let proof = tree.consistency_proof(4, 12);
+        assert_eq!(store.observe("log-eu", &new, Some((4, &proof))).unwrap(), Observation::Unproven);
+
+        let proof = tree.consistency_proof(5, 12);
+        assert_eq!(store.observe("log-eu", &new, Some((5, &proof))).unwrap(), Observation::Advanced);
+        assert_eq!(store.get("log-eu").unwrap().unwrap().tree_size, 12);
+
+        // Stored checkpoints remain readable as tree heads; a corrupt one is skipped
+        std::fs::write(dir.path().join("log-us.json"), "{").unwrap();
+        let all = store.load_all().unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].0, "log-eu");
+    }
+
+    #[test]
+    fn test_equivocation_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CheckpointStore::open(dir.path());
+        let key = SigningKey::from_bytes(&[1u8; 32]);
+        let tree = ReferenceTree::new(12);
+
+        store.observe("log-eu", &signed(&key, 8, &tree.root_at(8)), None).unwrap();
+
+        // Same size, different root
+        let err = store.observe("log-eu", &signed(&key, 8, &[7u8; 32]), None).unwrap_err();
+        assert!(err.downcast_ref::<Equivocation>().is_some());
+        assert!(err.to_string().contains("LOG_EQUIVOCATION"));
+
+        // Larger tree that rewrote history
+        let mut forked = ReferenceTree::new(12);
+        forked.leaves[2] = merkle::leaf_hash(b"rewritten");
+        let proof = forked.consistency_proof(8, 12);
+        let err = store.observe("log-eu", &signed(&key, 12, &forked.root()), Some((8, &proof))).unwrap_err();
+        assert!(err.downcast_ref::<Equivocation>().is_some());
+        assert_eq!(store.get("log-eu").unwrap().unwrap().tree_size, 8);
+    }
+
+    #[test]
+    fn test_unsafe_log_ids_rejected() {
+        let store = CheckpointStore::open("/tmp/unused");
+        assert!(store.path_for("../etc/passwd").is_err());
+        assert!(store.path_for("").is_err());
+        assert!(store.path_for("log-eu.v2").is_ok());
+    }
+}
@@ -0,0 +1,373 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Shim configuration (runtime-integration.adoc Section 6)
+//
+// Read from SHIM_CONFIG_PATH (default /etc/verified-container/shim.toml).
+// Every setting has a default, so a missing file is not an error.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShimConfig {
+    pub logs: LogsConfig,
+    pub gossip: GossipConfig,
+    pub revocation: RevocationConfig,
+    pub cache: CacheConfig,
+    pub audit: AuditConfig,
+    pub metrics: MetricsConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+/// Transparency log checks (verification-protocol.adoc Section 7.6)
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogsConfig {
+    /// Distinct log operators that must prove inclusion of each attestation
+    pub quorum: usize,
+    /// Accept a quorum of 1, below the 2 operators transparency-log.adoc
+    /// Section 8.2 requires
+    pub allow_single_operator: bool,
+    /// How far a log's integratedTime may lie ahead of the local clock
+    pub clock_skew_secs: u64,
+    /// Fetch fresh tree heads and proofs from each log's trust store `url`
+    pub online: bool,
+    /// Per-request timeout when talking to logs
+    pub timeout_secs: u64,
+    /// Retries after a failed request before the log counts as unreachable
+    pub retries: u32,
+    /// Oldest acceptable tree head fetched from a log
+    pub max_sth_age_secs: u64,
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            quorum: 2,
+            allow_single_operator: false,
+            clock_skew_secs: 300,
+            online: false,
+            timeout_secs: 10,
+            retries: 2,
+            max_sth_age_secs: 86400,
+        }
+    }
+}
+
+/// Tree head exchange with peer verifiers and witnesses (transparency-log.adoc Section 10.1)
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GossipConfig {
+    /// Shared directories or `http(s)://` witness endpoints
+    pub peers: Vec<String>,
+    /// Name this verifier publishes observations under (default: hostname)
+    pub observer: Option<String>,
+    /// Per-request timeout for HTTP peers
+    pub timeout_secs: u64,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            peers: vec![],
+            observer: None,
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// Revocation checking (transparency-log.adoc Section 9.2)
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RevocationConfig {
+    /// DSSE-signed revocation snapshot; must exist when set
+    pub snapshot: Option<PathBuf>,
+    /// Oldest acceptable snapshot `issuedAt`
+    pub max_snapshot_age_secs: u64,
+    /// Version of the newest snapshot used, so older ones cannot be replayed
+    pub state: PathBuf,
+}
+
+impl Default for RevocationConfig {
+    fn default() -> Self {
+        Self {
+            snapshot: None,
+            max_snapshot_age_secs: 7 * 86400,
+            state: PathBuf::from("/var/lib/verified-container/revocation-state.json"),
+        }
+    }
+}
+
+/// Verification result cache (verification-protocol.adoc Section 8)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How long a cached ALLOW is reused
+    pub ttl_secs: u64,
+    /// How long a REJECT is reused before the image is verified again;
+    /// 0 disables negative caching
+    pub negative_ttl_secs: u64,
+    /// Records kept before those closest to expiry are evicted
+    pub max_entries: usize,
+    /// Total size of the records kept before those closest to expiry are evicted
+    pub max_bytes: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 3600,
+            negative_ttl_secs: 0,
+            max_entries: 10_000,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn ttl(&self) -> chrono::TimeDelta {
+        seconds(self.ttl_secs)
+    }
+
+    pub fn negative_ttl(&self) -> chrono::TimeDelta {
+        seconds(self.negative_ttl_secs)
+    }
+}
+
+fn seconds(secs: u64) -> chrono::TimeDelta {
+    i64::try_from(secs).ok()
+        .and_then(chrono::TimeDelta::try_seconds)
+        .unwrap_or(chrono::TimeDelta::MAX)
+}
+
+/// Audit logging (verification-protocol.adoc Section 10.3)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Every record goes to each sink; with none, to a file at
+    /// AUDIT_LOG_PATH (default /var/log/verified-container/audit.log)
+    pub sinks: Vec<AuditSinkConfig>,
+    /// In strict mode, refuse to start a container when any sink fails
+    pub fatal_in_strict: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sinks: vec![],
+            fatal_in_strict: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AuditSinkConfig {
+    File(FileSinkConfig),
+    Syslog(SyslogSinkConfig),
+    Journald(JournaldSinkConfig),
+}
+
+/// Hash-chained JSON lines, rotated by size and age
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileSinkConfig {
+    pub path: PathBuf,
+    /// Rotate before the file would grow past this size; 0 disables
+    pub max_bytes: u64,
+    /// Rotate once the oldest record in the file is this old; 0 disables
+    pub max_age_secs: u64,
+    /// Rotated files kept; older ones are deleted
+    pub retain: usize,
+    /// Gzip rotated files
+    pub compress: bool,
+}
+
+impl Default for FileSinkConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("/var/log/verified-container/audit.log"),
+            max_bytes: 64 * 1024 * 1024,
+            max_age_secs: 0,
+            retain: 10,
+            compress: true,
+        }
+    }
+}
+
+/// RFC 5424 messages over a unix datagram socket or UDP
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyslogSinkConfig {
+    /// Socket path, or `udp://host:port`
+    pub address: String,
+    /// Facility name, e.g. `auth`, `daemon` or `local0`
+    pub facility: String,
+    pub app_name: String,
+}
+
+impl Default for SyslogSinkConfig {
+    fn default() -> Self {
+        Self {
+            address: "/dev/log".to_string(),
+            facility: "authpriv".to_string(),
+            app_name: "verified-container".to_string(),
+        }
+    }
+}
+
+/// systemd-journald native protocol
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JournaldSinkConfig {
+    pub socket: PathBuf,
+    pub identifier: String,
+}
+
+impl Default for JournaldSinkConfig {
+    fn default() -> Self {
+        Self {
+            socket: PathBuf::from("/run/systemd/journal/socket"),
+            identifier: "verified-container".to_string(),
+        }
+    }
+}
+
+/// Prometheus metrics. Each shim run adds its counts to `state`, which
+/// `metrics serve` exposes over HTTP and which is also written to `textfile`
+/// for the node-exporter textfile collector.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Totals across shim runs; only kept when `textfile` or `listen` is set
+    pub state: PathBuf,
+    /// Node-exporter textfile collector file, e.g.
+    /// `/var/lib/node_exporter/textfile_collector/verified_container.prom`
+    pub textfile: Option<PathBuf>,
+    /// Address `metrics serve` listens on
+    pub listen: Option<String>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            state: PathBuf::from("/var/lib/verified-container/metrics.json"),
+            textfile: None,
+            listen: None,
+        }
+    }
+}
+
+impl MetricsConfig {
+    pub fn enabled(&self) -> bool {
+        self.textfile.is_some() || self.listen.is_some()
+    }
+}
+
+/// OpenTelemetry trace export as OTLP/JSON. A `TRACEPARENT` environment
+/// variable makes the shim's spans part of the caller's trace.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector, e.g. `http://127.0.0.1:4318`
+    pub otlp_endpoint: Option<String>,
+    /// File to append one export request per line to
+    pub otlp_file: Option<PathBuf>,
+    pub service_name: String,
+    /// Per-request timeout for the collector
+    pub timeout_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            otlp_file: None,
+            service_name: "verified-container-shim".to_string(),
+            timeout_secs: 2,
+        }
+    }
+}
+
+impl ShimConfig {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("SHIM_CONFIG_PATH")
+            .unwrap_or_else(|_| "/etc/verified-container/shim.toml".to_string());
+
+        if !std::path::Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .context(format!("Failed to read shim config from {}", path))?;
+
+        let config: Self = toml::from_str(&content)
+            .context(format!("Failed to parse shim config {}", path))?;
+        config.logs.validate()
+            .context(format!("Invalid shim config {}", path))?;
+        Ok(config)
+    }
+}
+
+impl LogsConfig {
+    /// A quorum of 0 would skip the log coverage check altogether, and a
+    /// single operator is only accepted when explicitly allowed
+    pub fn validate(&self) -> Result<()> {
+        match self.quorum {
+            0 => bail!("invalid input: logs.quorum must be at least 1"),
+            1 if !self.allow_single_operator => bail!(
+                "invalid input: logs.quorum = 1 is below the 2 operators required by \
+                 transparency-log.adoc Section 8.2; set logs.allow_single_operator = true to accept it"
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: ShimConfig = toml::from_str("[gossip]\npeers = [\"/srv/witness\"]\n").unwrap();
+        assert_eq!(config.gossip.peers, vec!["/srv/witness"]);
+        assert_eq!(config.gossip.timeout_secs, 5);
+        assert_eq!(config.logs.quorum, 2);
+        assert_eq!(config.logs.clock_skew_secs, 300);
+
+        assert_eq!(config.cache.ttl_secs, 3600);
+        assert_eq!(config.cache.negative_ttl_secs, 0);
+        assert!(!config.metrics.enabled());
+
+        assert!(toml::from_str::<ShimConfig>("[gossip]\npeer = []\n").is_err());
+    }
+
+    #[test]
+    fn test_log_quorum_below_two_needs_opt_out() {
+        let logs = |toml: &str| toml::from_str::<ShimConfig>(toml).unwrap().logs;
+
+        assert!(logs("").validate().is_ok());
+        assert!(logs("[logs]\nquorum = 0\n").validate().is_err());
+        assert!(logs("[logs]\nquorum = 0\nallow_single_operator = true\n").validate().is_err());
+        assert!(logs("[logs]\nquorum = 1\n").validate().is_err());
+        assert!(logs("[logs]\nquorum = 1\nallow_single_operator = true\n").validate().is_ok());
+    }
+
+    #[test]
+    fn test_audit_sinks_are_tagged() {
+        let config: ShimConfig = toml::from_str(concat!(
+            "[[audit.sinks]]\ntype = \"file\"\npath = \"/tmp/audit.log\"\nretain = 3\n",
+            "[[audit.sinks]]\ntype = \"syslog\"\naddress = \"udp://127.0.0.1:514\"\n",
+            "[[audit.sinks]]\ntype = \"journald\"\n",
+        )).unwrap();
+        assert!(matches!(&config.audit.sinks[0], AuditSinkConfig::File(f) if f.retain == 3 && f.compress));
+        assert!(matches!(&config.audit.sinks[1], AuditSinkConfig::Syslog(s) if s.facility == "authpriv"));
+        assert!(matches!(&config.audit.sinks[2], AuditSinkConfig::Journald(_)));
+        assert!(config.audit.fatal_in_strict);
+
+        assert!(toml::from_str::<ShimConfig>("[[audit.sinks]]\ntype = \"file\"\npaht = \"x\"\n").is_err());
+        assert!(toml::from_str::<ShimConfig>("[[audit.sinks]]\ntype = \"kafka\"\n").is_err());
+    }
+}
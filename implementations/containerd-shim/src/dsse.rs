@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// DSSE envelopes (attestation-bundle.adoc, canonicalization.adoc Section 6.1)
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::canonical;
+use crate::signing::Signer;
+
+/// Payload type for in-toto statements
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Dead Simple Signing Envelope
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Envelope {
+    #[serde(rename = "payloadType")]
+    pub payload_type: String,
+    #[serde(with = "base64_bytes")]
+    pub payload: Vec<u8>,
+    pub signatures: Vec<Signature>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Signature {
+    pub keyid: String,
+    #[serde(with = "base64_bytes")]
+    pub sig: Vec<u8>,
+}
+
+impl Envelope {
+    /// Bytes covered by each signature in this envelope
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        pae(&self.payload_type, &self.payload)
+    }
+}
+
+/// DSSE v1 Pre-Authentication Encoding:
+/// `"DSSEv1" SP LEN(type) SP type SP LEN(body) SP body`
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Canonicalize an in-toto statement and sign it into a DSSE envelope
+pub fn sign_statement(statement: &serde_json::Value, signer: &Signer, keyid: &str) -> Result<Envelope> {
+    let payload = canonical::canonicalize(statement).into_bytes();
+    let sig = signer.sign(&pae(IN_TOTO_PAYLOAD_TYPE, &payload))
+        .context("Failed to sign DSSE envelope")?;
+
+    Ok(Envelope {
+        payload_type: IN_TOTO_PAYLOAD_TYPE.to_string(),
+        payload,
+        signatures: vec![Signature {
+            keyid: keyid.to_string(),
+            sig,
+        }],
+    })
+}
+
+/// Serde adapter for base64-encoded byte fields (attestation-bundle.schema.json)
+pub mod base64_bytes {
+    use base64::{Engine as _, engine::general_purpose};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        general_purpose::STANDARD
+            .decode(s)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pae_encoding() {
+        // Test vector from the DSSE protocol specification
+        assert_eq!(
+            pae("http://example.com/HelloWorld", b"hello world"),
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_envelope_wire_format_is_base64() {
+        let envelope = Envelope {
+            payload_type: IN_TOTO_PAYLOAD_TYPE.to_string(),
+            payload: b"{}".to_vec(),
+            signatures: vec![Signature { keyid: "k".to_string(), sig: vec![1, 2, 3] }],
+        };
+
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["payload"], "e30=");
+        assert_eq!(json["signatures"][0]["sig"], "AQID");
+
+        let parsed: Envelope = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.payload, b"{}");
+    }
+}
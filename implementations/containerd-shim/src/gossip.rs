@@ -0,0 +1,363 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Tree head gossip between verifiers (transparency-log.adoc Section 10.1)
+//
+// Each verifier publishes the tree heads it has verified to its peers and
+// reads back what they have seen. Both sides of a split view carry the log's
+// signature, so two observations with different roots for the same tree size
+// are proof of equivocation.
+//
+// A peer is either a shared directory, laid out as
+// `<dir>/<logId>/<treeSize>-<root prefix>-<observer>.json`, or an HTTP
+// witness that accepts `POST <url>` with a JSON array of observations and
+// answers `GET <url>?logId=<id>` with the observations it holds for that log.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::checkpoint;
+use crate::config::GossipConfig;
+use crate::merkle;
+use crate::sth::{SignedTreeHead, VerifiedTreeHeads};
+
+/// A signed tree head as seen by one verifier
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Report {
+    pub observer: String,
+    #[serde(flatten)]
+    pub sth: SignedTreeHead,
+}
+
+impl Report {
+    fn new(observer: &str, log_id: &str, sth: &SignedTreeHead) -> Self {
+        Self {
+            observer: observer.to_string(),
+            sth: SignedTreeHead {
+                log_id: Some(log_id.to_string()),
+                consistency: vec![],
+                ..sth.clone()
+            },
+        }
+    }
+
+    fn log_id(&self) -> &str {
+        self.sth.log_id.as_deref().unwrap_or_default()
+    }
+}
+
+/// Where observations are exchanged
+#[derive(Debug, PartialEq)]
+pub enum Peer {
+    Directory(PathBuf),
+    Http(String),
+}
+
+impl Peer {
+    /// `http(s)://` URLs are witness endpoints; anything else is a directory
+    pub fn parse(spec: &str) -> Self {
+        if spec.starts_with("http://") || spec.starts_with("https://") {
+            Peer::Http(spec.to_string())
+        } else {
+            Peer::Directory(PathBuf::from(spec))
+        }
+    }
+
+    async fn publish(&self, client: &reqwest::Client, reports: &[Report]) -> Result<()> {
+        match self {
+            Peer::Directory(dir) => {
+                for report in reports {
+                    write_report(dir, report)?;
+                }
+                Ok(())
+            }
+            Peer::Http(url) => {
+                client.post(url).json(reports).send().await?.error_for_status()?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn fetch(&self, client: &reqwest::Client, log_id: &str) -> Result<Vec<Report>> {
+        match self {
+            Peer::Directory(dir) => read_reports(&log_dir(dir, log_id)?),
+            Peer::Http(url) => Ok(client
+                .get(url)
+                .query(&[("logId", log_id)])
+                .send().await?
+                .error_for_status()?
+                .json().await?),
+        }
+    }
+}
+
+fn log_dir(dir: &Path, log_id: &str) -> Result<PathBuf> {
+    if !checkpoint::is_safe_file_name(log_id) {
+        bail!("Log ID {:?} cannot be used as a gossip directory name", log_id);
+    }
+    Ok(dir.join(log_id))
+}
+
+fn write_report(dir: &Path, report: &Report) -> Result<()> {
+    let dir = log_dir(dir, report.log_id())?;
+    std::fs::create_dir_all(&dir)
+        .context(format!("Failed to create gossip directory {:?}", dir))?;
+
+    let root = hex::encode(report.sth.root()?);
+    let path = dir.join(format!("{}-{}-{}.json", report.sth.tree_size, &root[..16], report.observer));
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(report)?)?;
+    std::fs::rename(&tmp, &path)
+        .context(format!("Failed to write gossip observation {:?}", path))?;
+    Ok(())
+}
+
+fn read_reports(dir: &Path) -> Result<Vec<Report>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut reports = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str(&content)?));
+        match parsed {
+            Ok(report) => reports.push(report),
+            Err(e) => warn!("Ignoring unreadable gossip observation {:?}: {:#}", path, e),
+        }
+    }
+    Ok(reports)
+}
+
+/// Name this verifier publishes under: the configured observer or the hostname
+fn observer_name(config: &GossipConfig) -> String {
+    let name = config.observer.clone()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "verifier".to_string());
+
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect()
+}
+
+/// Publish our verified tree heads to every configured peer and compare them
+/// with the peers' observations.
+///
+/// Unreachable peers and observations without a valid log signature are
+/// logged and skipped; conflicting roots fail with LOG_EQUIVOCATION.
+pub async fn exchange<'a>(
+    config: &GossipConfig,
+    heads: &VerifiedTreeHeads,
+    log_key: impl Fn(&str) -> Option<&'a [u8]>,
+) -> Result<()> {
+    if config.peers.is_empty() {
+        return Ok(());
+    }
+
+    let observer = observer_name(config);
+    let ours: Vec<Report> = heads.iter()
+        .map(|(log_id, sth)| Report::new(&observer, log_id, sth))
+        .collect();
+    let log_ids: BTreeSet<&str> = ours.iter().map(Report::log_id).collect();
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()?;
+
+    let mut seen = ours.clone();
+    for spec in &config.peers {
+        let peer = Peer::parse(spec);
+
+        if let Err(e) = peer.publish(&client, &ours).await {
+            warn!("Failed to publish tree heads to gossip peer {}: {:#}", spec, e);
+        }
+
+        for log_id in &log_ids {
+            match peer.fetch(&client, log_id).await {
+                Ok(reports) => seen.extend(authentic(reports, log_id, &log_key)),
+                Err(e) => warn!("Failed to fetch tree heads for log {} from gossip peer {}: {:#}", log_id, spec, e),
+            }
+        }
+    }
+
+    info!("Compared {} tree head observations across {} gossip peers", seen.len(), config.peers.len());
+    detect_split_view(&seen)
+}
+
+/// Keep only observations for `log_id` that carry a valid signature from that log
+fn authentic<'a>(
+    reports: Vec<Report>,
+    log_id: &str,
+    log_key: impl Fn(&str) -> Option<&'a [u8]>,
+) -> Vec<Report> {
+    let Some(key) = log_key(log_id) else {
+        return vec![];
+    };
+
+    reports.into_iter()
+        .filter(|r| r.log_id() == log_id)
+        .filter(|r| match r.sth.verify(key) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Ignoring tree head for log {} from observer {}: {:#}", log_id, r.observer, e);
+                false
+            }
+        })
+        .collect()
+}
+
+/// Fail if two authenticated observations disagree on the root for a tree size
+pub fn detect_split_view(reports: &[Report]) -> Result<()> {
+    let mut views: BTreeMap<(&str, u64), BTreeMap<merkle::Hash, BTreeSet<&str>>> = BTreeMap::new();
+    for report in reports {
+        views.entry((report.log_id(), report.sth.tree_size))
+            .or_default()
+            .entry(report.sth.root()?)
+            .or_default()
+            .insert(&report.observer);
+    }
+
+    let mut conflicts = Vec::new();
+    for ((log_id, tree_size), roots) in &views {
+        if roots.len() < 2 {
+            continue;
+        }
+        let described: Vec<String> = roots.iter()
+            .map(|(root, observers)| format!("{} seen by {}",
+                hex::encode(root), observers.iter().copied().collect::<Vec<_>>().join(", ")))
+            .collect();
+        error!("SPLIT-VIEW ALARM: log {} signed {} different roots for tree size {}: {}",
+            log_id, roots.len(), tree_size, described.join("; "));
+        conflicts.push(format!("log {} at tree size {}", log_id, tree_size));
+    }
+
+    if !conflicts.is_empty() {
+        bail!("LOG_EQUIVOCATION: split view detected via gossip for {}", conflicts.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sth::testing::signed;
+    use ed25519_dalek::SigningKey;
+    use std::sync::{Arc, Mutex};
+
+    fn log_key() -> SigningKey {
+        SigningKey::from_bytes(&[6u8; 32])
+    }
+
+    fn verified(sth: SignedTreeHead) -> VerifiedTreeHeads {
+        let public = log_key().verifying_key().to_bytes();
+        VerifiedTreeHeads::verify(vec![("log-eu".to_string(), sth)], |_| Some(public.as_slice())).unwrap()
+    }
+
+    fn config(peer: &str, observer: &str) -> GossipConfig {
+        GossipConfig {
+            peers: vec![peer.to_string()],
+            observer: Some(observer.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_peer_parse() {
+        assert_eq!(Peer::parse("https://witness.example/sth"), Peer::Http("https://witness.example/sth".to_string()));
+        assert_eq!(Peer::parse("/srv/gossip"), Peer::Directory(PathBuf::from("/srv/gossip")));
+    }
+
+    #[test]
+    fn test_split_view_requires_same_tree_size() {
+        let key = log_key();
+        let a = Report::new("node-a", "log-eu", &signed(&key, 8, &[1u8; 32]));
+        let b = Report::new("node-b", "log-eu", &signed(&key, 8, &[1u8; 32]));
+        let c = Report::new("node-c", "log-eu", &signed(&key, 9, &[2u8; 32]));
+        assert!(detect_split_view(&[a.clone(), b, c]).is_ok());
+
+        let forked = Report::new("node-d", "log-eu", &signed(&key, 8, &[3u8; 32]));
+        let err = detect_split_view(&[a, forked]).unwrap_err();
+        assert!(err.to_string().contains("LOG_EQUIVOCATION"));
+    }
+
+    #[tokio::test]
+    async fn test_directory_gossip_detects_split_view() {
+        let dir = tempfile::tempdir().unwrap();
+        let peer = dir.path().to_str().unwrap();
+        let key = log_key();
+        let public = key.verifying_key().to_bytes();
+        let log_key = |_: &str| Some(public.as_slice());
+
+        // Two verifiers that were shown the same tree agree
+        let honest = signed(&key, 8, &[1u8; 32]);
+        exchange(&config(peer, "node-a"), &verified(honest.clone()), log_key).await.unwrap();
+        exchange(&config(peer, "node-b"), &verified(honest), log_key).await.unwrap();
+
+        // A forgery not signed by the log is ignored
+        let forged = signed(&SigningKey::from_bytes(&[9u8; 32]), 8, &[2u8; 32]);
+        write_report(dir.path(), &Report::new("mallory", "log-eu", &forged)).unwrap();
+        exchange(&config(peer, "node-a"), &verified(signed(&key, 8, &[1u8; 32])), log_key).await.unwrap();
+
+        // A third verifier shown a different tree at the same size
+        let split = signed(&key, 8, &[2u8; 32]);
+        let err = exchange(&config(peer, "node-c"), &verified(split), log_key).await.unwrap_err();
+        assert!(err.to_string().contains("LOG_EQUIVOCATION"));
+    }
+
+    #[tokio::test]
+    async fn test_http_witness_detects_split_view() {
+        use axum::{Json, Router, extract::{Query, State}, routing::get};
+
+        type Store = Arc<Mutex<Vec<Report>>>;
+
+        #[derive(Deserialize)]
+        struct LogQuery {
+            #[serde(rename = "logId")]
+            log_id: String,
+        }
+
+        // Minimal stand-in for a witness endpoint
+        let store: Store = Arc::default();
+        let app = Router::new()
+            .route("/gossip", get(|State(store): State<Store>, Query(q): Query<LogQuery>| async move {
+                Json(store.lock().unwrap().iter().filter(|r| r.log_id() == q.log_id).cloned().collect::<Vec<_>>())
+            }).post(|State(store): State<Store>, Json(reports): Json<Vec<Report>>| async move {
+                store.lock().unwrap().extend(reports);
+            }))
+            .with_state(store.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/gossip", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let key = log_key();
+        let public = key.verifying_key().to_bytes();
+        let log_key = |_: &str| Some(public.as_slice());
+
+        exchange(&config(&url, "node-a"), &verified(signed(&key, 8, &[1u8; 32])), log_key).await.unwrap();
+        assert_eq!(store.lock().unwrap().len(), 1);
+
+        let err = exchange(&config(&url, "node-b"), &verified(signed(&key, 8, &[2u8; 32])), log_key)
+            .await.unwrap_err();
+        assert!(err.to_string().contains("LOG_EQUIVOCATION"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_peer_is_not_fatal() {
+        let key = log_key();
+        let public = key.verifying_key().to_bytes();
+        let config = GossipConfig {
+            timeout_secs: 1,
+            ..config("http://127.0.0.1:9/gossip", "node-a")
+        };
+        assert!(exchange(&config, &verified(signed(&key, 8, &[1u8; 32])), |_| Some(public.as_slice())).await.is_ok());
+    }
+}
@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// HTTP client for the transparency log API (transparency-log.adoc Section 7)
+//
+// Transport failures, timeouts and 5xx responses are retried with backoff.
+// Once retries are exhausted the error is a `NetworkError`, which the shim
+// maps to EXIT_NETWORK_ERROR (runtime-integration.adoc Section 8.1).
+
+use anyhow::{Result, anyhow, bail};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::dsse::Envelope;
+use crate::log_server::{InclusionProof, LoggedEntry, RevocationRecord, SubmitRequest, SubmitResponse, TreeConsistency};
+use crate::sth::SignedTreeHead;
+use crate::tlog::REVOCATION_PAYLOAD_TYPE;
+
+/// A log or tile mirror could not be reached. Unlike a bad proof or a
+/// rejected request this is transient, so it is never cached as a REJECT.
+#[derive(Debug)]
+pub struct NetworkError(pub String);
+
+impl std::fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "network error: {}", self.0)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// First retry delay; doubled for each further attempt
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+pub struct LogClient {
+    url: String,
+    http: reqwest::Client,
+    retries: u32,
+}
+
+impl LogClient {
+    /// Client for the log at `url` (e.g. `https://log-eu.verified-container.org`)
+    pub fn new(url: &str, timeout: Duration, retries: u32) -> Result<Self> {
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder().timeout(timeout).build()?,
+            retries,
+        })
+    }
+
+    /// Current signed tree head, with a consistency proof from `from_size` if given
+    pub async fn get_sth(&self, from_size: Option<u64>) -> Result<SignedTreeHead> {
+        let url = format!("{}/api/v1/sth", self.url);
+        let query: Vec<(&str, u64)> = from_size.map(|s| ("fromSize", s)).into_iter().collect();
+        self.send(|| self.http.get(&url).query(&query)).await
+    }
+
+    /// Inclusion proof for `log_index` against the tree of size `tree_size`
+    pub async fn get_proof(&self, log_index: u64, tree_size: u64) -> Result<InclusionProof> {
+        let url = format!("{}/api/v1/proof", self.url);
+        let query = [("logIndex", log_index), ("treeSize", tree_size)];
+        self.send(|| self.http.get(&url).query(&query)).await
+    }
+
+    /// Consistency proof from tree size `from_size` to `to_size`
+    pub async fn get_consistency(&self, from_size: u64, to_size: u64) -> Result<TreeConsistency> {
+        let url = format!("{}/api/v1/consistency", self.url);
+        let query = [("fromSize", from_size), ("toSize", to_size)];
+        self.send(|| self.http.get(&url).query(&query)).await
+    }
+
+    /// Entries from `start` up to (excluding) `end`; logs may return fewer
+    pub async fn get_entries(&self, start: u64, end: u64) -> Result<Vec<LoggedEntry>> {
+        let url = format!("{}/api/v1/entries", self.url);
+        let query = [("start", start), ("end", end)];
+        self.send(|| self.http.get(&url).query(&query)).await
+    }
+
+    /// Revocation entries for any of `digests` (key IDs or attestation
+    /// digests), with inclusion proofs against the tree of size `tree_size`
+    pub async fn get_revocations(&self, digests: &[String], tree_size: u64) -> Result<Vec<RevocationRecord>> {
+        let url = format!("{}/api/v1/revocations", self.url);
+        let query = [("digests", digests.join(",")), ("treeSize", tree_size.to_string())];
+        self.send(|| self.http.get(&url).query(&query)).await
+    }
+
+    /// Submit a DSSE envelope of an attestation or a revocation. Logs
+    /// deduplicate entries, so retrying is safe.
+    pub async fn submit(&self, envelope: &Envelope) -> Result<SubmitResponse> {
+        let url = format!("{}/api/v1/entries", self.url);
+        let bytes = serde_json::to_vec(envelope)?;
+        let request = match envelope.payload_type.as_str() {
+            REVOCATION_PAYLOAD_TYPE => SubmitRequest::Revocation { revocation: bytes },
+            _ => SubmitRequest::Attestation { attestation: bytes },
+        };
+        self.send(|| self.http.post(&url).json(&request)).await
+    }
+
+    /// Send a request, retrying transport failures and server errors
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let mut delay = RETRY_BACKOFF;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let failure = match request().send().await {
+                Ok(response) if response.status().is_success() => {
+                    return response.json().await
+                        .map_err(|e| anyhow!("Invalid response from log at {}: {}", self.url, e));
+                }
+                Ok(response) if response.status().is_client_error() => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    bail!("Log at {} rejected the request with HTTP {}: {}", self.url, status, body);
+                }
+                Ok(response) => format!("HTTP {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+
+            if attempt > self.retries {
+                let message = format!("log at {} unavailable after {} attempts: {}", self.url, attempt, failure);
+                return Err(NetworkError(message).into());
+            }
+            warn!("Request to log at {} failed ({}), retrying in {:?}", self.url, failure, delay);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_server;
+    use ed25519_dalek::SigningKey;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_fetch_sth_and_proof_from_local_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::from_bytes(&[8u8; 32]);
+        let url = log_server::testing::spawn(dir.path(), "log-dev", &key).await;
+        let client = LogClient::new(&url, Duration::from_secs(5), 0).unwrap();
+
+        let sth = client.get_sth(None).await.unwrap();
+        sth.verify(&key.verifying_key().to_bytes()).unwrap();
+        assert_eq!(sth.tree_size, 0);
+
+        // Asking for an entry the log does not have is not a network failure
+        let err = client.get_proof(0, 1).await.unwrap_err();
+        assert!(!err.to_string().contains("network error"));
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried() {
+        use axum::{Router, http::StatusCode, routing::get};
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let app = Router::new().route("/api/v1/sth", get(move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(StatusCode::SERVICE_UNAVAILABLE)
+                } else {
+                    Ok(axum::Json(serde_json::json!({
+                        "treeSize": 0,
+                        "timestamp": "2026-01-01T00:00:00Z",
+                        "rootHash": hex::encode([0u8; 32]),
+                        "signature": ""
+                    })))
+                }
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // Two failures, then success on the third attempt
+        let patient = LogClient::new(&url, Duration::from_secs(5), 2).unwrap();
+        assert!(patient.get_sth(None).await.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        let impatient = LogClient::new(&url, Duration::from_secs(5), 1).unwrap();
+        let err = impatient.get_sth(None).await.unwrap_err();
+        assert!(err.to_string().contains("network error"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_log_is_network_error() {
+        let client = LogClient::new("http://127.0.0.1:9", Duration::from_secs(1), 1).unwrap();
+        let err = client.get_sth(None).await.unwrap_err();
+        assert!(err.to_string().contains("network error"));
+    }
+}
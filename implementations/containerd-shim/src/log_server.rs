@@ -0,0 +1,567 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// File-backed transparency log server (transparency-log.adoc Section 7)
+//
+// A single-node log for development and integration tests. Entries are
+// appended to `<dir>/entries.jsonl` and the Merkle tree is rebuilt from that
+// file on start. Tree heads and entry timestamps are signed on demand with
+// the log's Ed25519 key, which may live in a file or behind PKCS#11.
+
+use anyhow::{Context, Result, bail};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+use crate::dsse::{Envelope, base64_bytes};
+use crate::merkle;
+use crate::signing::Signer;
+use crate::sth::{ConsistencyProof, SignedTreeHead};
+use crate::tlog;
+
+/// `POST /api/v1/entries` request body
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SubmitRequest {
+    /// JSON DSSE envelope, base64-encoded
+    Attestation {
+        #[serde(with = "base64_bytes")]
+        attestation: Vec<u8>,
+    },
+    /// Revocation of a key or attestation digest as a JSON DSSE envelope
+    /// signed by its `revokedBy` key, base64-encoded (Section 9.1)
+    Revocation {
+        #[serde(with = "base64_bytes")]
+        revocation: Vec<u8>,
+    },
+}
+
+/// `POST /api/v1/entries` response: a bundle log entry plus the tree head
+/// its inclusion proof was computed against
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SubmitResponse {
+    #[serde(flatten)]
+    pub entry: IntegratedEntry,
+    #[serde(rename = "signedTreeHead")]
+    pub signed_tree_head: SignedTreeHead,
+}
+
+/// An entry as recorded in an attestation bundle's `logEntries`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IntegratedEntry {
+    #[serde(rename = "logId")]
+    pub log_id: String,
+    #[serde(rename = "logIndex")]
+    pub log_index: u64,
+    #[serde(rename = "integratedTime")]
+    pub integrated_time: String,
+    #[serde(rename = "inclusionProof")]
+    pub inclusion_proof: InclusionProof,
+    #[serde(rename = "signedEntryTimestamp")]
+    pub signed_entry_timestamp: String,
+}
+
+/// `GET /api/v1/proof` response
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InclusionProof {
+    #[serde(rename = "logIndex")]
+    pub log_index: u64,
+    #[serde(rename = "treeSize")]
+    pub tree_size: u64,
+    #[serde(rename = "rootHash")]
+    pub root_hash: String,
+    pub hashes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProofQuery {
+    #[serde(rename = "logIndex")]
+    log_index: u64,
+    #[serde(rename = "treeSize")]
+    tree_size: Option<u64>,
+}
+
+/// `GET /api/v1/revocations` response item: the signed revocation and its
+/// inclusion proof against the requested tree size
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RevocationRecord {
+    #[serde(rename = "logIndex")]
+    pub log_index: u64,
+    #[serde(rename = "integratedTime")]
+    pub integrated_time: String,
+    pub revocation: Envelope,
+    #[serde(rename = "inclusionProof")]
+    pub inclusion_proof: InclusionProof,
+}
+
+/// `GET /api/v1/entries` response item
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggedEntry {
+    #[serde(rename = "logIndex")]
+    pub log_index: u64,
+    #[serde(rename = "integratedTime")]
+    pub integrated_time: String,
+    pub entry: tlog::Entry,
+    /// The submitted DSSE envelope, for attestation entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Envelope>,
+}
+
+/// Most entries returned by one `GET /api/v1/entries` request
+const MAX_ENTRIES_PAGE: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+struct EntriesQuery {
+    start: u64,
+    end: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevocationQuery {
+    /// Comma-separated key IDs and attestation digests
+    digests: String,
+    #[serde(rename = "treeSize")]
+    tree_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SthQuery {
+    #[serde(rename = "fromSize")]
+    from_size: Option<u64>,
+}
+
+/// `GET /api/v1/consistency` response: an RFC 9162 consistency proof between
+/// two tree sizes, neither of which need be the current one
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TreeConsistency {
+    #[serde(rename = "fromSize")]
+    pub from_size: u64,
+    #[serde(rename = "toSize")]
+    pub to_size: u64,
+    pub hashes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConsistencyQuery {
+    #[serde(rename = "fromSize")]
+    from_size: u64,
+    #[serde(rename = "toSize")]
+    to_size: u64,
+}
+
+/// One line of `entries.jsonl`
+#[derive(Debug, Deserialize, Serialize)]
+struct StoredEntry {
+    #[serde(rename = "integratedTime")]
+    integrated_time: String,
+    entry: tlog::Entry,
+    /// The submitted envelope, of an attestation or a revocation
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "attestation")]
+    envelope: Option<Envelope>,
+}
+
+struct LogState {
+    path: PathBuf,
+    entries: Vec<StoredEntry>,
+    leaves: Vec<merkle::Hash>,
+    signer: Signer,
+}
+
+/// An embeddable transparency log
+#[derive(Clone)]
+pub struct LogServer {
+    log_id: String,
+    state: Arc<Mutex<LogState>>,
+}
+
+fn hash_string(hash: &merkle::Hash) -> String {
+    format!("sha256:{}", hex::encode(hash))
+}
+
+impl LogServer {
+    /// Open (or create) the log stored in `dir`
+    pub fn open(dir: &Path, log_id: &str, signer: Signer) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .context(format!("Failed to create log directory {:?}", dir))?;
+        let path = dir.join("entries.jsonl");
+
+        let mut entries = Vec::new();
+        let mut leaves = Vec::new();
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .context(format!("Failed to read log entries {:?}", path))?;
+            for (i, line) in content.lines().enumerate() {
+                let stored: StoredEntry = serde_json::from_str(line)
+                    .context(format!("Corrupt log entry at index {} in {:?}", i, path))?;
+                leaves.push(stored.entry.leaf_hash()?);
+                entries.push(stored);
+            }
+        }
+
+        info!("Opened log {} with {} entries from {:?}", log_id, leaves.len(), dir);
+        Ok(Self {
+            log_id: log_id.to_string(),
+            state: Arc::new(Mutex::new(LogState { path, entries, leaves, signer })),
+        })
+    }
+
+    /// HTTP routes for the transparency-log.adoc Section 7 API
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/api/v1/entries", post(submit_entry).get(list_entries))
+            .route("/api/v1/proof", get(get_proof))
+            .route("/api/v1/sth", get(get_sth))
+            .route("/api/v1/consistency", get(get_consistency))
+            .route("/api/v1/revocations", get(get_revocations))
+            .with_state(self)
+    }
+
+    /// Serve the API on `listener` until the task is dropped
+    pub async fn serve(self, listener: tokio::net::TcpListener) -> Result<()> {
+        info!("Log {} listening on {}", self.log_id, listener.local_addr()?);
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+
+    /// Append an entry (idempotent for identical entries), keeping the
+    /// envelope it was built from for monitors and verifiers
+    pub fn submit(&self, entry: tlog::Entry, envelope: Option<Envelope>) -> Result<SubmitResponse> {
+        let leaf = entry.leaf_hash()?;
+        let mut state = self.state.lock().unwrap();
+
+        let log_index = match state.leaves.iter().position(|l| *l == leaf) {
+            Some(existing) => existing,
+            None => {
+                let stored = StoredEntry {
+                    integrated_time: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                    entry,
+                    envelope,
+                };
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&state.path)
+                    .context("Failed to open log entries file")?;
+                writeln!(file, "{}", serde_json::to_string(&stored)?)?;
+                file.sync_data()?;
+
+                state.leaves.push(leaf);
+                state.entries.push(stored);
+                info!("Log {} integrated entry {}", self.log_id, state.leaves.len() - 1);
+                state.leaves.len() - 1
+            }
+        };
+
+        let integrated_time = state.entries[log_index].integrated_time.clone();
+        let timestamp = tlog::EntryTimestamp {
+            log_id: self.log_id.clone(),
+            log_index: log_index as u64,
+            integrated_time: integrated_time.clone(),
+            entry_hash: leaf,
+        };
+        let set = state.signer.sign(&timestamp.signed_bytes())?;
+
+        let tree_size = state.leaves.len() as u64;
+        Ok(SubmitResponse {
+            entry: IntegratedEntry {
+                log_id: self.log_id.clone(),
+                log_index: log_index as u64,
+                integrated_time,
+                inclusion_proof: self.proof(&state, log_index as u64, tree_size)?,
+                signed_entry_timestamp: general_purpose::STANDARD.encode(set),
+            },
+            signed_tree_head: self.tree_head(&state, None)?,
+        })
+    }
+
+    fn proof(&self, state: &LogState, log_index: u64, tree_size: u64) -> Result<InclusionProof> {
+        if tree_size > state.leaves.len() as u64 || log_index >= tree_size {
+            bail!("No entry {} in a tree of size {}", log_index, tree_size);
+        }
+        let leaves = &state.leaves[..tree_size as usize];
+        Ok(InclusionProof {
+            log_index,
+            tree_size,
+            root_hash: hash_string(&merkle::tree_root(leaves)),
+            hashes: merkle::inclusion_proof(log_index, leaves).iter().map(hash_string).collect(),
+        })
+    }
+
+    fn consistency(&self, state: &LogState, from_size: u64, to_size: u64) -> Result<TreeConsistency> {
+        if from_size > to_size || to_size > state.leaves.len() as u64 {
+            bail!("Cannot prove consistency from size {} to {}", from_size, to_size);
+        }
+        Ok(TreeConsistency {
+            from_size,
+            to_size,
+            hashes: merkle::consistency_proof(from_size, &state.leaves[..to_size as usize])
+                .iter().map(hash_string).collect(),
+        })
+    }
+
+    /// Sign the current tree head, with a consistency proof from `from_size` if requested
+    fn tree_head(&self, state: &LogState, from_size: Option<u64>) -> Result<SignedTreeHead> {
+        let consistency = match from_size {
+            Some(size) if size > state.leaves.len() as u64 => {
+                bail!("Cannot prove consistency from size {} to {}", size, state.leaves.len())
+            }
+            Some(size) => vec![ConsistencyProof {
+                from_size: size,
+                hashes: merkle::consistency_proof(size, &state.leaves).iter().map(hash_string).collect(),
+            }],
+            None => vec![],
+        };
+
+        let mut sth = SignedTreeHead {
+            log_id: Some(self.log_id.clone()),
+            tree_size: state.leaves.len() as u64,
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            root_hash: hash_string(&merkle::tree_root(&state.leaves)),
+            signature: vec![],
+            consistency,
+        };
+        sth.signature = state.signer.sign(&sth.signed_bytes())?;
+        Ok(sth)
+    }
+}
+
+/// Handler error carrying an HTTP status and message
+struct ApiError(StatusCode, anyhow::Error);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": format!("{:#}", self.1) }))).into_response()
+    }
+}
+
+async fn submit_entry(
+    State(log): State<LogServer>,
+    Json(request): Json<SubmitRequest>,
+) -> Result<Json<SubmitResponse>, ApiError> {
+    let (envelope, what) = match request {
+        SubmitRequest::Attestation { attestation } => (attestation, "attestation"),
+        SubmitRequest::Revocation { revocation } => (revocation, "revocation"),
+    };
+    let envelope: Envelope = serde_json::from_slice(&envelope)
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, anyhow::anyhow!("Invalid DSSE envelope: {}", e)))?;
+    let entry = tlog::Entry::from_envelope(&envelope).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))?;
+    // Signatures are checked by verifiers against their own trust stores
+    let expected = match &entry.body {
+        tlog::EntryBody::Attestation(_) => "attestation",
+        tlog::EntryBody::Revocation(_) => "revocation",
+    };
+    if expected != what {
+        let error = anyhow::anyhow!("Envelope of type {} submitted as {}", envelope.payload_type, what);
+        return Err(ApiError(StatusCode::BAD_REQUEST, error));
+    }
+
+    log.submit(entry, Some(envelope))
+        .map(Json)
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+async fn list_entries(
+    State(log): State<LogServer>,
+    Query(query): Query<EntriesQuery>,
+) -> Json<Vec<LoggedEntry>> {
+    let state = log.state.lock().unwrap();
+    let size = state.entries.len() as u64;
+    let end = query.end.unwrap_or(size).min(size).min(query.start.saturating_add(MAX_ENTRIES_PAGE));
+
+    Json((query.start..end).map(|i| {
+        let stored = &state.entries[i as usize];
+        LoggedEntry {
+            log_index: i,
+            integrated_time: stored.integrated_time.clone(),
+            entry: stored.entry.clone(),
+            attestation: match stored.entry.body {
+                tlog::EntryBody::Attestation(_) => stored.envelope.clone(),
+                tlog::EntryBody::Revocation(_) => None,
+            },
+        }
+    }).collect())
+}
+
+async fn get_revocations(
+    State(log): State<LogServer>,
+    Query(query): Query<RevocationQuery>,
+) -> Result<Json<Vec<RevocationRecord>>, ApiError> {
+    let digests: Vec<&str> = query.digests.split(',').collect();
+    let state = log.state.lock().unwrap();
+    let tree_size = query.tree_size.unwrap_or(state.leaves.len() as u64);
+    if tree_size > state.leaves.len() as u64 {
+        let error = anyhow::anyhow!("No tree of size {}", tree_size);
+        return Err(ApiError(StatusCode::BAD_REQUEST, error));
+    }
+
+    let mut records = Vec::new();
+    for (i, stored) in state.entries.iter().enumerate().take(tree_size as usize) {
+        let (tlog::EntryBody::Revocation(body), Some(envelope)) = (&stored.entry.body, &stored.envelope) else {
+            continue;
+        };
+        if !digests.contains(&body.revoked_digest.as_str()) {
+            continue;
+        }
+        records.push(RevocationRecord {
+            log_index: i as u64,
+            integrated_time: stored.integrated_time.clone(),
+            revocation: envelope.clone(),
+            inclusion_proof: log.proof(&state, i as u64, tree_size)
+                .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e))?,
+        });
+    }
+    Ok(Json(records))
+}
+
+async fn get_proof(
+    State(log): State<LogServer>,
+    Query(query): Query<ProofQuery>,
+) -> Result<Json<InclusionProof>, ApiError> {
+    let state = log.state.lock().unwrap();
+    let tree_size = query.tree_size.unwrap_or(state.leaves.len() as u64);
+    log.proof(&state, query.log_index, tree_size)
+        .map(Json)
+        .map_err(|e| ApiError(StatusCode::NOT_FOUND, e))
+}
+
+async fn get_sth(
+    State(log): State<LogServer>,
+    Query(query): Query<SthQuery>,
+) -> Result<Json<SignedTreeHead>, ApiError> {
+    let state = log.state.lock().unwrap();
+    log.tree_head(&state, query.from_size)
+        .map(Json)
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))
+}
+
+async fn get_consistency(
+    State(log): State<LogServer>,
+    Query(query): Query<ConsistencyQuery>,
+) -> Result<Json<TreeConsistency>, ApiError> {
+    let state = log.state.lock().unwrap();
+    log.consistency(&state, query.from_size, query.to_size)
+        .map(Json)
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    /// Start a log on an ephemeral local port, returning its base URL
+    pub async fn spawn(dir: &Path, log_id: &str, key: &SigningKey) -> String {
+        let log = LogServer::open(dir, log_id, Signer::File(key.clone())).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(log.serve(listener));
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsse;
+    use ed25519_dalek::SigningKey;
+
+    fn envelope(n: u8) -> Envelope {
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "image", "digest": {"sha256": hex::encode([n; 32])}}],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {}
+        });
+        dsse::sign_statement(&statement, &Signer::File(SigningKey::from_bytes(&[1u8; 32])), "builder").unwrap()
+    }
+
+    async fn submit(client: &reqwest::Client, url: &str, envelope: &Envelope) -> SubmitResponse {
+        let request = SubmitRequest::Attestation { attestation: serde_json::to_vec(envelope).unwrap() };
+        client.post(format!("{}/api/v1/entries", url)).json(&request).send().await.unwrap()
+            .error_for_status().unwrap()
+            .json().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_submit_returns_verifiable_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::from_bytes(&[8u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let url = testing::spawn(dir.path(), "log-dev", &key).await;
+        let client = reqwest::Client::new();
+
+        submit(&client, &url, &envelope(1)).await;
+        let response = submit(&client, &url, &envelope(2)).await;
+        assert_eq!(response.entry.log_index, 1);
+
+        // SET covers the rebuilt entry
+        let entry = tlog::Entry::from_envelope(&envelope(2)).unwrap();
+        let leaf = entry.leaf_hash().unwrap();
+        let set = general_purpose::STANDARD.decode(&response.entry.signed_entry_timestamp).unwrap();
+        tlog::EntryTimestamp {
+            log_id: "log-dev".to_string(),
+            log_index: 1,
+            integrated_time: response.entry.integrated_time.clone(),
+            entry_hash: leaf,
+        }.verify(&public, &set).unwrap();
+
+        // Inclusion proof is against the signed tree head
+        let sth = &response.signed_tree_head;
+        sth.verify(&public).unwrap();
+        let proof = &response.entry.inclusion_proof;
+        assert!(sth.matches(&merkle::decode_hash(&proof.root_hash).unwrap(), proof.tree_size));
+        let hashes: Vec<_> = proof.hashes.iter().map(|h| merkle::decode_hash(h).unwrap()).collect();
+        merkle::verify_inclusion(&leaf, 1, proof.tree_size, &hashes, &sth.root().unwrap()).unwrap();
+
+        // Resubmitting is idempotent
+        let again = submit(&client, &url, &envelope(2)).await;
+        assert_eq!(again.entry.log_index, 1);
+        assert_eq!(again.entry.integrated_time, response.entry.integrated_time);
+
+        let bad = client.post(format!("{}/api/v1/entries", url))
+            .json(&serde_json::json!({"attestation": "bm90IGpzb24="}))
+            .send().await.unwrap();
+        assert_eq!(bad.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_proofs_and_consistency_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::from_bytes(&[8u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let client = reqwest::Client::new();
+
+        let url = testing::spawn(dir.path(), "log-dev", &key).await;
+        for n in 0..3 {
+            submit(&client, &url, &envelope(n)).await;
+        }
+        let old: SignedTreeHead = client.get(format!("{}/api/v1/sth", url)).send().await.unwrap()
+            .json().await.unwrap();
+
+        // A second instance over the same directory sees the same tree
+        let url = testing::spawn(dir.path(), "log-dev", &key).await;
+        for n in 3..7 {
+            submit(&client, &url, &envelope(n)).await;
+        }
+        let new: SignedTreeHead = client.get(format!("{}/api/v1/sth?fromSize=3", url)).send().await.unwrap()
+            .json().await.unwrap();
+        new.verify(&public).unwrap();
+        assert_eq!(new.tree_size, 7);
+
+        let proof = new.consistency_from(3).unwrap().decoded_hashes().unwrap();
+        merkle::verify_consistency(3, 7, &old.root().unwrap(), &new.root().unwrap(), &proof).unwrap();
+
+        let historical: InclusionProof = client.get(format!("{}/api/v1/proof?logIndex=1&treeSize=3", url))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(historical.root_hash, old.root_hash);
+
+        let missing = client.get(format!("{}/api/v1/proof?logIndex=9", url)).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+}
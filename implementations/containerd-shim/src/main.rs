@@ -6,13 +6,37 @@
 
 use anyhow::{Context, Result, bail};
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitCode};
-use tracing::{info, error, warn};
+use std::process::{Child, Command, ExitCode};
+use tracing::{Instrument, info, error, info_span, instrument, warn};
+use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
+mod audit;
+mod audit_query;
+mod audit_sink;
 mod bundle;
+mod cache;
+mod canonical;
+mod checkpoint;
+mod config;
+mod dsse;
+mod gossip;
+mod log_client;
+mod log_server;
+mod merkle;
+mod metrics;
+mod monitor;
+mod report;
+mod revocation;
+mod signing;
+mod sth;
+mod submit;
+mod telemetry;
+mod tiles;
+mod tlog;
 mod verify;
 
 use bundle::CtpBundle;
+use signing::Signer;
 use verify::{VerificationMode, verify_bundle};
 
 /// Exit codes as defined in runtime-integration.adoc Section 8.1
@@ -21,13 +45,36 @@
 const EXIT_MALFORMED: u8 = 2;  // Bundle malformed
 const EXIT_NETWORK_ERROR: u8 = 3;  // Network error (log unavailable)
 
+/// Producer command exit codes as defined in conformance/runner/cli-contract.json
+const EXIT_INVALID_INPUT: u8 = 1;  // Invalid input data
+const EXIT_COMMAND_ERROR: u8 = 2;  // Signing or output error
+
 #[tokio::main]
 async fn main() -> ExitCode {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+    // Initialize tracing; spans are also exported if the shim config asks
+    // for it. A broken config is reported where it is first loaded.
+    let telemetry = config::ShimConfig::load().ok()
+        .and_then(|config| telemetry::layer(&config.telemetry));
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(telemetry)
         .init();
 
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("sign") => return command_exit_code(cmd_sign(&args[2..])),
+        Some("verify") => return verify_exit_code(cmd_verify(&args[2..]).await),
+        Some("submit") => return command_exit_code(cmd_submit(&args[2..]).await),
+        Some("log-server") => return command_exit_code(cmd_log_server(&args[2..]).await),
+        Some("monitor") => return command_exit_code(cmd_monitor(&args[2..]).await),
+        Some("tile-proof") => return command_exit_code(cmd_tile_proof(&args[2..]).await),
+        Some("cache") => return command_exit_code(cmd_cache(&args[2..])),
+        Some("audit") => return command_exit_code(cmd_audit(&args[2..])),
+        Some("metrics") => return command_exit_code(cmd_metrics(&args[2..]).await),
+        Some("verify-audit-log") => return command_exit_code(cmd_verify_audit_log(&args[2..])),
+        _ => {}
+    }
+
     match run().await {
         Ok(()) => {
             info!("Container started successfully");
@@ -36,18 +83,448 @@ async fn main() -> ExitCode {
         Err(e) => {
             error!("Shim error: {:#}", e);
             // Determine exit code based on error type
-            let code = if e.to_string().contains("malformed") {
-                EXIT_MALFORMED
-            } else if e.to_string().contains("network") {
-                EXIT_NETWORK_ERROR
-            } else {
-                EXIT_VERIFY_FAILED
+            let code = match audit::error_code_of(&e).as_deref() {
+                Some("MALFORMED_BUNDLE") => EXIT_MALFORMED,
+                Some("NETWORK_ERROR") => EXIT_NETWORK_ERROR,
+                _ => EXIT_VERIFY_FAILED,
             };
             ExitCode::from(code)
         }
     }
 }
 
+/// Map a subcommand result to cli-contract.json exit codes
+fn command_exit_code(result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::from(EXIT_SUCCESS),
+        Err(e) => {
+            error!("Command failed: {:#}", e);
+            if format!("{:#}", e).contains("invalid input") {
+                ExitCode::from(EXIT_INVALID_INPUT)
+            } else {
+                ExitCode::from(EXIT_COMMAND_ERROR)
+            }
+        }
+    }
+}
+
+/// Map a `verify` result to cli-contract.json exit codes: ALLOW, REJECT
+/// (whatever the mode does with it), or it could not be verified at all
+fn verify_exit_code(result: Result<report::VerificationReport>) -> ExitCode {
+    match result {
+        Ok(report) if report.passed() => ExitCode::from(EXIT_SUCCESS),
+        Ok(report) if report.error_code.as_deref() == Some("NETWORK_ERROR") => ExitCode::from(EXIT_COMMAND_ERROR),
+        Ok(_) => ExitCode::from(EXIT_VERIFY_FAILED),
+        Err(e) => {
+            error!("Command failed: {:#}", e);
+            ExitCode::from(EXIT_COMMAND_ERROR)
+        }
+    }
+}
+
+/// Look up `--name value` or `--name=value` in command arguments
+fn flag_value(args: &[String], name: &str) -> Option<String> {
+    flag_values(args, name).into_iter().next()
+}
+
+/// Every value of a repeatable flag, in order
+fn flag_values(args: &[String], name: &str) -> Vec<String> {
+    let prefix = format!("{}=", name);
+    args.iter().enumerate().filter_map(|(i, arg)| {
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            Some(value.to_string())
+        } else if arg == name {
+            args.get(i + 1).cloned()
+        } else {
+            None
+        }
+    }).collect()
+}
+
+fn required_flag(args: &[String], name: &str) -> Result<String> {
+    flag_value(args, name).context(format!("invalid input: missing required argument {}", name))
+}
+
+/// `sign (--statement <file> | --revocation <file>) --key <file|pkcs11:uri> --output <file> [--keyid <id>]`
+///
+/// Signs an in-toto statement into a DSSE envelope (cli-contract.json `sign`).
+/// `--key` accepts an Ed25519 PKCS#8 PEM file or a PKCS#11 URI for HSM-held keys.
+/// With `--revocation`, signs a revocation body for `submit` instead; its
+/// key ID is always the revocation's `revokedBy`.
+fn cmd_sign(args: &[String]) -> Result<()> {
+    let key_ref = required_flag(args, "--key")?;
+    let output = required_flag(args, "--output")?;
+    let (input_path, revocation) = match (flag_value(args, "--statement"), flag_value(args, "--revocation")) {
+        (Some(path), None) => (path, false),
+        (None, Some(path)) => (path, true),
+        _ => bail!("invalid input: give exactly one of --statement and --revocation"),
+    };
+
+    let content = std::fs::read_to_string(&input_path)
+        .context(format!("invalid input: failed to read {}", input_path))?;
+
+    let signer = Signer::open(&key_ref)
+        .context("Failed to open signing key")?;
+
+    let envelope = if revocation {
+        let body: tlog::RevocationBody = serde_json::from_str(&content)
+            .context("invalid input: not a revocation body")?;
+        if flag_value(args, "--keyid").is_some_and(|keyid| keyid != body.revoked_by) {
+            bail!("invalid input: a revocation is signed as its revokedBy key {}", body.revoked_by);
+        }
+        revocation::sign_revocation(&body, &signer)?
+    } else {
+        let statement: serde_json::Value = serde_json::from_str(&content)
+            .context("invalid input: statement is not valid JSON")?;
+        let keyid = flag_value(args, "--keyid").unwrap_or_else(|| signer.key_id());
+        dsse::sign_statement(&statement, &signer, &keyid)?
+    };
+    std::fs::write(&output, serde_json::to_string_pretty(&envelope)?)
+        .context(format!("Failed to write DSSE envelope to {}", output))?;
+
+    info!("Signed {} with key {} -> {}", input_path, envelope.signatures[0].keyid, output);
+    Ok(())
+}
+
+/// `verify --bundle <file> --trust-store <file> [--image-digest <digest>] [--mode <mode>]
+///  [--tree-heads <dir>] [--format json|sarif|junit] [--output <file>]`
+///
+/// Verifies an attestation bundle (cli-contract.json `verify`) and prints the
+/// verification report.
+async fn cmd_verify(args: &[String]) -> Result<report::VerificationReport> {
+    let input = verify::AttestationInput {
+        path: PathBuf::from(required_flag(args, "--bundle")?),
+        image_digest: flag_value(args, "--image-digest"),
+        tree_heads: flag_value(args, "--tree-heads").map(PathBuf::from),
+    };
+    let trust_store = verify::TrustStore::load_from(Path::new(&required_flag(args, "--trust-store")?))?;
+    let mode = match flag_value(args, "--mode").as_deref() {
+        None | Some("strict") => VerificationMode::Strict,
+        Some("permissive") => VerificationMode::Permissive,
+        Some("audit") => VerificationMode::Audit,
+        Some(other) => bail!("invalid input: unknown verification mode {:?}", other),
+    };
+    let format = report::Format::parse(flag_value(args, "--format").as_deref().unwrap_or("json"))?;
+
+    let report = verify::verify_attestation_bundle(&input, &trust_store, mode).await?;
+    let rendered = report.render(format)?;
+    match flag_value(args, "--output") {
+        Some(path) => std::fs::write(&path, rendered)
+            .context(format!("Failed to write report to {}", path))?,
+        None => print!("{}", rendered),
+    }
+    Ok(report)
+}
+
+/// `submit --envelope <file> --log <logId>=<url>... --output <file> [--quorum <n>] [--sth-dir <dir>]`
+///
+/// Submits a DSSE envelope to every given log in parallel and writes the
+/// `logEntries` array for the attestation bundle once `--quorum` logs
+/// (default: the shim config's `logs.quorum`) have integrated it
+/// (transparency-log.adoc Section 8.1). With `--sth-dir`, each log's tree
+/// head is also written as `<logId>.proof` for the bundle's `signatures/logs`.
+async fn cmd_submit(args: &[String]) -> Result<()> {
+    let envelope_path = required_flag(args, "--envelope")?;
+    let output = required_flag(args, "--output")?;
+    let targets = flag_values(args, "--log")
+        .iter()
+        .map(|spec| submit::LogTarget::parse(spec))
+        .collect::<Result<Vec<_>>>()?;
+    if targets.is_empty() {
+        bail!("invalid input: missing required argument --log");
+    }
+
+    let config = config::ShimConfig::load()?.logs;
+    let quorum = match flag_value(args, "--quorum") {
+        Some(q) => q.parse().context("invalid input: --quorum must be a number")?,
+        None => config.quorum,
+    };
+
+    let content = std::fs::read_to_string(&envelope_path)
+        .context(format!("invalid input: failed to read envelope {}", envelope_path))?;
+    let envelope: dsse::Envelope = serde_json::from_str(&content)
+        .context("invalid input: envelope is not a valid DSSE envelope")?;
+
+    let receipts = submit::submit_to_quorum(
+        &envelope,
+        &targets,
+        quorum,
+        std::time::Duration::from_secs(config.timeout_secs),
+        config.retries,
+    ).await?;
+
+    let entries: Vec<_> = receipts.iter().map(|r| &r.entry).collect();
+    std::fs::write(&output, serde_json::to_string_pretty(&entries)?)
+        .context(format!("Failed to write log entries to {}", output))?;
+
+    if let Some(dir) = flag_value(args, "--sth-dir") {
+        std::fs::create_dir_all(&dir)
+            .context(format!("Failed to create {}", dir))?;
+        for receipt in &receipts {
+            let path = Path::new(&dir).join(format!("{}.proof", receipt.entry.log_id));
+            std::fs::write(&path, serde_json::to_string_pretty(&receipt.signed_tree_head)?)
+                .context(format!("Failed to write tree head {:?}", path))?;
+        }
+    }
+
+    info!("Entry integrated by {} of {} logs -> {}", receipts.len(), targets.len(), output);
+    Ok(())
+}
+
+/// `monitor --manifest <file> [--state-dir <dir>] [--output <file>]`
+///
+/// Walk new entries in every trust store log with a `url` and print an
+/// alert per unexpected use of the watched keys as a JSON line. A log that
+/// cannot be scanned does not stop the others; the command fails at the end.
+async fn cmd_monitor(args: &[String]) -> Result<()> {
+    let manifest = monitor::ReleaseManifest::load(&required_flag(args, "--manifest")?)?;
+    let state_dir = flag_value(args, "--state-dir")
+        .unwrap_or_else(|| "/var/lib/verified-container/monitor".to_string());
+
+    let trust_store = verify::TrustStore::load()?;
+    let config = config::ShimConfig::load()?.logs;
+    let store = checkpoint::CheckpointStore::open(state_dir);
+    let monitor = monitor::Monitor { manifest: &manifest, trust_store: &trust_store, store: &store };
+
+    let mut out: Box<dyn std::io::Write> = match flag_value(args, "--output") {
+        Some(path) => Box::new(std::fs::OpenOptions::new().create(true).append(true).open(&path)
+            .context(format!("Failed to open alert output {}", path))?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    let mut alerts = Vec::new();
+    let mut failed = Vec::new();
+    for (log_id, log) in &trust_store.logs {
+        let Some(url) = &log.url else {
+            warn!("No url for log {} in the trust store, not monitoring it", log_id);
+            continue;
+        };
+        let scanned = async {
+            let client = log_client::LogClient::new(
+                url,
+                std::time::Duration::from_secs(config.timeout_secs),
+                config.retries,
+            )?;
+            monitor.scan_log(log_id, &client, |alert| alerts.push(alert)).await
+        }.await;
+        if let Err(e) = scanned {
+            error!("Failed to monitor log {}: {:#}", log_id, e);
+            failed.push(log_id.as_str());
+        }
+
+        // Alerts found before a failure are still reported
+        for alert in alerts.drain(..) {
+            writeln!(out, "{}", serde_json::to_string(&alert)?)?;
+        }
+        out.flush()?;
+    }
+
+    if !failed.is_empty() {
+        bail!("Failed to monitor {} of {} logs: {}", failed.len(), trust_store.logs.len(), failed.join(", "));
+    }
+    Ok(())
+}
+
+/// Print inclusion and/or consistency proofs computed from a C2SP tiled log
+/// against its verified checkpoint
+async fn cmd_tile_proof(args: &[String]) -> Result<()> {
+    let config = config::ShimConfig::load()?.logs;
+    let source = tiles::TileSource::parse(
+        &required_flag(args, "--tiles")?,
+        std::time::Duration::from_secs(config.timeout_secs),
+    )?;
+    let verifier = tiles::NoteVerifier::parse(&required_flag(args, "--vkey")?)
+        .context("invalid input: bad --vkey")?;
+    let origin = flag_value(args, "--origin").unwrap_or_else(|| verifier.name.clone());
+    let (checkpoint, mut reader) = tiles::TileReader::open(source, &verifier, &origin).await?;
+
+    let hash_string = |h: &merkle::Hash| format!("sha256:{}", hex::encode(h));
+    let mut output = serde_json::json!({
+        "origin": checkpoint.origin,
+        "treeSize": checkpoint.tree_size,
+        "rootHash": hash_string(&checkpoint.root_hash),
+    });
+
+    if let Some(index) = flag_value(args, "--index") {
+        let log_index = index.parse().context("invalid input: --index must be a number")?;
+        let proof = log_server::InclusionProof {
+            log_index,
+            tree_size: checkpoint.tree_size,
+            root_hash: hash_string(&checkpoint.root_hash),
+            hashes: reader.inclusion_proof(log_index).await?.iter().map(hash_string).collect(),
+        };
+        output["inclusionProof"] = serde_json::to_value(proof)?;
+    }
+    if let Some(size) = flag_value(args, "--from-size") {
+        let from_size = size.parse().context("invalid input: --from-size must be a number")?;
+        let proof = sth::ConsistencyProof {
+            from_size,
+            hashes: reader.consistency_proof(from_size).await?.iter().map(hash_string).collect(),
+        };
+        output["consistency"] = serde_json::to_value(proof)?;
+    }
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// Operator commands for the verification cache
+fn cmd_cache(args: &[String]) -> Result<()> {
+    let config = config::ShimConfig::load()?;
+    let cache = cache::VerificationCache::from_env(&config.cache);
+    let json = args.iter().any(|a| a == "--json");
+    match args.first().map(String::as_str) {
+        Some("flush") => {
+            let digest = flag_value(args, "--digest");
+            let removed = cache.flush(digest.as_deref())?;
+            println!("Flushed {} cached verification results", removed);
+            Ok(())
+        }
+        Some("gc") => {
+            let report = cache.gc()?;
+            println!("Removed {} expired and evicted {} cached results", report.expired, report.evicted);
+            Ok(())
+        }
+        Some("stats") => {
+            let stats = cache.stats();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+                return Ok(());
+            }
+            println!("Trust store version: {}", stats.trust_store_version.as_deref().unwrap_or("-"));
+            println!("Entries: {} of {} ({} ALLOW, {} REJECT, {} expired)",
+                stats.entries, stats.max_entries, stats.allowed, stats.rejected, stats.expired);
+            println!("Size: {} of {} bytes", stats.bytes, stats.max_bytes);
+            Ok(())
+        }
+        Some("list") => {
+            let entries = cache.list();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+                return Ok(());
+            }
+            let time = |t: chrono::DateTime<chrono::Utc>| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            println!("{:<8} {:<20} {:<20} {:>6}  DIGEST", "DECISION", "VERIFIED", "EXPIRES", "BYTES");
+            for entry in entries {
+                let decision = match entry.decision {
+                    cache::Decision::Allow => "ALLOW",
+                    cache::Decision::Reject => "REJECT",
+                };
+                let verified_at = entry.verified_at.map_or("-".to_string(), time);
+                println!("{:<8} {:<20} {:<20} {:>6}  {}",
+                    decision, verified_at, time(entry.expires_at), entry.bytes, entry.digest);
+            }
+            Ok(())
+        }
+        Some(other) => bail!("invalid input: unknown cache command {:?}", other),
+        None => bail!("invalid input: missing cache command (flush, gc, stats, list)"),
+    }
+}
+
+fn cmd_verify_audit_log(args: &[String]) -> Result<()> {
+    let log = match flag_value(args, "--log") {
+        Some(path) => audit::AuditLog::open(path),
+        None => default_audit_log(),
+    };
+    match log.verify(flag_value(args, "--head").as_deref())? {
+        Some(chain) if chain.first_seq > 0 => println!(
+            "Audit log intact: records {} to {} (earlier records rotated out), head {}",
+            chain.first_seq, chain.head.seq, chain.head.hash
+        ),
+        Some(chain) => println!("Audit log intact: {} records, head {}", chain.head.seq + 1, chain.head.hash),
+        None => println!("Audit log is empty"),
+    }
+    Ok(())
+}
+
+/// Operator commands for reading the audit log
+fn cmd_audit(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("query") => cmd_audit_query(&args[1..]),
+        Some(other) => bail!("invalid input: unknown audit command {:?}", other),
+        None => bail!("invalid input: missing audit command (query)"),
+    }
+}
+
+fn cmd_audit_query(args: &[String]) -> Result<()> {
+    let log = match flag_value(args, "--log") {
+        Some(path) => audit::AuditLog::open(path),
+        None => default_audit_log(),
+    };
+    let now = chrono::Utc::now();
+    let list = |flag: &str| flag_values(args, flag).join(",");
+    let query = audit_query::AuditQuery {
+        since: flag_value(args, "--since").map(|t| audit_query::parse_time(&t, now)).transpose()?,
+        until: flag_value(args, "--until").map(|t| audit_query::parse_time(&t, now)).transpose()?,
+        outcomes: match list("--outcome") {
+            outcomes if outcomes.is_empty() => Vec::new(),
+            outcomes => audit_query::parse_list(&outcomes.to_uppercase().replace('-', "_"), "outcome")?,
+        },
+        modes: match list("--mode") {
+            modes if modes.is_empty() => Vec::new(),
+            modes => audit_query::parse_list(&modes.to_lowercase(), "mode")?,
+        },
+        digest: flag_value(args, "--digest"),
+        keyid: flag_value(args, "--key"),
+        error_code: flag_value(args, "--error-code"),
+    };
+    let format = audit_query::Format::parse(flag_value(args, "--format").as_deref().unwrap_or("table"))?;
+    let group_by = flag_value(args, "--group-by").map(|g| audit_query::GroupBy::parse(&g)).transpose()?;
+
+    let (records, skipped) = query.run(&log)?;
+    if skipped > 0 {
+        warn!("Skipped {} lines of {:?} that are not audit records", skipped, log.path());
+    }
+    let output = match group_by {
+        Some(group_by) => audit_query::render_summary(&audit_query::summarize(&records, group_by), format)?,
+        None => audit_query::render_records(&records, format)?,
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+/// Operator commands for Prometheus metrics
+async fn cmd_metrics(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("serve") => {
+            let config = config::ShimConfig::load()?.metrics;
+            let listen = flag_value(args, "--listen").or(config.listen)
+                .ok_or_else(|| anyhow::anyhow!("invalid input: set --listen or metrics.listen in the shim config"))?;
+            let listener = tokio::net::TcpListener::bind(&listen).await
+                .context(format!("Failed to listen on {}", listen))?;
+            metrics::serve(config.state, listener).await
+        }
+        Some(other) => bail!("invalid input: unknown metrics command {:?}", other),
+        None => bail!("invalid input: missing metrics command (serve)"),
+    }
+}
+
+/// The first file sink in the shim config, or the default audit log
+fn default_audit_log() -> audit::AuditLog {
+    audit_sink::Auditor::load().file_logs().next().cloned()
+        .unwrap_or_else(|| audit::AuditLog::new(&audit::AuditLog::default_config()))
+}
+
+/// `log-server --dir <dir> --key <file|pkcs11:uri> --log-id <id> [--listen <addr>]`
+///
+/// Runs a file-backed transparency log implementing the transparency-log.adoc
+/// Section 7 API, for development and integration testing.
+async fn cmd_log_server(args: &[String]) -> Result<()> {
+    let dir = required_flag(args, "--dir")?;
+    let key_ref = required_flag(args, "--key")?;
+    let log_id = required_flag(args, "--log-id")?;
+    let listen = flag_value(args, "--listen").unwrap_or_else(|| "127.0.0.1:3000".to_string());
+
+    let signer = Signer::open(&key_ref)
+        .context("Failed to open log signing key")?;
+    info!("Log {} public key: {}", log_id, hex::encode(signer.public_key()));
+
+    let log = log_server::LogServer::open(Path::new(&dir), &log_id, signer)?;
+    let listener = tokio::net::TcpListener::bind(&listen).await
+        .context(format!("Failed to listen on {}", listen))?;
+    log.serve(listener).await
+}
+
 async fn run() -> Result<()> {
     // 1. Parse command-line arguments
     let args: Vec<String> = std::env::args().collect();
@@ -62,9 +539,36 @@ async fn run() -> Result<()> {
     info!("Processing .ctp bundle: {:?}", bundle_path);
     info!("Verification mode: {:?}", verify_mode);
 
+    // 2-5. Load, verify and extract the bundle, then start the runtime
+    let span = info_span!("start_container", bundle = %bundle_path.display(), mode = ?verify_mode);
+    let started = async {
+        let oci_dir = prepare_bundle(&bundle_path, verify_mode).await?;
+        delegate_to_runtime(&oci_dir)
+    }.instrument(span).await;
+
+    // The runtime runs until the container exits, so publish metrics and
+    // traces first
+    if let Err(e) = config::ShimConfig::load().and_then(|config| metrics::publish(&config.metrics)) {
+        warn!("Failed to publish metrics: {:#}", e);
+    }
+    telemetry::flush().await;
+
+    wait_for_runtime(started?)
+}
+
+/// Load and verify a bundle, then extract its OCI layout for the runtime
+async fn prepare_bundle(bundle_path: &Path, verify_mode: VerificationMode) -> Result<PathBuf> {
     // 2. Load and parse .ctp bundle
-    let ctp_bundle = CtpBundle::load(&bundle_path)
-        .context("Failed to load .ctp bundle - malformed bundle")?;
+    let started = std::time::Instant::now();
+    let loaded = CtpBundle::load(bundle_path).context("MALFORMED_BUNDLE: failed to load .ctp bundle");
+    metrics::observe_step("load_bundle", started.elapsed());
+    let ctp_bundle = match loaded {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            verify::record_load_failure(bundle_path, verify_mode, &e);
+            return Err(e);
+        }
+    };
 
     info!("Bundle loaded: {} (version {})",
         ctp_bundle.manifest.name,
@@ -81,7 +585,7 @@ async fn run() -> Result<()> {
 
             match verify_mode {
                 VerificationMode::Strict => {
-                    bail!("Verification failed in strict mode: {}", e);
+                    return Err(e.context("Verification failed in strict mode"));
                 }
                 VerificationMode::Permissive => {
                     warn!("Verification failed in permissive mode, continuing anyway");
@@ -94,15 +598,15 @@ async fn run() -> Result<()> {
     }
 
     // 4. Extract OCI image to temporary location
+    let started = std::time::Instant::now();
     let oci_dir = ctp_bundle.extract_oci_layout()
-        .context("Failed to extract OCI layout")?;
+        .context("Failed to extract OCI layout");
+    metrics::observe_step("extract_oci", started.elapsed());
+    let oci_dir = oci_dir?;
 
     info!("OCI layout extracted to: {:?}", oci_dir);
 
-    // 5. Delegate to runc/crun
-    delegate_to_runtime(&oci_dir)?;
-
-    Ok(())
+    Ok(oci_dir)
 }
 
 fn parse_verify_mode(args: &[String]) -> Result<VerificationMode> {
@@ -121,7 +625,9 @@ fn parse_verify_mode(args: &[String]) -> Result<VerificationMode> {
     Ok(VerificationMode::Strict)
 }
 
-fn delegate_to_runtime(oci_dir: &Path) -> Result<()> {
+/// Start the OCI runtime on the extracted layout
+#[instrument(skip_all, err(level = "debug"), fields(runtime = tracing::field::Empty, pid = tracing::field::Empty))]
+fn delegate_to_runtime(oci_dir: &Path) -> Result<Child> {
     // SECURITY: Validate OCI runtime against allowlist to prevent command injection
     let runtime = std::env::var("OCI_RUNTIME").unwrap_or_else(|_| "runc".to_string());
 
@@ -136,13 +642,23 @@ fn delegate_to_runtime(oci_dir: &Path) -> Result<()> {
     }
 
     info!("Delegating to OCI runtime: {}", runtime);
+    let span = tracing::Span::current();
+    span.record("runtime", runtime.as_str());
 
     // Use runtime name directly (simple validation above prevents injection)
-    let status = Command::new(&runtime)
+    let child = Command::new(&runtime)
         .arg("run")
         .arg(oci_dir)
-        .status()
+        .spawn()
         .context(format!("Failed to execute {}", runtime))?;
+    span.record("pid", child.id());
+
+    Ok(child)
+}
+
+fn wait_for_runtime(mut child: Child) -> Result<()> {
+    let status = child.wait()
+        .context("Failed to wait for the OCI runtime")?;
 
     if !status.success() {
         bail!("OCI runtime exited with status: {}", status);
@@ -164,6 +680,22 @@ fn test_parse_verify_mode() {
         assert!(matches!(parse_verify_mode(&args).unwrap(), VerificationMode::Permissive));
     }
 
+    #[test]
+    fn test_flag_values_repeat() {
+        let args: Vec<String> = ["--log", "a=x", "--output", "o", "--log=b=y"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(flag_values(&args, "--log"), vec!["a=x", "b=y"]);
+        assert_eq!(flag_value(&args, "--log").as_deref(), Some("a=x"));
+    }
+
+    #[test]
+    fn test_flag_value_forms() {
+        let args: Vec<String> = ["--statement", "s.json", "--key=pkcs11:object=k"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(flag_value(&args, "--statement").as_deref(), Some("s.json"));
+        assert_eq!(flag_value(&args, "--key").as_deref(), Some("pkcs11:object=k"));
+        assert!(required_flag(&args, "--output").is_err());
+    }
+
     #[test]
     fn test_default_mode_is_strict() {
         let args = vec!["shim".to_string()];
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Merkle tree hashing and proofs (transparency-log.adoc Section 5.1, RFC 9162 Section 2.1)
+
+use anyhow::{Context, Result, bail};
+use sha2::{Sha256, Digest};
+
+/// SHA-256 Merkle tree hash
+pub type Hash = [u8; 32];
+
+/// Leaf hash: `SHA256(0x00 || entry)`
+pub fn leaf_hash(entry: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(entry);
+    hasher.finalize().into()
+}
+
+/// Interior node hash: `SHA256(0x01 || left || right)`
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Decode a hex hash, accepting the optional `sha256:` prefix allowed by the schemas
+pub fn decode_hash(s: &str) -> Result<Hash> {
+    let hex_str = s.strip_prefix("sha256:").unwrap_or(s);
+    let bytes = hex::decode(hex_str)
+        .context(format!("Invalid hex hash: {}", s))?;
+    bytes.as_slice().try_into()
+        .context(format!("Hash must be 32 bytes, got {}", bytes.len()))
+}
+
+/// Verify an inclusion proof for `leaf` at `index` in a tree of `tree_size`
+/// leaves with the given `root` (RFC 9162 Section 2.1.3.2)
+pub fn verify_inclusion(leaf: &Hash, index: u64, tree_size: u64, proof: &[Hash], root: &Hash) -> Result<()> {
+    let computed = root_from_inclusion_proof(leaf, index, tree_size, proof)?;
+
+    if computed != *root {
+        bail!("Merkle proof verification failed: computed root {} != expected {}",
+            hex::encode(computed), hex::encode(root));
+    }
+
+    Ok(())
+}
+
+/// Recompute the tree root from a leaf hash and its inclusion proof
+pub fn root_from_inclusion_proof(leaf: &Hash, index: u64, tree_size: u64, proof: &[Hash]) -> Result<Hash> {
+    if index >= tree_size {
+        bail!("Invalid proof: log_index ({}) >= tree_size ({})", index, tree_size);
+    }
+
+    let mut f_n = index;
+    let mut s_n = tree_size - 1;
+    let mut r = *leaf;
+
+    for p in proof {
+        if s_n == 0 {
+            bail!("Inclusion proof too long: {} hashes for index {} in tree of size {}",
+                proof.len(), index, tree_size);
+        }
+
+        if f_n & 1 == 1 || f_n == s_n {
+            r = node_hash(p, &r);
+            if f_n & 1 == 0 {
+                // Skip levels where this node is the rightmost, unpaired child
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    if s_n != 0 {
+        bail!("Inclusion proof too short: {} hashes for index {} in tree of size {}",
+            proof.len(), index, tree_size);
+    }
+
+    Ok(r)
+}
+
+/// Verify that a tree of `second_size` with `second_root` is an append-only
+/// extension of a tree of `first_size` with `first_root` (RFC 9162 Section 2.1.4.2)
+pub fn verify_consistency(
+    first_size: u64,
+    second_size: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> Result<()> {
+    if first_size > second_size {
+        bail!("Consistency proof from size {} to smaller size {}", first_size, second_size);
+    }
+    if first_size == second_size {
+        if !proof.is_empty() {
+            bail!("Consistency proof between equal tree sizes must be empty");
+        }
+        if first_root != second_root {
+            bail!("Tree heads of equal size {} have different roots", first_size);
+        }
+        return Ok(());
+    }
+    if first_size == 0 {
+        // Every tree is consistent with the empty tree
+        if !proof.is_empty() {
+            bail!("Consistency proof from the empty tree must be empty");
+        }
+        return Ok(());
+    }
+    if proof.is_empty() {
+        bail!("Empty consistency proof from size {} to {}", first_size, second_size);
+    }
+
+    // When the first tree is a complete subtree its root is the path's implicit first node
+    let mut path = Vec::with_capacity(proof.len() + 1);
+    if first_size.is_power_of_two() {
+        path.push(*first_root);
+    }
+    path.extend_from_slice(proof);
+
+    let mut f_n = first_size - 1;
+    let mut s_n = second_size - 1;
+    while f_n & 1 == 1 {
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    let mut f_r = path[0];
+    let mut s_r = path[0];
+
+    for c in &path[1..] {
+        if s_n == 0 {
+            bail!("Consistency proof too long: {} hashes from size {} to {}",
+                proof.len(), first_size, second_size);
+        }
+
+        if f_n & 1 == 1 || f_n == s_n {
+            f_r = node_hash(c, &f_r);
+            s_r = node_hash(c, &s_r);
+            if f_n & 1 == 0 {
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            }
+        } else {
+            s_r = node_hash(&s_r, c);
+        }
+
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    if s_n != 0 {
+        bail!("Consistency proof too short: {} hashes from size {} to {}",
+            proof.len(), first_size, second_size);
+    }
+    if f_r != *first_root {
+        bail!("Consistency proof does not reproduce the old root {}", hex::encode(first_root));
+    }
+    if s_r != *second_root {
+        bail!("Consistency proof does not reproduce the new root {}", hex::encode(second_root));
+    }
+
+    Ok(())
+}
+
+/// Largest power of two strictly less than n
+pub(crate) fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// MTH(D[n]) from RFC 9162 Section 2.1.1
+pub fn tree_root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&tree_root(&leaves[..k]), &tree_root(&leaves[k..]))
+        }
+    }
+}
+
+/// PATH(m, D[n]) from RFC 9162 Section 2.1.3.1
+pub fn inclusion_proof(index: u64, leaves: &[Hash]) -> Vec<Hash> {
+    fn path(m: usize, leaves: &[Hash]) -> Vec<Hash> {
+        let n = leaves.len();
+        if n <= 1 {
+            return vec![];
+        }
+        let k = split(n);
+        if m < k {
+            let mut p = path(m, &leaves[..k]);
+            p.push(tree_root(&leaves[k..]));
+            p
+        } else {
+            let mut p = path(m - k, &leaves[k..]);
+            p.push(tree_root(&leaves[..k]));
+            p
+        }
+    }
+    path(index as usize, leaves)
+}
+
+/// PROOF(m, D[n]) from RFC 9162 Section 2.1.4.1, where `leaves` is D[n]
+pub fn consistency_proof(first_size: u64, leaves: &[Hash]) -> Vec<Hash> {
+    fn subproof(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+        let n = leaves.len();
+        if m == n {
+            return if complete { vec![] } else { vec![tree_root(leaves)] };
+        }
+        let k = split(n);
+        if m <= k {
+            let mut p = subproof(m, &leaves[..k], complete);
+            p.push(tree_root(&leaves[k..]));
+            p
+        } else {
+            let mut p = subproof(m - k, &leaves[k..], false);
+            p.push(tree_root(&leaves[..k]));
+            p
+        }
+    }
+
+    if first_size == 0 || first_size as usize == leaves.len() {
+        return vec![];
+    }
+    subproof(first_size as usize, leaves, true)
+}
+
+/// In-memory tree of synthetic leaves for tests
+#[cfg(test)]
+pub mod reference {
+    use super::*;
+
+    pub struct ReferenceTree {
+        pub leaves: Vec<Hash>,
+    }
+
+    impl ReferenceTree {
+        pub fn new(size: u64) -> Self {
+            Self {
+                leaves: (0..size).map(|i| leaf_hash(format!("entry-{}", i).as_bytes())).collect(),
+            }
+        }
+
+        pub fn root(&self) -> Hash {
+            tree_root(&self.leaves)
+        }
+
+        pub fn inclusion_proof(&self, index: u64) -> Vec<Hash> {
+            inclusion_proof(index, &self.leaves)
+        }
+
+        /// Root of the tree restricted to its first `size` leaves
+        pub fn root_at(&self, size: u64) -> Hash {
+            tree_root(&self.leaves[..size as usize])
+        }
+
+        pub fn consistency_proof(&self, first_size: u64, second_size: u64) -> Vec<Hash> {
+            consistency_proof(first_size, &self.leaves[..second_size as usize])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::reference::ReferenceTree;
+    use proptest::prelude::*;
+
+    /// Tree size and a leaf index within it
+    fn size_and_index() -> impl Strategy<Value = (u64, u64)> {
+        (1u64..300).prop_flat_map(|size| (Just(size), 0..size))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_valid_proofs_verify((size, index) in size_and_index()) {
+            let tree = ReferenceTree::new(size);
+            let proof = tree.inclusion_proof(index);
+            prop_assert!(verify_inclusion(&tree.leaves[index as usize], index, size, &proof, &tree.root()).is_ok());
+        }
+
+        #[test]
+        fn prop_tampered_proof_rejected((size, index) in size_and_index(), pick in any::<prop::sample::Index>(), bit in 0usize..256) {
+            let tree = ReferenceTree::new(size);
+            let mut proof = tree.inclusion_proof(index);
+            prop_assume!(!proof.is_empty());
+            let i = pick.index(proof.len());
+            proof[i][bit / 8] ^= 1 << (bit % 8);
+            prop_assert!(verify_inclusion(&tree.leaves[index as usize], index, size, &proof, &tree.root()).is_err());
+        }
+
+        #[test]
+        fn prop_wrong_leaf_rejected((size, index) in size_and_index(), other in any::<prop::sample::Index>()) {
+            let tree = ReferenceTree::new(size);
+            let other = other.index(size as usize);
+            prop_assume!(other as u64 != index);
+            let proof = tree.inclusion_proof(index);
+            prop_assert!(verify_inclusion(&tree.leaves[other], index, size, &proof, &tree.root()).is_err());
+            // The claimed index must also match the leaf's position
+            prop_assert!(verify_inclusion(&tree.leaves[index as usize], other as u64, size, &proof, &tree.root()).is_err());
+        }
+
+        #[test]
+        fn prop_wrong_length_rejected((size, index) in size_and_index()) {
+            let tree = ReferenceTree::new(size);
+            let proof = tree.inclusion_proof(index);
+            let leaf = tree.leaves[index as usize];
+
+            let mut longer = proof.clone();
+            longer.push([0u8; 32]);
+            prop_assert!(root_from_inclusion_proof(&leaf, index, size, &longer).is_err());
+
+            if !proof.is_empty() {
+                let shorter = &proof[..proof.len() - 1];
+                prop_assert!(root_from_inclusion_proof(&leaf, index, size, shorter).is_err());
+            }
+        }
+    }
+
+    /// Two tree sizes with first <= second
+    fn size_pair() -> impl Strategy<Value = (u64, u64)> {
+        (1u64..300).prop_flat_map(|second| (0..=second, Just(second)))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_valid_consistency_proofs_verify((first, second) in size_pair()) {
+            let tree = ReferenceTree::new(second);
+            let proof = tree.consistency_proof(first, second);
+            prop_assert!(verify_consistency(first, second, &tree.root_at(first), &tree.root(), &proof).is_ok());
+        }
+
+        #[test]
+        fn prop_forked_tree_detected((first, second) in size_pair(), fork_at in any::<prop::sample::Index>()) {
+            prop_assume!(first > 0);
+            let tree = ReferenceTree::new(second);
+
+            // A log that rewrote one of the first `first` entries after publishing
+            let mut forked = ReferenceTree::new(second);
+            forked.leaves[fork_at.index(first as usize)] = leaf_hash(b"rewritten");
+            let proof = forked.consistency_proof(first, second);
+
+            prop_assert!(verify_consistency(first, second, &tree.root_at(first), &forked.root(), &proof).is_err());
+        }
+
+        #[test]
+        fn prop_tampered_consistency_proof_rejected((first, second) in size_pair(), pick in any::<prop::sample::Index>()) {
+            let tree = ReferenceTree::new(second);
+            let mut proof = tree.consistency_proof(first, second);
+            prop_assume!(!proof.is_empty());
+            let i = pick.index(proof.len());
+            proof[i][0] ^= 0x80;
+            prop_assert!(verify_consistency(first, second, &tree.root_at(first), &tree.root(), &proof).is_err());
+
+            let mut longer = tree.consistency_proof(first, second);
+            longer.push([0u8; 32]);
+            prop_assert!(verify_consistency(first, second, &tree.root_at(first), &tree.root(), &longer).is_err());
+        }
+    }
+
+    #[test]
+    fn test_equal_size_consistency() {
+        let tree = ReferenceTree::new(5);
+        assert!(verify_consistency(5, 5, &tree.root(), &tree.root(), &[]).is_ok());
+        assert!(verify_consistency(5, 5, &tree.root(), &[0u8; 32], &[]).is_err());
+        assert!(verify_consistency(6, 5, &tree.root(), &tree.root(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_index_out_of_range() {
+        let tree = ReferenceTree::new(4);
+        assert!(verify_inclusion(&tree.leaves[0], 4, 4, &[], &tree.root()).is_err());
+    }
+
+    #[test]
+    fn test_single_leaf_tree() {
+        let tree = ReferenceTree::new(1);
+        assert_eq!(tree.root(), leaf_hash(b"entry-0"));
+        assert!(verify_inclusion(&tree.leaves[0], 0, 1, &[], &tree.root()).is_ok());
+    }
+
+    #[test]
+    fn test_first_proof_hash_is_not_trusted_as_leaf() {
+        // The old verifier accepted hashes[0] as the leaf; a proof whose "first
+        // hash" is an arbitrary value must not verify for an unrelated entry
+        let tree = ReferenceTree::new(8);
+        let mut proof = vec![tree.leaves[5]];
+        proof.extend(tree.inclusion_proof(5));
+        assert!(verify_inclusion(&leaf_hash(b"attacker"), 5, 8, &proof, &tree.root()).is_err());
+    }
+
+    #[test]
+    fn test_decode_hash_accepts_prefix() {
+        let hex_hash = "ab".repeat(32);
+        assert_eq!(decode_hash(&hex_hash).unwrap(), [0xab; 32]);
+        assert_eq!(decode_hash(&format!("sha256:{}", hex_hash)).unwrap(), [0xab; 32]);
+        assert!(decode_hash("abcd").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Prometheus metrics for verification outcomes and latency
+//
+// The shim runs once per container, so a process only sees one decision.
+// Its counts are added to a state file shared by every run on the host;
+// `publish` writes the totals in the text exposition format for the
+// node-exporter textfile collector, and `serve` exposes them over HTTP.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+use crate::audit::AuditRecord;
+use crate::config::MetricsConfig;
+
+/// Upper bounds of the step latency buckets, in seconds
+const BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Metrics recorded by this process and not yet published
+static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(|| Mutex::new(Metrics::default()));
+
+#[derive(Debug, Clone, Copy)]
+pub enum CacheLookup {
+    /// A cached decision was used
+    Hit,
+    /// Nothing usable was cached, or the cached result was revoked
+    Miss,
+    /// A cached record failed authentication
+    Invalid,
+}
+
+impl CacheLookup {
+    fn as_str(self) -> &'static str {
+        match self {
+            CacheLookup::Hit => "hit",
+            CacheLookup::Miss => "miss",
+            CacheLookup::Invalid => "invalid",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Histogram {
+    /// Observations per bucket of BUCKETS, then above the last bound
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self { buckets: vec![0; BUCKETS.len() + 1], sum: 0.0, count: 0 }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        let bucket = BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        // Totals kept with other bucket bounds cannot be combined; start over
+        if self.buckets.len() != other.buckets.len() {
+            *self = Histogram::default();
+        }
+        for (total, n) in self.buckets.iter_mut().zip(&other.buckets) {
+            *total += n;
+        }
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Metrics {
+    /// Decisions by outcome, then error code ("" for none)
+    decisions: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Cache lookups by result
+    cache_lookups: BTreeMap<String, u64>,
+    /// Latency by verification step
+    steps: BTreeMap<String, Histogram>,
+}
+
+impl Metrics {
+    fn record_decision(&mut self, record: &AuditRecord) {
+        let outcome = serde_json::to_value(record.outcome).ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let code = record.error_code.clone().unwrap_or_default();
+        *self.decisions.entry(outcome).or_default().entry(code).or_default() += 1;
+    }
+
+    fn record_cache_lookup(&mut self, result: CacheLookup) {
+        *self.cache_lookups.entry(result.as_str().to_string()).or_default() += 1;
+    }
+
+    fn observe_step(&mut self, step: &str, elapsed: Duration) {
+        self.steps.entry(step.to_string()).or_default().observe(elapsed.as_secs_f64());
+    }
+
+    pub fn merge(&mut self, other: &Metrics) {
+        for (outcome, codes) in &other.decisions {
+            let totals = self.decisions.entry(outcome.clone()).or_default();
+            for (code, n) in codes {
+                *totals.entry(code.clone()).or_default() += n;
+            }
+        }
+        for (result, n) in &other.cache_lookups {
+            *self.cache_lookups.entry(result.clone()).or_default() += n;
+        }
+        for (step, histogram) in &other.steps {
+            self.steps.entry(step.clone()).or_default().merge(histogram);
+        }
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP verified_container_decisions_total Verification decisions by outcome and error code.\n");
+        out.push_str("# TYPE verified_container_decisions_total counter\n");
+        for (outcome, codes) in &self.decisions {
+            for (code, n) in codes {
+                out.push_str(&format!(
+                    "verified_container_decisions_total{{outcome=\"{}\",error_code=\"{}\"}} {}\n",
+                    escape(outcome), escape(code), n
+                ));
+            }
+        }
+
+        out.push_str("# HELP verified_container_cache_lookups_total Verification cache lookups by result.\n");
+        out.push_str("# TYPE verified_container_cache_lookups_total counter\n");
+        for (result, n) in &self.cache_lookups {
+            out.push_str(&format!("verified_container_cache_lookups_total{{result=\"{}\"}} {}\n", escape(result), n));
+        }
+
+        out.push_str("# HELP verified_container_step_duration_seconds Time spent in each verification step.\n");
+        out.push_str("# TYPE verified_container_step_duration_seconds histogram\n");
+        for (step, histogram) in &self.steps {
+            let step = escape(step);
+            let bounds = BUCKETS.iter().map(f64::to_string).chain(["+Inf".to_string()]);
+            let mut cumulative = 0;
+            for (bound, n) in bounds.zip(&histogram.buckets) {
+                cumulative += n;
+                out.push_str(&format!(
+                    "verified_container_step_duration_seconds_bucket{{step=\"{}\",le=\"{}\"}} {}\n",
+                    step, bound, cumulative
+                ));
+            }
+            out.push_str(&format!("verified_container_step_duration_seconds_sum{{step=\"{}\"}} {}\n", step, histogram.sum));
+            out.push_str(&format!("verified_container_step_duration_seconds_count{{step=\"{}\"}} {}\n", step, histogram.count));
+        }
+        out
+    }
+
+    /// Totals in the state file; none if it does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .context(format!("Failed to parse metrics state {:?}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).context(format!("Failed to read metrics state {:?}", path)),
+        }
+    }
+}
+
+/// Label values may not contain unescaped backslashes, quotes or newlines
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn with_metrics(update: impl FnOnce(&mut Metrics)) {
+    update(&mut METRICS.lock().unwrap_or_else(|e| e.into_inner()));
+}
+
+/// Count the decision in an audit record
+pub fn record_decision(record: &AuditRecord) {
+    with_metrics(|m| m.record_decision(record));
+}
+
+pub fn record_cache_lookup(result: CacheLookup) {
+    with_metrics(|m| m.record_cache_lookup(result));
+}
+
+pub fn observe_step(step: &str, elapsed: Duration) {
+    with_metrics(|m| m.observe_step(step, elapsed));
+}
+
+/// Add this process's metrics to the host totals and rewrite the textfile.
+/// Does nothing unless metrics are configured.
+pub fn publish(config: &MetricsConfig) -> Result<()> {
+    if !config.enabled() {
+        return Ok(());
+    }
+    let metrics = std::mem::take(&mut *METRICS.lock().unwrap_or_else(|e| e.into_inner()));
+    publish_metrics(config, &metrics)
+}
+
+fn publish_metrics(config: &MetricsConfig, metrics: &Metrics) -> Result<()> {
+    let dir = config.state.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).context("Failed to create metrics state directory")?;
+    let mut lock_path = config.state.clone().into_os_string();
+    lock_path.push(".lock");
+    let lock = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(PathBuf::from(lock_path))
+        .context("Failed to open metrics state lock")?;
+    lock.lock().context("Failed to lock metrics state")?;
+
+    let mut totals = Metrics::load(&config.state)?;
+    totals.merge(metrics);
+    write_atomic(&config.state, serde_json::to_string(&totals)?.as_bytes())
+        .context(format!("Failed to write metrics state {:?}", config.state))?;
+    if let Some(textfile) = &config.textfile {
+        // The collector reads the file at any time, so it must never be partial
+        write_atomic(textfile, totals.render().as_bytes())
+            .context(format!("Failed to write metrics textfile {:?}", textfile))?;
+    }
+    Ok(())
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(bytes)?;
+    tmp.persist(path)?;
+    Ok(())
+}
+
+/// Serve the host totals at `/metrics` until the task is dropped
+pub async fn serve(state: PathBuf, listener: tokio::net::TcpListener) -> Result<()> {
+    use axum::{Router, extract::State, http::{StatusCode, header}, routing::get};
+
+    async fn scrape(State(state): State<PathBuf>) -> (StatusCode, [(header::HeaderName, &'static str); 1], String) {
+        let content_type = [(header::CONTENT_TYPE, "text/plain; version=0.0.4")];
+        match Metrics::load(&state) {
+            Ok(metrics) => (StatusCode::OK, content_type, metrics.render()),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, content_type, format!("{:#}\n", e)),
+        }
+    }
+
+    info!("Serving metrics from {:?} on {}", state, listener.local_addr()?);
+    let app = Router::new().route("/metrics", get(scrape)).with_state(state);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditTrail, Outcome};
+    use crate::verify::VerificationMode;
+
+    fn run_metrics(outcome: Outcome, error: Option<&str>, lookup: CacheLookup, step_ms: u64) -> Metrics {
+        let mut record = AuditRecord::new("app", "sha256:aa", VerificationMode::Strict, outcome, AuditTrail::default());
+        if let Some(error) = error {
+            record = record.with_error(&anyhow::anyhow!("{}", error));
+        }
+        let mut metrics = Metrics::default();
+        metrics.record_decision(&record);
+        metrics.record_cache_lookup(lookup);
+        metrics.observe_step("signatures", Duration::from_millis(step_ms));
+        metrics
+    }
+
+    #[test]
+    fn test_runs_accumulate_in_the_textfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MetricsConfig {
+            state: dir.path().join("state/metrics.json"),
+            textfile: Some(dir.path().join("verified_container.prom")),
+            listen: None,
+        };
+        publish_metrics(&config, &run_metrics(Outcome::Allow, None, CacheLookup::Miss, 3)).unwrap();
+        publish_metrics(&config, &run_metrics(Outcome::Allow, None, CacheLookup::Hit, 40)).unwrap();
+        publish_metrics(&config, &run_metrics(Outcome::Reject, Some("INVALID_SIGNATURE: bad signature"), CacheLookup::Miss, 2000)).unwrap();
+
+        let text = std::fs::read_to_string(config.textfile.as_ref().unwrap()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        for expected in [
+            "verified_container_decisions_total{outcome=\"ALLOW\",error_code=\"\"} 2",
+            "verified_container_decisions_total{outcome=\"REJECT\",error_code=\"INVALID_SIGNATURE\"} 1",
+            "verified_container_cache_lookups_total{result=\"hit\"} 1",
+            "verified_container_cache_lookups_total{result=\"miss\"} 2",
+            "verified_container_step_duration_seconds_bucket{step=\"signatures\",le=\"0.005\"} 1",
+            "verified_container_step_duration_seconds_bucket{step=\"signatures\",le=\"0.05\"} 2",
+            "verified_container_step_duration_seconds_bucket{step=\"signatures\",le=\"2.5\"} 3",
+            "verified_container_step_duration_seconds_bucket{step=\"signatures\",le=\"+Inf\"} 3",
+            "verified_container_step_duration_seconds_count{step=\"signatures\"} 3",
+        ] {
+            assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, text);
+        }
+        assert_eq!(Metrics::load(&config.state).unwrap().render(), text);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_served_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MetricsConfig { state: dir.path().join("metrics.json"), textfile: None, listen: None };
+        publish_metrics(&config, &run_metrics(Outcome::WarnAllowed, Some("REVOKED: key"), CacheLookup::Invalid, 1)).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/metrics", listener.local_addr().unwrap());
+        tokio::spawn(serve(config.state.clone(), listener));
+        let body = reqwest::get(&url).await.unwrap().text().await.unwrap();
+        assert!(body.contains("verified_container_decisions_total{outcome=\"WARN_ALLOWED\",error_code=\"REVOKED\"} 1\n"));
+        assert!(body.contains("verified_container_cache_lookups_total{result=\"invalid\"} 1\n"));
+    }
+}
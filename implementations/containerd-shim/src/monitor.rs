@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Transparency log monitor for a producer's own signing keys
+// (transparency-log.adoc Section 10.2)
+//
+// For each log the monitor keeps the last tree head it scanned in its own
+// checkpoint store. A run fetches the current tree head, proves every new
+// entry is included in it, matches signers and subjects against the
+// expected-release manifest and only then advances the checkpoint, so an
+// interrupted run rescans rather than skips entries.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+use crate::checkpoint::{CheckpointStore, Observation};
+use crate::dsse::Envelope;
+use crate::log_client::LogClient;
+use crate::log_server::LoggedEntry;
+use crate::merkle;
+use crate::sth::VerifiedTreeHeads;
+use crate::tlog::{self, EntryBody};
+use crate::verify::{TrustStore, verify_ed25519_signature};
+
+/// Releases the producer expects its keys to have signed
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseManifest {
+    /// Key IDs to watch; each must be in the trust store
+    pub keyids: Vec<String>,
+    pub releases: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Release {
+    pub name: String,
+    /// Subject digest, e.g. `sha256:...`
+    pub digest: String,
+}
+
+impl ReleaseManifest {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("invalid input: failed to read release manifest {}", path))?;
+        serde_json::from_str(&content)
+            .context(format!("invalid input: failed to parse release manifest {}", path))
+    }
+
+    fn release(&self, digest: &str) -> Option<&Release> {
+        self.releases.iter().find(|r| r.digest == digest)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// A watched key signed a subject that is not an expected release
+    UnexpectedRelease,
+    /// An expected release was attested without any watched key
+    UnexpectedSigner,
+    /// A watched key was revoked in the log
+    KeyRevoked,
+    /// The stored envelope does not reproduce the logged entry
+    EntryMismatch,
+}
+
+/// One JSON-line alert
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    pub alert: AlertKind,
+    #[serde(rename = "logId")]
+    pub log_id: String,
+    #[serde(rename = "logIndex")]
+    pub log_index: u64,
+    #[serde(rename = "integratedTime")]
+    pub integrated_time: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keyids: Vec<String>,
+    #[serde(rename = "subjectDigest", skip_serializing_if = "Option::is_none")]
+    pub subject_digest: Option<String>,
+    /// Name of the expected release with this subject
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>,
+}
+
+pub struct Monitor<'a> {
+    pub manifest: &'a ReleaseManifest,
+    pub trust_store: &'a TrustStore,
+    pub store: &'a CheckpointStore,
+}
+
+impl Monitor<'_> {
+    /// Scan the entries `log_id` added since the last run, passing each
+    /// alert to `emit`. Returns the number of entries scanned.
+    pub async fn scan_log(&self, log_id: &str, client: &LogClient, mut emit: impl FnMut(Alert)) -> Result<u64> {
+        for keyid in &self.manifest.keyids {
+            if self.trust_store.get_key(keyid).is_none() {
+                bail!("invalid input: watched key {} is not in the trust store", keyid);
+            }
+        }
+
+        let checkpoint = self.store.get(log_id)?;
+        let start = checkpoint.as_ref().map_or(0, |c| c.tree_size);
+        let sth = client.get_sth(checkpoint.as_ref().map(|c| c.tree_size)).await
+            .context(format!("Failed to fetch tree head from log {}", log_id))?;
+        let heads = VerifiedTreeHeads::verify(vec![(log_id.to_string(), sth)], |id| self.trust_store.log_key(id))?;
+        let Some((_, head)) = heads.iter().next() else {
+            bail!("Log {} is not in the trust store", log_id);
+        };
+        let root = head.root()?;
+
+        let mut next = start;
+        while next < head.tree_size {
+            let page = client.get_entries(next, head.tree_size).await?;
+            if page.is_empty() {
+                bail!("Log {} returned no entries from index {}", log_id, next);
+            }
+            for logged in page {
+                if logged.log_index != next {
+                    bail!("Log {} returned entry {} where {} was expected", log_id, logged.log_index, next);
+                }
+                let leaf = logged.entry.leaf_hash()?;
+                let proof = client.get_proof(next, head.tree_size).await?;
+                let hashes = proof.hashes.iter().map(|h| merkle::decode_hash(h)).collect::<Result<Vec<_>>>()?;
+                merkle::verify_inclusion(&leaf, next, head.tree_size, &hashes, &root)
+                    .context(format!("LOG_PROOF_INVALID: entry {} of log {} is not in its tree head", next, log_id))?;
+
+                for alert in self.check_entry(log_id, &logged) {
+                    emit(alert);
+                }
+                next += 1;
+            }
+        }
+
+        let proof = checkpoint.as_ref()
+            .and_then(|c| head.consistency_from(c.tree_size))
+            .map(|p| p.decoded_hashes().map(|hashes| (p.from_size, hashes)))
+            .transpose()?;
+        let observation = self.store.observe(log_id, head, proof.as_ref().map(|(size, hashes)| (*size, hashes.as_slice())))?;
+        if observation == Observation::Unproven {
+            bail!("LOG_PROOF_INVALID: log {} gave no consistency proof from its checkpoint to tree size {}",
+                log_id, head.tree_size);
+        }
+
+        info!("Scanned {} new entries in log {} (tree size {})", next - start, log_id, head.tree_size);
+        Ok(next - start)
+    }
+
+    fn check_entry(&self, log_id: &str, logged: &LoggedEntry) -> Vec<Alert> {
+        let alert = |kind, keyids: Vec<String>, subject_digest: Option<&str>| Alert {
+            alert: kind,
+            log_id: log_id.to_string(),
+            log_index: logged.log_index,
+            integrated_time: logged.integrated_time.clone(),
+            keyids,
+            subject_digest: subject_digest.map(str::to_string),
+            release: subject_digest.and_then(|d| self.manifest.release(d)).map(|r| r.name.clone()),
+        };
+
+        match &logged.entry.body {
+            EntryBody::Revocation(body) if self.manifest.keyids.contains(&body.revoked_digest) => {
+                vec![alert(AlertKind::KeyRevoked, vec![body.revoked_digest.clone()], None)]
+            }
+            EntryBody::Revocation(_) => vec![],
+            EntryBody::Attestation(body) => {
+                let Some(envelope) = &logged.attestation else {
+                    warn!("Entry {} of log {} has no attestation envelope", logged.log_index, log_id);
+                    return vec![];
+                };
+                if tlog::Entry::from_envelope(envelope).ok().as_ref() != Some(&logged.entry) {
+                    return vec![alert(AlertKind::EntryMismatch, vec![], Some(&body.subject_digest))];
+                }
+
+                let watched = self.watched_signers(envelope);
+                let subject = Some(body.subject_digest.as_str());
+                match (watched.is_empty(), self.manifest.release(&body.subject_digest).is_some()) {
+                    (false, false) => vec![alert(AlertKind::UnexpectedRelease, watched, subject)],
+                    (true, true) => {
+                        let signers = envelope.signatures.iter().map(|s| s.keyid.clone()).collect();
+                        vec![alert(AlertKind::UnexpectedSigner, signers, subject)]
+                    }
+                    _ => vec![],
+                }
+            }
+        }
+    }
+
+    /// Watched key IDs with a valid signature on `envelope`. Signatures that
+    /// merely claim a watched key ID are ignored.
+    fn watched_signers(&self, envelope: &Envelope) -> Vec<String> {
+        let signed = envelope.signed_bytes();
+        let keyids: BTreeSet<_> = envelope.signatures.iter()
+            .filter(|s| self.manifest.keyids.contains(&s.keyid))
+            .filter(|s| {
+                let key = self.trust_store.get_key(&s.keyid);
+                key.is_some_and(|k| verify_ed25519_signature(&signed, &s.sig, &k.key_bytes).is_ok())
+            })
+            .map(|s| s.keyid.clone())
+            .collect();
+        keyids.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsse;
+    use crate::log_server::{LogServer, SubmitRequest};
+    use crate::signing::Signer;
+    use base64::{Engine, engine::general_purpose};
+    use ed25519_dalek::SigningKey;
+    use std::time::Duration;
+
+    fn envelope(key: &SigningKey, keyid: &str, subject: &str) -> Envelope {
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "image", "digest": {"sha256": subject}}],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {}
+        });
+        dsse::sign_statement(&statement, &Signer::File(key.clone()), keyid).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_monitor_alerts_on_unexpected_entries() {
+        let release_key = SigningKey::from_bytes(&[11u8; 32]);
+        let other_key = SigningKey::from_bytes(&[12u8; 32]);
+        let log_key = SigningKey::from_bytes(&[13u8; 32]);
+
+        let log_dir = tempfile::tempdir().unwrap();
+        let log = LogServer::open(log_dir.path(), "log-dev", Signer::File(log_key.clone())).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(log.clone().serve(listener));
+
+        let trust_store: TrustStore = serde_json::from_value(serde_json::json!({
+            "keys": [{
+                "keyid": "release",
+                "key_bytes": release_key.verifying_key().to_bytes().to_vec(),
+                "algorithm": "ed25519",
+                "valid_from": null,
+                "valid_until": null,
+                "trust_level": "release"
+            }],
+            "threshold_groups": [],
+            "logs": {"log-dev": {
+                "operator": "dev",
+                "publicKey": general_purpose::STANDARD.encode(log_key.verifying_key().to_bytes()),
+                "url": url,
+                "algorithm": "ed25519"
+            }}
+        })).unwrap();
+        let manifest = ReleaseManifest {
+            keyids: vec!["release".to_string()],
+            releases: vec![Release { name: "app-1.0".to_string(), digest: format!("sha256:{}", "aa".repeat(32)) }],
+        };
+        let state_dir = tempfile::tempdir().unwrap();
+        let store = CheckpointStore::open(state_dir.path());
+        let monitor = Monitor { manifest: &manifest, trust_store: &trust_store, store: &store };
+        let client = LogClient::new(&url, Duration::from_secs(5), 0).unwrap();
+
+        let submit = |envelope: Envelope| {
+            let entry = tlog::Entry::from_envelope(&envelope).unwrap();
+            log.submit(entry, Some(envelope)).unwrap();
+        };
+        submit(envelope(&release_key, "release", &"aa".repeat(32)));   // expected
+        submit(envelope(&release_key, "release", &"bb".repeat(32)));   // not a release
+        submit(envelope(&other_key, "release", &"cc".repeat(32)));     // forged key ID
+        submit(envelope(&other_key, "someone", &"aa".repeat(32)));     // release, foreign signer
+
+        let mut alerts = Vec::new();
+        assert_eq!(monitor.scan_log("log-dev", &client, |a| alerts.push(a)).await.unwrap(), 4);
+        let found: Vec<_> = alerts.iter().map(|a| (a.alert, a.log_index)).collect();
+        assert_eq!(found, [(AlertKind::UnexpectedRelease, 1), (AlertKind::UnexpectedSigner, 3)]);
+        assert_eq!(alerts[0].keyids, ["release"]);
+        assert_eq!(alerts[1].release.as_deref(), Some("app-1.0"));
+
+        // The next run only walks entries added since the checkpoint
+        let body = tlog::RevocationBody {
+            revoked_digest: "release".to_string(),
+            reason: "key_compromise".to_string(),
+            revoked_by: "release".to_string(),
+        };
+        let revocation = crate::revocation::sign_revocation(&body, &Signer::File(release_key.clone())).unwrap();
+        reqwest::Client::new().post(format!("{}/api/v1/entries", url))
+            .json(&SubmitRequest::Revocation { revocation: serde_json::to_vec(&revocation).unwrap() })
+            .send().await.unwrap()
+            .error_for_status().unwrap();
+
+        alerts.clear();
+        assert_eq!(monitor.scan_log("log-dev", &client, |a| alerts.push(a)).await.unwrap(), 1);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].alert, alerts[0].log_index), (AlertKind::KeyRevoked, 4));
+    }
+}
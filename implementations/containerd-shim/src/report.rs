@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Machine-readable verification reports
+//
+// The result of verifying an attestation bundle: as the cli-contract.json
+// `verify` output plus the steps behind it, as SARIF 2.1.0 for code-scanning UIs
+// and as JUnit XML for CI dashboards, with one test case per step.
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::audit::{self, AuditTrail, Outcome, StepResult, StepStatus};
+use crate::cache::Decision;
+use crate::verify::VerificationMode;
+
+/// Verification steps in the order they run (verification-protocol.adoc Section 6)
+const STEPS: [&str; 6] = ["parse_bundle", "subject_match", "signatures", "revocation", "log_inclusion", "threshold"];
+
+/// SARIF rule for warnings that do not fail verification
+const WARNING_RULE: &str = "VERIFICATION_WARNING";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Sarif,
+    Junit,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "json" => Ok(Format::Json),
+            "sarif" => Ok(Format::Sarif),
+            "junit" => Ok(Format::Junit),
+            _ => bail!("invalid input: unknown report format {:?} (json, sarif, junit)", value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    /// ALLOW or REJECT, as in cli-contract.json
+    pub decision: Decision,
+    /// What the mode does with the decision: also WARN_ALLOWED or
+    /// AUDIT_ALLOWED when it lets a failure through, as in the audit log
+    pub outcome: Outcome,
+    pub mode: VerificationMode,
+    pub bundle: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trust_store_version: Option<String>,
+    /// Every step, including those that did not run
+    pub steps: Vec<StepResult>,
+    pub attestations_verified: usize,
+    pub log_entries_verified: usize,
+    pub thresholds_met: Vec<String>,
+    pub keyids: Vec<String>,
+    pub log_ids: Vec<String>,
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl VerificationReport {
+    pub fn new(
+        bundle: &str,
+        mode: VerificationMode,
+        image_digest: Option<&str>,
+        trail: AuditTrail,
+        error: Option<&anyhow::Error>,
+    ) -> Self {
+        let decision = if error.is_none() { Decision::Allow } else { Decision::Reject };
+        let outcome = Outcome::decide(error.is_none(), mode);
+        let mut warnings = trail.warnings;
+        if matches!(outcome, Outcome::WarnAllowed | Outcome::AuditAllowed) {
+            warnings.push(format!("Verification failed; {} mode allows the image anyway", mode_name(mode)));
+        }
+
+        let failed = trail.steps.iter().find(|s| s.status == StepStatus::Failed).map(|s| s.step.clone());
+        let mut steps = trail.steps;
+        for step in STEPS {
+            if !steps.iter().any(|s| s.step == step) {
+                steps.push(StepResult {
+                    step: step.to_string(),
+                    status: StepStatus::Skipped,
+                    detail: failed.as_ref().map(|f| format!("not run after {} failed", f)),
+                });
+            }
+        }
+        steps.sort_by_key(|s| STEPS.iter().position(|step| *step == s.step));
+
+        let message = error.map(|e| format!("{:#}", e));
+        Self {
+            decision,
+            outcome,
+            mode,
+            bundle: bundle.to_string(),
+            image_digest: image_digest.map(str::to_string),
+            trust_store_version: trail.trust_store_version,
+            steps,
+            attestations_verified: trail.attestations_verified,
+            log_entries_verified: trail.log_entries_verified,
+            thresholds_met: trail.thresholds_met,
+            keyids: trail.keyids,
+            log_ids: trail.log_ids,
+            warnings,
+            error_code: error.and_then(audit::error_code_of),
+            message,
+        }
+    }
+
+    /// Verification passed. A failure the mode lets through is still a REJECT.
+    pub fn passed(&self) -> bool {
+        self.decision == Decision::Allow
+    }
+
+    pub fn render(&self, format: Format) -> Result<String> {
+        Ok(match format {
+            Format::Json => serde_json::to_string_pretty(self)? + "\n",
+            Format::Sarif => serde_json::to_string_pretty(&self.to_sarif())? + "\n",
+            Format::Junit => self.to_junit(),
+        })
+    }
+
+    /// SARIF 2.1.0 log with a result per failed step and per warning
+    fn to_sarif(&self) -> Value {
+        let location = json!([{ "physicalLocation": { "artifactLocation": { "uri": self.bundle } } }]);
+        let failure_level = if self.outcome == Outcome::Reject { "error" } else { "warning" };
+
+        let mut results = Vec::new();
+        for step in self.steps.iter().filter(|s| s.status == StepStatus::Failed) {
+            let detail = step.detail.clone().unwrap_or_else(|| format!("{} failed", step.step));
+            let rule = audit::error_code(&detail).unwrap_or_else(|| "VERIFICATION_FAILED".to_string());
+            results.push(json!({
+                "ruleId": rule,
+                "level": failure_level,
+                "message": { "text": detail },
+                "locations": location,
+                "properties": { "step": step.step },
+            }));
+        }
+        for warning in &self.warnings {
+            results.push(json!({
+                "ruleId": WARNING_RULE,
+                "level": "warning",
+                "message": { "text": warning },
+                "locations": location,
+            }));
+        }
+
+        let mut rule_ids: Vec<&str> = results.iter().filter_map(|r| r["ruleId"].as_str()).collect();
+        rule_ids.sort();
+        rule_ids.dedup();
+        let rules: Vec<Value> = rule_ids.iter()
+            .map(|id| json!({
+                "id": id,
+                "shortDescription": { "text": match *id {
+                    WARNING_RULE => "Verification warning".to_string(),
+                    code => format!("Verification failed with {}", code),
+                } },
+            }))
+            .collect();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                } },
+                "invocations": [{ "executionSuccessful": true }],
+                "results": results,
+                "properties": {
+                    "decision": self.decision,
+                    "outcome": self.outcome,
+                    "mode": self.mode,
+                    "imageDigest": self.image_digest,
+                },
+            }],
+        })
+    }
+
+    /// JUnit XML with a test suite for the bundle and a test case per step
+    fn to_junit(&self) -> String {
+        let count = |status: StepStatus| self.steps.iter().filter(|s| s.status == status).count();
+        let (tests, failures, skipped) = (self.steps.len(), count(StepStatus::Failed), count(StepStatus::Skipped));
+        let suite = self.image_digest.as_deref().unwrap_or(&self.bundle);
+        let label = |value: Value| value.as_str().map(str::to_string).unwrap_or_default();
+        let decision = label(json!(self.decision));
+        let outcome = label(json!(self.outcome));
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites name=\"verified-container\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+            tests, failures, skipped
+        ));
+        out.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">\n",
+            xml_escape(suite), tests, failures, skipped
+        ));
+        out.push_str("    <properties>\n");
+        let properties = [
+            ("decision", decision.as_str()),
+            ("outcome", outcome.as_str()),
+            ("mode", mode_name(self.mode)),
+            ("bundle", self.bundle.as_str()),
+        ];
+        for (name, value) in properties {
+            out.push_str(&format!("      <property name=\"{}\" value=\"{}\"/>\n", name, xml_escape(value)));
+        }
+        out.push_str("    </properties>\n");
+
+        for step in &self.steps {
+            let open = format!("    <testcase classname=\"verify\" name=\"{}\"", xml_escape(&step.step));
+            let detail = step.detail.as_deref().unwrap_or_default();
+            match step.status {
+                StepStatus::Passed => out.push_str(&format!("{}/>\n", open)),
+                StepStatus::Failed => {
+                    let kind = audit::error_code(detail).unwrap_or_else(|| "VERIFICATION_FAILED".to_string());
+                    out.push_str(&format!(
+                        "{}>\n      <failure type=\"{}\" message=\"{}\">{}</failure>\n    </testcase>\n",
+                        open, kind, xml_escape(detail), xml_escape(detail)
+                    ));
+                }
+                StepStatus::Skipped => out.push_str(&format!(
+                    "{}>\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                    open, xml_escape(detail)
+                )),
+            }
+        }
+        if !self.warnings.is_empty() {
+            out.push_str(&format!("    <system-err>{}</system-err>\n", xml_escape(&self.warnings.join("\n"))));
+        }
+        out.push_str("  </testsuite>\n</testsuites>\n");
+        out
+    }
+}
+
+fn mode_name(mode: VerificationMode) -> &'static str {
+    match mode {
+        VerificationMode::Strict => "strict",
+        VerificationMode::Permissive => "permissive",
+        VerificationMode::Audit => "audit",
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trail(failing: Option<&str>) -> AuditTrail {
+        let mut trail = AuditTrail {
+            keyids: vec!["builder".to_string()],
+            warnings: vec!["No inclusion proof for log log-b, skipping Merkle verification".to_string()],
+            ..AuditTrail::default()
+        };
+        for step in STEPS {
+            let passed = failing != Some(step);
+            let _ = trail.step(step, if passed {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("THRESHOLD_NOT_MET: only 1 signatures, need 2 of 3 <release-signers>"))
+            });
+            if !passed {
+                return trail;
+            }
+        }
+        trail.attestations_verified = 2;
+        trail.log_entries_verified = 2;
+        trail.thresholds_met = vec!["release-signers".to_string()];
+        trail
+    }
+
+    #[test]
+    fn test_report_follows_the_cli_contract() {
+        let report = VerificationReport::new("bundle.json", VerificationMode::Strict, Some("sha256:aa"), trail(None), None);
+        assert!(report.passed());
+        let json: Value = serde_json::from_str(&report.render(Format::Json).unwrap()).unwrap();
+        assert_eq!(json["decision"], "ALLOW");
+        assert_eq!(json["attestationsVerified"], 2);
+        assert_eq!(json["logEntriesVerified"], 2);
+        assert_eq!(json["thresholdsMet"], json!(["release-signers"]));
+        assert!(json.get("errorCode").is_none());
+
+        let error = anyhow::anyhow!("THRESHOLD_NOT_MET: only 1 signatures, need 2 of 3 <release-signers>");
+        let report = VerificationReport::new("bundle.json", VerificationMode::Strict, None, trail(Some("threshold")), Some(&error));
+        assert!(!report.passed());
+        let json: Value = serde_json::from_str(&report.render(Format::Json).unwrap()).unwrap();
+        assert_eq!(json["decision"], "REJECT");
+        assert_eq!(json["errorCode"], "THRESHOLD_NOT_MET");
+        assert!(json["message"].as_str().unwrap().starts_with("THRESHOLD_NOT_MET: only 1"));
+
+        // The contract only knows ALLOW and REJECT; the override is separate
+        let permissive = VerificationReport::new("bundle.json", VerificationMode::Permissive, None, trail(Some("threshold")), Some(&error));
+        assert!(!permissive.passed());
+        let json: Value = serde_json::from_str(&permissive.render(Format::Json).unwrap()).unwrap();
+        assert_eq!((&json["decision"], &json["outcome"]), (&json!("REJECT"), &json!("WARN_ALLOWED")));
+        assert!(permissive.warnings.last().unwrap().contains("permissive mode"));
+    }
+
+    #[test]
+    fn test_skipped_steps_appear_in_sarif_and_junit() {
+        let error = anyhow::anyhow!("INVALID_SIGNATURE: Ed25519 verification failed");
+        let mut trail = trail(Some("signatures"));
+        trail.steps[2].detail = Some(format!("{:#}", error));
+        let report = VerificationReport::new("app & co.json", VerificationMode::Strict, Some("sha256:aa"), trail, Some(&error));
+        let statuses: Vec<StepStatus> = report.steps.iter().map(|s| s.status).collect();
+        assert_eq!(statuses, [
+            StepStatus::Passed, StepStatus::Passed, StepStatus::Failed,
+            StepStatus::Skipped, StepStatus::Skipped, StepStatus::Skipped,
+        ]);
+        assert_eq!(report.steps[3].detail.as_deref(), Some("not run after signatures failed"));
+
+        let sarif: Value = serde_json::from_str(&report.render(Format::Sarif).unwrap()).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["results"][0]["ruleId"], "INVALID_SIGNATURE");
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(run["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "app & co.json");
+        assert_eq!(run["results"][1]["ruleId"], WARNING_RULE);
+        let rules: Vec<&str> = run["tool"]["driver"]["rules"].as_array().unwrap().iter()
+            .map(|r| r["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, ["INVALID_SIGNATURE", WARNING_RULE]);
+
+        let junit = report.render(Format::Junit).unwrap();
+        assert!(junit.contains("<testsuites name=\"verified-container\" tests=\"6\" failures=\"1\" skipped=\"3\">"));
+        assert!(junit.contains("<property name=\"bundle\" value=\"app &amp; co.json\"/>"));
+        assert!(junit.contains("<testcase classname=\"verify\" name=\"signatures\">\n      <failure type=\"INVALID_SIGNATURE\""));
+        assert!(junit.contains("<skipped message=\"not run after signatures failed\"/>"));
+        assert!(junit.contains("<system-err>No inclusion proof for log log-b"));
+    }
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Revocation checking (transparency-log.adoc Section 9)
+//
+// The revocation set is built from a signed local snapshot and, in online
+// mode, from the revocation entries of each trusted log. Snapshots are
+// accepted wholesale once signed by a trusted key, unless they are older
+// than the configured age or than a snapshot already used on this host.
+// Anyone may write to a log, so a log revocation only counts when it is a
+// DSSE envelope signed by the trust store key its `revokedBy` names and the
+// log proves its inclusion under a tree head signed with the log's key.
+
+use anyhow::{Context, Result, bail};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::canonical;
+use crate::config::RevocationConfig;
+use crate::dsse::{self, Envelope};
+use crate::log_client::LogClient;
+use crate::merkle;
+use crate::signing::Signer;
+use crate::tlog::{self, REVOCATION_PAYLOAD_TYPE, RevocationBody};
+
+/// DSSE payload type of a revocation snapshot
+pub const SNAPSHOT_PAYLOAD_TYPE: &str = "application/vnd.verified-container.revocations+json";
+
+/// Payload of a revocation snapshot
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// Increases with every snapshot the publisher issues
+    pub version: u64,
+    #[serde(rename = "issuedAt")]
+    pub issued_at: String,
+    pub revocations: Vec<RevocationBody>,
+}
+
+/// The newest snapshot used on this host, kept at `revocation.state`
+#[derive(Debug, Deserialize, Serialize)]
+struct SnapshotState {
+    version: u64,
+    #[serde(rename = "issuedAt")]
+    issued_at: String,
+}
+
+/// Revoked key IDs and attestation digests
+#[derive(Debug, Default)]
+pub struct RevocationSet {
+    revoked: BTreeMap<String, RevocationBody>,
+}
+
+impl RevocationSet {
+    pub fn add(&mut self, revocation: RevocationBody) {
+        self.revoked.entry(revocation.revoked_digest.clone()).or_insert(revocation);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+
+    pub fn get(&self, digest: &str) -> Option<&RevocationBody> {
+        self.revoked.get(digest)
+    }
+
+    /// Fail with REVOKED if any of `digests` is revoked
+    pub fn check<'a>(&self, digests: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        for digest in digests {
+            if let Some(revocation) = self.get(digest) {
+                bail!(
+                    "REVOKED: {} was revoked by {} ({})",
+                    digest, revocation.revoked_by, revocation.reason
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a DSSE-signed snapshot, requiring a valid signature from a key
+    /// that `trusted_key` resolves. A snapshot older than
+    /// `max_snapshot_age_secs`, or with a lower version than one already
+    /// used on this host, is rejected as REVOCATION_STALE.
+    pub fn load_snapshot<'k>(
+        path: &Path,
+        config: &RevocationConfig,
+        trusted_key: impl Fn(&str) -> Option<&'k [u8]>,
+    ) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read revocation snapshot {}", path.display()))?;
+        let envelope: Envelope = serde_json::from_str(&content)
+            .context("Failed to parse revocation snapshot envelope")?;
+
+        if envelope.payload_type != SNAPSHOT_PAYLOAD_TYPE {
+            bail!("Revocation snapshot has payload type {}, expected {}", envelope.payload_type, SNAPSHOT_PAYLOAD_TYPE);
+        }
+
+        let signer = envelope.signatures.iter()
+            .find(|s| trusted_key(&s.keyid).is_some_and(|key| verify_ed25519(&envelope.signed_bytes(), &s.sig, key)))
+            .context("Revocation snapshot is not signed by a trusted key")?;
+
+        let snapshot: Snapshot = serde_json::from_slice(&envelope.payload)
+            .context("Failed to parse revocation snapshot payload")?;
+
+        let issued_at = chrono::DateTime::parse_from_rfc3339(&snapshot.issued_at)
+            .context(format!("Invalid revocation snapshot issuedAt: {}", snapshot.issued_at))?;
+        let age = chrono::Utc::now().signed_duration_since(issued_at);
+        if age > chrono::Duration::seconds(config.max_snapshot_age_secs as i64) {
+            bail!(
+                "REVOCATION_STALE: revocation snapshot version {} issued at {} is {}s old (limit {}s)",
+                snapshot.version, snapshot.issued_at, age.num_seconds(), config.max_snapshot_age_secs
+            );
+        }
+        check_snapshot_rollback(&config.state, &snapshot)?;
+
+        info!(
+            "Loaded {} revocations from snapshot version {} of {} signed by {}",
+            snapshot.revocations.len(), snapshot.version, snapshot.issued_at, signer.keyid
+        );
+
+        let mut set = Self::default();
+        for revocation in snapshot.revocations {
+            set.add(revocation);
+        }
+        Ok(set)
+    }
+
+    /// Add revocations of any of `digests` recorded in the log `log_id`
+    /// behind `client`. Each must be signed by the key `trusted_key` resolves
+    /// for its `revokedBy`, and included in a tree head signed with `log_key`.
+    pub async fn query_log<'k>(
+        &mut self,
+        client: &LogClient,
+        log_id: &str,
+        log_key: &[u8],
+        digests: &[String],
+        trusted_key: impl Fn(&str) -> Option<&'k [u8]>,
+    ) -> Result<()> {
+        let head = client.get_sth(None).await?;
+        head.verify(log_key)
+            .context(format!("STH_INVALID: tree head from log {} failed verification", log_id))?;
+        let root = head.root()?;
+
+        for record in client.get_revocations(digests, head.tree_size).await? {
+            let revocation = match open_revocation(&record.revocation, &trusted_key) {
+                Ok(revocation) => revocation,
+                Err(e) => {
+                    warn!("Ignoring revocation at index {} of log {}: {:#}", record.log_index, log_id, e);
+                    continue;
+                }
+            };
+
+            // The log must prove the revocation is part of its signed tree
+            let proof = &record.inclusion_proof;
+            if (proof.log_index, proof.tree_size) != (record.log_index, head.tree_size) {
+                bail!("LOG_PROOF_INVALID: log {} returned a revocation proof for the wrong entry or tree size", log_id);
+            }
+            let hashes = proof.hashes.iter().map(|h| merkle::decode_hash(h)).collect::<Result<Vec<_>>>()?;
+            let leaf = tlog::Entry::revocation(revocation.clone()).leaf_hash()?;
+            merkle::verify_inclusion(&leaf, record.log_index, head.tree_size, &hashes, &root)
+                .context(format!(
+                    "LOG_PROOF_INVALID: revocation at index {} is not in the signed tree head of log {}",
+                    record.log_index, log_id
+                ))?;
+
+            self.add(revocation);
+        }
+        Ok(())
+    }
+}
+
+/// Sign a revocation for submission to a log. The signature's key ID is the
+/// revocation's `revokedBy`.
+pub fn sign_revocation(body: &RevocationBody, signer: &Signer) -> Result<Envelope> {
+    let payload = canonical::canonicalize(&serde_json::to_value(body)?).into_bytes();
+    let sig = signer.sign(&dsse::pae(REVOCATION_PAYLOAD_TYPE, &payload))
+        .context("Failed to sign revocation")?;
+
+    Ok(Envelope {
+        payload_type: REVOCATION_PAYLOAD_TYPE.to_string(),
+        payload,
+        signatures: vec![dsse::Signature {
+            keyid: body.revoked_by.clone(),
+            sig,
+        }],
+    })
+}
+
+/// The revocation in a signed envelope, if the key its `revokedBy` names
+/// resolves through `trusted_key` and signed it
+fn open_revocation<'k>(envelope: &Envelope, trusted_key: impl Fn(&str) -> Option<&'k [u8]>) -> Result<RevocationBody> {
+    if envelope.payload_type != REVOCATION_PAYLOAD_TYPE {
+        bail!("payload type {} is not {}", envelope.payload_type, REVOCATION_PAYLOAD_TYPE);
+    }
+    let body: RevocationBody = serde_json::from_slice(&envelope.payload)
+        .context("payload is not a revocation")?;
+    let key = trusted_key(&body.revoked_by)
+        .context(format!("{} is not a trusted key", body.revoked_by))?;
+    let signed = envelope.signatures.iter()
+        .filter(|s| s.keyid == body.revoked_by)
+        .any(|s| verify_ed25519(&envelope.signed_bytes(), &s.sig, key));
+    if !signed {
+        bail!("revocation of {} is not signed by {}", body.revoked_digest, body.revoked_by);
+    }
+    Ok(body)
+}
+
+/// Refuse a snapshot older than the newest one used on this host, then
+/// record it. The state only narrows replays within the age limit, so a
+/// state that cannot be read or written (e.g. rootless) is not fatal.
+fn check_snapshot_rollback(state: &Path, snapshot: &Snapshot) -> Result<()> {
+    let _lock = match lock_state(state) {
+        Ok(lock) => lock,
+        Err(e) => {
+            warn!("Not checking revocation snapshot for rollback: {:#}", e);
+            return Ok(());
+        }
+    };
+
+    let seen: Option<SnapshotState> = match std::fs::read(state) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| warn!("Ignoring unreadable revocation state {:?}: {}", state, e))
+            .ok(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("Failed to read revocation state {:?}: {}", state, e);
+            None
+        }
+    };
+
+    match seen {
+        Some(seen) if snapshot.version < seen.version => bail!(
+            "REVOCATION_STALE: revocation snapshot version {} is older than version {} of {} already used",
+            snapshot.version, seen.version, seen.issued_at
+        ),
+        Some(seen) if snapshot.version == seen.version => Ok(()),
+        _ => {
+            let record = SnapshotState { version: snapshot.version, issued_at: snapshot.issued_at.clone() };
+            if let Err(e) = write_state(state, &record) {
+                warn!("Failed to record revocation snapshot version: {:#}", e);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Exclusive lock on `<state>.lock`, released on drop
+fn lock_state(state: &Path) -> Result<std::fs::File> {
+    let dir = state.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).context(format!("Failed to create {:?}", dir))?;
+    let mut lock_path = state.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(PathBuf::from(lock_path))
+        .context("Failed to open revocation state lock")?;
+    lock.lock().context("Failed to lock revocation state")?;
+    Ok(lock)
+}
+
+fn write_state(state: &Path, record: &SnapshotState) -> Result<()> {
+    let dir = state.parent().unwrap_or(Path::new("."));
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(serde_json::to_string(record)?.as_bytes())?;
+    tmp.persist(state)
+        .context(format!("Failed to write revocation state {:?}", state))?;
+    Ok(())
+}
+
+fn verify_ed25519(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    let (Ok(key), Ok(signature)) = (<&[u8; 32]>::try_from(public_key), <&[u8; 64]>::try_from(signature)) else {
+        return false;
+    };
+    VerifyingKey::from_bytes(key)
+        .is_ok_and(|key| key.verify(message, &Signature::from_bytes(signature)).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsse::Signature as DsseSignature;
+    use crate::log_server::{self, SubmitRequest};
+    use ed25519_dalek::SigningKey;
+    use std::time::Duration;
+
+    fn revocation(digest: &str, by: &str) -> RevocationBody {
+        RevocationBody {
+            revoked_digest: digest.to_string(),
+            reason: "key_compromise".to_string(),
+            revoked_by: by.to_string(),
+        }
+    }
+
+    fn snapshot_envelope(key: &SigningKey, keyid: &str, version: u64, issued_at: &str) -> Envelope {
+        let payload = serde_json::to_vec(&Snapshot {
+            version,
+            issued_at: issued_at.to_string(),
+            revocations: vec![revocation("sha256:builder", "sha256:security")],
+        }).unwrap();
+        let pae = dsse::pae(SNAPSHOT_PAYLOAD_TYPE, &payload);
+        let sig = Signer::File(key.clone()).sign(&pae).unwrap();
+        Envelope {
+            payload_type: SNAPSHOT_PAYLOAD_TYPE.to_string(),
+            payload,
+            signatures: vec![DsseSignature { keyid: keyid.to_string(), sig }],
+        }
+    }
+
+    #[test]
+    fn test_snapshot_requires_trusted_signature() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let dir = tempfile::tempdir().unwrap();
+        let config = RevocationConfig { state: dir.path().join("state.json"), ..RevocationConfig::default() };
+        let path = dir.path().join("revocations.json");
+        let now = chrono::Utc::now().to_rfc3339();
+        std::fs::write(&path, serde_json::to_vec(&snapshot_envelope(&key, "security", 1, &now)).unwrap()).unwrap();
+
+        let set = RevocationSet::load_snapshot(&path, &config, |id| (id == "security").then_some(&public[..])).unwrap();
+        let err = set.check(["sha256:other", "sha256:builder"]).unwrap_err();
+        assert!(err.to_string().starts_with("REVOKED: sha256:builder"));
+        assert!(set.check(["sha256:other"]).is_ok());
+
+        // Valid signature, but from a key the trust store does not know
+        assert!(RevocationSet::load_snapshot(&path, &config, |_| None).is_err());
+
+        let mut tampered = snapshot_envelope(&key, "security", 1, &now);
+        tampered.payload = br#"{"version":1,"issuedAt":"2026-01-01T00:00:00Z","revocations":[]}"#.to_vec();
+        std::fs::write(&path, serde_json::to_vec(&tampered).unwrap()).unwrap();
+        assert!(RevocationSet::load_snapshot(&path, &config, |_| Some(&public[..])).is_err());
+    }
+
+    #[test]
+    fn test_stale_or_rolled_back_snapshot_rejected() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let dir = tempfile::tempdir().unwrap();
+        let config = RevocationConfig { state: dir.path().join("state.json"), ..RevocationConfig::default() };
+        let path = dir.path().join("revocations.json");
+        let load = |version: u64, issued_at: chrono::DateTime<chrono::Utc>| {
+            let envelope = snapshot_envelope(&key, "security", version, &issued_at.to_rfc3339());
+            std::fs::write(&path, serde_json::to_vec(&envelope).unwrap()).unwrap();
+            RevocationSet::load_snapshot(&path, &config, |_| Some(&public[..]))
+        };
+        let now = chrono::Utc::now();
+
+        let err = load(1, now - chrono::Duration::days(30)).unwrap_err();
+        assert!(err.to_string().starts_with("REVOCATION_STALE"));
+
+        load(2, now - chrono::Duration::days(1)).unwrap();
+        load(3, now).unwrap();
+        load(3, now).unwrap();
+
+        // Still within the age limit, but older than a snapshot already used
+        let err = load(2, now - chrono::Duration::days(1)).unwrap_err();
+        assert!(err.to_string().starts_with("REVOCATION_STALE"));
+    }
+
+    #[tokio::test]
+    async fn test_log_revocations_must_be_signed_and_included() {
+        let log_key = SigningKey::from_bytes(&[5u8; 32]);
+        let security = SigningKey::from_bytes(&[6u8; 32]);
+        let mallory = SigningKey::from_bytes(&[7u8; 32]);
+        let dir = tempfile::tempdir().unwrap();
+        let url = log_server::testing::spawn(dir.path(), "log-dev", &log_key).await;
+
+        let submissions = [
+            // Signed by the revoking key
+            sign_revocation(&revocation("sha256:builder", "sha256:security"), &Signer::File(security.clone())).unwrap(),
+            // Claims to come from a trusted key, but signed by another
+            sign_revocation(&revocation("sha256:release", "sha256:security"), &Signer::File(mallory.clone())).unwrap(),
+            // Signed by a key the trust store does not know
+            sign_revocation(&revocation("sha256:base", "sha256:mallory"), &Signer::File(mallory)).unwrap(),
+        ];
+        let http = reqwest::Client::new();
+        for envelope in &submissions {
+            http.post(format!("{}/api/v1/entries", url))
+                .json(&SubmitRequest::Revocation { revocation: serde_json::to_vec(envelope).unwrap() })
+                .send().await.unwrap()
+                .error_for_status().unwrap();
+        }
+
+        let public = security.verifying_key().to_bytes();
+        let trusted_key = |id: &str| (id == "sha256:security").then_some(&public[..]);
+        let client = LogClient::new(&url, Duration::from_secs(5), 0).unwrap();
+        let digests = ["sha256:builder", "sha256:release", "sha256:base"].map(String::from);
+        let mut set = RevocationSet::default();
+        set.query_log(&client, "log-dev", &log_key.verifying_key().to_bytes(), &digests, trusted_key).await.unwrap();
+
+        assert!(set.get("sha256:builder").is_some());
+        assert!(set.get("sha256:release").is_none());
+        assert!(set.get("sha256:base").is_none());
+
+        // Tree heads must be signed by the log's own key
+        let err = RevocationSet::default()
+            .query_log(&client, "log-dev", &public, &digests, trusted_key).await
+            .unwrap_err();
+        assert!(err.to_string().contains("STH_INVALID"));
+    }
+}
@@ -0,0 +1,406 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Producer signing backends: file keys and PKCS#11 tokens
+//
+// transparency-log.adoc Section 6.4 and SECURITY.md require production signing
+// keys to stay in an HSM. Keys are referenced either by a PEM file path or by an
+// RFC 7512 PKCS#11 URI, e.g.
+//
+//   pkcs11:token=release;object=release-key-1?module-path=/usr/lib/softhsm/libsofthsm2.so
+//
+// The PIN comes from the `pin-value` query attribute or the PKCS11_PIN
+// environment variable; the module from `module-path` or PKCS11_MODULE.
+
+use anyhow::{Context, Result, bail};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::mechanism::eddsa::{EddsaParams, EddsaSignatureScheme};
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use ed25519_dalek::SigningKey;
+use std::path::Path;
+
+/// A producer signing key
+pub enum Signer {
+    /// Ed25519 private key loaded from a PKCS#8 PEM file
+    File(SigningKey),
+    /// Ed25519 private key held in a PKCS#11 token
+    Pkcs11(Pkcs11Signer),
+}
+
+impl Signer {
+    /// Open a signing key from a file path or `pkcs11:` URI
+    pub fn open(key_ref: &str) -> Result<Self> {
+        if key_ref.starts_with("pkcs11:") {
+            let uri = Pkcs11Uri::parse(key_ref)?;
+            return Ok(Self::Pkcs11(Pkcs11Signer::open(&uri)?));
+        }
+
+        Self::from_pem_file(Path::new(key_ref))
+    }
+
+    fn from_pem_file(path: &Path) -> Result<Self> {
+        use ed25519_dalek::pkcs8::DecodePrivateKey;
+
+        let pem = std::fs::read_to_string(path)
+            .context(format!("Failed to read signing key {:?}", path))?;
+        let key = SigningKey::from_pkcs8_pem(&pem)
+            .context("Failed to parse Ed25519 PKCS#8 PEM private key")?;
+
+        Ok(Self::File(key))
+    }
+
+    /// Raw 32-byte Ed25519 public key
+    pub fn public_key(&self) -> [u8; 32] {
+        match self {
+            Self::File(key) => key.verifying_key().to_bytes(),
+            Self::Pkcs11(signer) => signer.public_key,
+        }
+    }
+
+    /// Key identifier as recommended by trust-store.adoc Section 6.5.1
+    pub fn key_id(&self) -> String {
+        use sha2::{Sha256, Digest};
+        format!("sha256:{}", hex::encode(Sha256::digest(self.public_key())))
+    }
+
+    /// Produce an Ed25519 signature over `message`
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::File(key) => {
+                use ed25519_dalek::Signer as _;
+                Ok(key.sign(message).to_bytes().to_vec())
+            }
+            Self::Pkcs11(signer) => signer.sign(message),
+        }
+    }
+}
+
+/// The subset of RFC 7512 PKCS#11 URIs needed to select a signing key
+#[derive(Debug, Default, PartialEq)]
+pub struct Pkcs11Uri {
+    pub token: Option<String>,
+    pub object: Option<String>,
+    pub id: Option<Vec<u8>>,
+    pub module_path: Option<String>,
+    pub pin_value: Option<String>,
+}
+
+impl Pkcs11Uri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let rest = uri.strip_prefix("pkcs11:")
+            .context("PKCS#11 URI must start with 'pkcs11:'")?;
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let mut parsed = Self::default();
+
+        for attr in path.split(';').filter(|a| !a.is_empty()) {
+            let (name, value) = attr.split_once('=')
+                .context(format!("Invalid PKCS#11 URI path attribute: {}", attr))?;
+            let value = percent_decode(value)?;
+            match name {
+                "token" => parsed.token = Some(String::from_utf8(value)?),
+                "object" => parsed.object = Some(String::from_utf8(value)?),
+                "id" => parsed.id = Some(value),
+                // Other path attributes (manufacturer, serial, type, ...) are not
+                // needed to locate an Ed25519 signing key and are ignored
+                _ => {}
+            }
+        }
+
+        for attr in query.split('&').filter(|a| !a.is_empty()) {
+            let (name, value) = attr.split_once('=')
+                .context(format!("Invalid PKCS#11 URI query attribute: {}", attr))?;
+            let value = String::from_utf8(percent_decode(value)?)?;
+            match name {
+                "module-path" => parsed.module_path = Some(value),
+                "pin-value" => parsed.pin_value = Some(value),
+                _ => {}
+            }
+        }
+
+        if parsed.object.is_none() && parsed.id.is_none() {
+            bail!("PKCS#11 URI must select a key by 'object' (label) or 'id'");
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn percent_decode(s: &str) -> Result<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)
+                .context(format!("Truncated percent-encoding in PKCS#11 URI: {}", s))?;
+            out.push(u8::from_str_radix(hex, 16)
+                .context(format!("Invalid percent-encoding in PKCS#11 URI: {}", s))?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Ed25519 key in a PKCS#11 token, signing with CKM_EDDSA
+pub struct Pkcs11Signer {
+    session: Session,
+    private_key: ObjectHandle,
+    public_key: [u8; 32],
+}
+
+impl Pkcs11Signer {
+    pub fn open(uri: &Pkcs11Uri) -> Result<Self> {
+        let module = uri.module_path.clone()
+            .or_else(|| std::env::var("PKCS11_MODULE").ok())
+            .context("No PKCS#11 module: set module-path in the URI or PKCS11_MODULE")?;
+
+        let pkcs11 = Pkcs11::new(&module)
+            .context(format!("Failed to load PKCS#11 module {}", module))?;
+        pkcs11.initialize(CInitializeArgs::OsThreads)
+            .context("Failed to initialize PKCS#11 module")?;
+
+        let slot = pkcs11.get_slots_with_token()?
+            .into_iter()
+            .find(|slot| match &uri.token {
+                Some(label) => pkcs11.get_token_info(*slot)
+                    .map(|info| info.label().trim_end() == label)
+                    .unwrap_or(false),
+                None => true,
+            })
+            .context(format!("PKCS#11 token not found: {:?}", uri.token))?;
+
+        let session = pkcs11.open_ro_session(slot)
+            .context("Failed to open PKCS#11 session")?;
+
+        let pin = uri.pin_value.clone()
+            .or_else(|| std::env::var("PKCS11_PIN").ok());
+        let token = pkcs11.get_token_info(slot)
+            .context("Failed to read PKCS#11 token info")?;
+        match pin {
+            Some(pin) => session.login(UserType::User, Some(&AuthPin::new(pin)))
+                .context("PKCS#11 login failed")?,
+            // A PIN pad or other protected path authenticates outside the library
+            None if token.protected_authentication_path() => session.login(UserType::User, None)
+                .context("PKCS#11 login failed")?,
+            // Private keys are invisible before login, so the key search would
+            // otherwise fail with a misleading "not found"
+            None if token.login_required() => bail!(
+                "PKCS#11 token {:?} requires a PIN: set pin-value in the URI or PKCS11_PIN",
+                token.label().trim_end()
+            ),
+            None => {}
+        }
+
+        let private_key = find_single_key(&session, ObjectClass::PRIVATE_KEY, uri)?;
+        let public_handle = find_single_key(&session, ObjectClass::PUBLIC_KEY, uri)?;
+        let public_key = read_ed25519_public_key(&session, public_handle)?;
+
+        Ok(Self { session, private_key, public_key })
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mechanism = Mechanism::Eddsa(EddsaParams::new(EddsaSignatureScheme::Pure));
+        self.session.sign(&mechanism, self.private_key, message)
+            .context("PKCS#11 EdDSA signing failed")
+    }
+}
+
+fn find_single_key(session: &Session, class: ObjectClass, uri: &Pkcs11Uri) -> Result<ObjectHandle> {
+    let mut template = vec![
+        Attribute::Class(class),
+        Attribute::KeyType(KeyType::EC_EDWARDS),
+    ];
+    if let Some(label) = &uri.object {
+        template.push(Attribute::Label(label.as_bytes().to_vec()));
+    }
+    if let Some(id) = &uri.id {
+        template.push(Attribute::Id(id.clone()));
+    }
+
+    let objects = session.find_objects(&template)
+        .context("PKCS#11 object search failed")?;
+
+    match objects.as_slice() {
+        [handle] => Ok(*handle),
+        [] => bail!("No Ed25519 {} found in PKCS#11 token for {:?}/{:?}",
+            class, uri.object, uri.id.as_ref().map(hex::encode)),
+        _ => bail!("Ambiguous PKCS#11 key selection: {} matching {} objects", objects.len(), class),
+    }
+}
+
+/// CKA_EC_POINT for Edwards keys is a DER OCTET STRING; some tokens return the raw point
+fn read_ed25519_public_key(session: &Session, handle: ObjectHandle) -> Result<[u8; 32]> {
+    let attrs = session.get_attributes(handle, &[AttributeType::EcPoint])?;
+    let point = attrs.into_iter()
+        .find_map(|attr| match attr {
+            Attribute::EcPoint(point) => Some(point),
+            _ => None,
+        })
+        .context("PKCS#11 public key has no CKA_EC_POINT")?;
+
+    let raw = match point.as_slice() {
+        [0x04, 0x20, rest @ ..] if rest.len() == 32 => rest,
+        raw => raw,
+    };
+
+    raw.try_into()
+        .context(format!("Invalid Ed25519 public key length from token: {} bytes", raw.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkcs11_uri() {
+        let uri = Pkcs11Uri::parse(
+            "pkcs11:token=release;object=key%201;id=%01%ff?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-value=1234"
+        ).unwrap();
+
+        assert_eq!(uri.token.as_deref(), Some("release"));
+        assert_eq!(uri.object.as_deref(), Some("key 1"));
+        assert_eq!(uri.id, Some(vec![0x01, 0xff]));
+        assert_eq!(uri.module_path.as_deref(), Some("/usr/lib/softhsm/libsofthsm2.so"));
+        assert_eq!(uri.pin_value.as_deref(), Some("1234"));
+
+        assert!(Pkcs11Uri::parse("pkcs11:token=release").is_err());
+        assert!(Pkcs11Uri::parse("pkcs11:object=%zz").is_err());
+    }
+
+    #[test]
+    fn test_file_key_signs_verifiable_signature() {
+        use ed25519_dalek::pkcs8::{EncodePrivateKey, spki::der::pem::LineEnding};
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("builder.pem");
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        std::fs::write(&key_path, key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes()).unwrap();
+
+        let signer = Signer::open(key_path.to_str().unwrap()).unwrap();
+        let sig = signer.sign(b"payload").unwrap();
+
+        let verifying_key = VerifyingKey::from_bytes(&signer.public_key()).unwrap();
+        let sig = Signature::from_bytes(sig.as_slice().try_into().unwrap());
+        assert!(verifying_key.verify(b"payload", &sig).is_ok());
+        assert!(signer.key_id().starts_with("sha256:"));
+    }
+
+    /// Locate a SoftHSM v2 module
+    fn softhsm_module() -> Option<String> {
+        std::env::var("SOFTHSM2_MODULE").ok()
+            .into_iter()
+            .chain([
+                "/usr/lib/softhsm/libsofthsm2.so",
+                "/usr/lib/x86_64-linux-gnu/softhsm/libsofthsm2.so",
+                "/usr/lib64/pkcs11/libsofthsm2.so",
+                "/usr/local/lib/softhsm/libsofthsm2.so",
+            ].map(String::from))
+            .find(|path| Path::new(path).exists())
+    }
+
+    /// SoftHSM only reads its configuration from SOFTHSM2_CONF, and setting
+    /// that here would race with the harness's other threads, so the token
+    /// test runs in a child test process given a private token directory.
+    #[test]
+    #[ignore = "requires SoftHSM v2; CI runs it with `cargo test -- --ignored test_softhsm`"]
+    fn test_softhsm_token_signing() {
+        let module = softhsm_module()
+            .expect("SoftHSM v2 not found: install softhsm2 or set SOFTHSM2_MODULE");
+
+        let dir = tempfile::tempdir().unwrap();
+        let token_dir = dir.path().join("tokens");
+        std::fs::create_dir_all(&token_dir).unwrap();
+        let conf = dir.path().join("softhsm2.conf");
+        std::fs::write(&conf, format!(
+            "directories.tokendir = {}\nobjectstore.backend = file\n",
+            token_dir.display()
+        )).unwrap();
+
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--ignored", "--exact", "--nocapture", "signing::tests::softhsm_token_signing_child"])
+            .env("SOFTHSM2_CONF", &conf)
+            .env("SOFTHSM2_MODULE", &module)
+            .env("VC_SOFTHSM_TEST_CHILD", "1")
+            .env_remove("PKCS11_PIN")
+            .status()
+            .unwrap();
+        assert!(status.success(), "SoftHSM token signing failed in child test process");
+    }
+
+    /// Body of test_softhsm_token_signing; does nothing unless spawned by it,
+    /// so it never initializes a token in the system SoftHSM directory
+    #[test]
+    #[ignore = "run by test_softhsm_token_signing"]
+    fn softhsm_token_signing_child() {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        if std::env::var_os("VC_SOFTHSM_TEST_CHILD").is_none() {
+            return;
+        }
+        let module = softhsm_module().unwrap();
+
+        {
+            let pkcs11 = Pkcs11::new(&module).unwrap();
+            pkcs11.initialize(CInitializeArgs::OsThreads).unwrap();
+            let slot = pkcs11.get_all_slots().unwrap()[0];
+            let so_pin = AuthPin::new("so-pin".to_string());
+            pkcs11.init_token(slot, &so_pin, "vc-test").unwrap();
+
+            // SoftHSM renumbers slots once a token is initialized
+            let slot = pkcs11.get_slots_with_token().unwrap()
+                .into_iter()
+                .find(|s| pkcs11.get_token_info(*s).unwrap().label().trim_end() == "vc-test")
+                .unwrap();
+
+            let session = pkcs11.open_rw_session(slot).unwrap();
+            session.login(UserType::So, Some(&so_pin)).unwrap();
+            session.init_pin(&AuthPin::new("1234".to_string())).unwrap();
+            session.logout().unwrap();
+            session.login(UserType::User, Some(&AuthPin::new("1234".to_string()))).unwrap();
+
+            // DER-encoded OID 1.3.101.112 (id-Ed25519)
+            let ed25519_params = vec![0x06, 0x03, 0x2b, 0x65, 0x70];
+            let label = b"release-key".to_vec();
+            session.generate_key_pair(
+                &Mechanism::EccEdwardsKeyPairGen,
+                &[
+                    Attribute::Token(true),
+                    Attribute::Verify(true),
+                    Attribute::EcParams(ed25519_params),
+                    Attribute::Label(label.clone()),
+                    Attribute::Id(vec![0x01]),
+                ],
+                &[
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sensitive(true),
+                    Attribute::Sign(true),
+                    Attribute::Label(label),
+                    Attribute::Id(vec![0x01]),
+                ],
+            ).unwrap();
+        }
+
+        for selector in ["object=release-key", "id=%01"] {
+            let key_ref = format!("pkcs11:token=vc-test;{}?module-path={}&pin-value=1234", selector, module);
+            let signer = Signer::open(&key_ref).unwrap();
+            let sig = signer.sign(b"DSSEv1 test payload").unwrap();
+
+            let verifying_key = VerifyingKey::from_bytes(&signer.public_key()).unwrap();
+            let sig = Signature::from_bytes(sig.as_slice().try_into().unwrap());
+            assert!(verifying_key.verify(b"DSSEv1 test payload", &sig).is_ok());
+        }
+
+        let key_ref = format!("pkcs11:token=vc-test;object=release-key?module-path={}", module);
+        let err = Signer::open(&key_ref).err().unwrap();
+        assert!(format!("{:#}", err).contains("requires a PIN"), "{:#}", err);
+    }
+}
@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Signed Tree Heads (transparency-log.adoc Section 5.3)
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::canonical;
+use crate::dsse::base64_bytes;
+use crate::merkle;
+
+/// A log's signed commitment to its tree state.
+///
+/// The signature covers the canonical JSON of `rootHash`, `timestamp` and
+/// `treeSize` exactly as they appear on the wire.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SignedTreeHead {
+    /// Log the tree head belongs to; implied by the file name when absent
+    #[serde(rename = "logId", default, skip_serializing_if = "Option::is_none")]
+    pub log_id: Option<String>,
+    #[serde(rename = "treeSize")]
+    pub tree_size: u64,
+    pub timestamp: String,
+    #[serde(rename = "rootHash")]
+    pub root_hash: String,
+    #[serde(with = "base64_bytes")]
+    pub signature: Vec<u8>,
+    /// Proofs that this tree extends earlier tree sizes. Not covered by the
+    /// signature: consistency proofs authenticate themselves against both roots.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub consistency: Vec<ConsistencyProof>,
+}
+
+/// RFC 9162 consistency proof from `fromSize` to the enclosing tree head's size
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ConsistencyProof {
+    #[serde(rename = "fromSize")]
+    pub from_size: u64,
+    pub hashes: Vec<String>,
+}
+
+impl ConsistencyProof {
+    pub fn decoded_hashes(&self) -> Result<Vec<merkle::Hash>> {
+        self.hashes.iter().map(|h| merkle::decode_hash(h)).collect()
+    }
+}
+
+impl SignedTreeHead {
+    /// Bytes covered by the log's signature
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let value = serde_json::json!({
+            "rootHash": self.root_hash,
+            "timestamp": self.timestamp,
+            "treeSize": self.tree_size,
+        });
+        canonical::canonicalize(&value).into_bytes()
+    }
+
+    /// Decoded root hash
+    pub fn root(&self) -> Result<merkle::Hash> {
+        merkle::decode_hash(&self.root_hash)
+    }
+
+    /// Verify the log's Ed25519 signature over this tree head
+    pub fn verify(&self, log_public_key: &[u8]) -> Result<()> {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let public_key = VerifyingKey::from_bytes(
+            log_public_key.try_into()
+                .context("Invalid log public key length (expected 32 bytes)")?
+        )?;
+        let signature = Signature::from_bytes(
+            self.signature.as_slice().try_into()
+                .context("Invalid STH signature length (expected 64 bytes)")?
+        );
+
+        public_key.verify(&self.signed_bytes(), &signature)
+            .context("STH signature verification failed")?;
+
+        chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .context(format!("Invalid STH timestamp: {}", self.timestamp))?;
+        self.root()?;
+
+        Ok(())
+    }
+
+    /// Consistency proof from `from_size` carried alongside this tree head, if any
+    pub fn consistency_from(&self, from_size: u64) -> Option<&ConsistencyProof> {
+        self.consistency.iter().find(|p| p.from_size == from_size)
+    }
+
+    /// Whether this tree head commits to the given root at the given size
+    pub fn matches(&self, root: &merkle::Hash, tree_size: u64) -> bool {
+        self.tree_size == tree_size && self.root().map(|r| r == *root).unwrap_or(false)
+    }
+}
+
+/// Load every `*.proof` / `*.json` tree head file from a directory.
+///
+/// The log ID is taken from the `logId` field, falling back to the file stem.
+/// A missing directory yields no tree heads.
+pub fn load_dir(dir: &Path) -> Result<Vec<(String, SignedTreeHead)>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut heads = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_sth_file = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("proof") | Some("json")
+        );
+        if !is_sth_file {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .context(format!("Failed to read tree head {:?}", path))?;
+        let sth: SignedTreeHead = serde_json::from_str(&content)
+            .context(format!("Failed to parse signed tree head {:?} (MALFORMED_BUNDLE)", path))?;
+
+        let log_id = match (&sth.log_id, path.file_stem().and_then(|s| s.to_str())) {
+            (Some(id), _) => id.clone(),
+            (None, Some(stem)) => stem.to_string(),
+            (None, None) => bail!("Cannot determine log ID for tree head {:?}", path),
+        };
+
+        heads.push((log_id, sth));
+    }
+
+    info!("Loaded {} signed tree heads from {:?}", heads.len(), dir);
+    Ok(heads)
+}
+
+/// Signed tree heads whose signatures have been verified against the trust store
+#[derive(Debug, Default)]
+pub struct VerifiedTreeHeads {
+    heads: Vec<(String, SignedTreeHead)>,
+}
+
+impl VerifiedTreeHeads {
+    /// Verify each tree head with its log's key. Heads from logs without a
+    /// trusted key are ignored; a bad signature from a trusted log is an error.
+    pub fn verify<'a>(
+        candidates: Vec<(String, SignedTreeHead)>,
+        log_key: impl Fn(&str) -> Option<&'a [u8]>,
+    ) -> Result<Self> {
+        let mut heads = Vec::new();
+
+        for (log_id, sth) in candidates {
+            let Some(key) = log_key(&log_id) else {
+                warn!("Ignoring tree head from untrusted log {}", log_id);
+                continue;
+            };
+
+            sth.verify(key)
+                .context(format!("STH_INVALID: tree head for log {} at size {} failed verification",
+                    log_id, sth.tree_size))?;
+            heads.push((log_id, sth));
+        }
+
+        Ok(Self { heads })
+    }
+
+    /// All verified tree heads with their log IDs
+    pub fn iter(&self) -> impl Iterator<Item = &(String, SignedTreeHead)> {
+        self.heads.iter()
+    }
+
+    pub fn extend(&mut self, other: VerifiedTreeHeads) {
+        self.heads.extend(other.heads);
+    }
+
+    /// Find a verified tree head for `log_id` committing to `root` at `tree_size`
+    pub fn find(&self, log_id: &str, root: &merkle::Hash, tree_size: u64) -> Option<&SignedTreeHead> {
+        self.heads
+            .iter()
+            .filter(|(id, _)| id == log_id)
+            .map(|(_, sth)| sth)
+            .find(|sth| sth.matches(root, tree_size))
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    /// Sign a tree head with a test log key
+    pub fn signed(key: &SigningKey, tree_size: u64, root: &merkle::Hash) -> SignedTreeHead {
+        let mut sth = SignedTreeHead {
+            log_id: None,
+            tree_size,
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            root_hash: hex::encode(root),
+            signature: vec![],
+            consistency: vec![],
+        };
+        sth.signature = key.sign(&sth.signed_bytes()).to_bytes().to_vec();
+        sth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_sth_signature_verification() {
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let sth = testing::signed(&key, 10, &[1u8; 32]);
+        assert!(sth.verify(&public).is_ok());
+
+        let mut tampered = sth.clone();
+        tampered.tree_size = 11;
+        assert!(tampered.verify(&public).is_err());
+
+        let other = SigningKey::from_bytes(&[4u8; 32]).verifying_key().to_bytes();
+        assert!(sth.verify(&other).is_err());
+    }
+
+    #[test]
+    fn test_load_dir_uses_file_stem_as_log_id() {
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let dir = tempfile::tempdir().unwrap();
+        let sth = testing::signed(&key, 4, &[2u8; 32]);
+        std::fs::write(dir.path().join("log-eu.proof"), serde_json::to_string(&sth).unwrap()).unwrap();
+        std::fs::write(dir.path().join("README"), "ignored").unwrap();
+
+        let heads = load_dir(dir.path()).unwrap();
+        assert_eq!(heads, vec![("log-eu".to_string(), sth)]);
+        assert!(load_dir(&dir.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_verified_heads_reject_bad_signature_from_trusted_log() {
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let mut sth = testing::signed(&key, 4, &[2u8; 32]);
+
+        let heads = VerifiedTreeHeads::verify(
+            vec![("log-eu".to_string(), sth.clone()), ("unknown".to_string(), sth.clone())],
+            |id| (id == "log-eu").then_some(public.as_slice()),
+        ).unwrap();
+        assert!(heads.find("log-eu", &[2u8; 32], 4).is_some());
+        assert!(heads.find("log-eu", &[2u8; 32], 5).is_none());
+        assert!(heads.find("unknown", &[2u8; 32], 4).is_none());
+
+        sth.root_hash = hex::encode([9u8; 32]);
+        assert!(VerifiedTreeHeads::verify(
+            vec![("log-eu".to_string(), sth)],
+            |_| Some(public.as_slice()),
+        ).is_err());
+    }
+}
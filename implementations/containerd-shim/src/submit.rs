@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Producer submission to a quorum of transparency logs (transparency-log.adoc Section 8.1)
+
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::dsse::Envelope;
+use crate::log_client::LogClient;
+use crate::log_server::SubmitResponse;
+use crate::merkle;
+use crate::tlog;
+
+/// A log to submit to, given on the command line as `<logId>=<url>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogTarget {
+    pub log_id: String,
+    pub url: String,
+}
+
+impl LogTarget {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (log_id, url) = spec.split_once('=')
+            .filter(|(id, url)| !id.is_empty() && !url.is_empty())
+            .context(format!("invalid input: log {:?} is not of the form <logId>=<url>", spec))?;
+        Ok(Self {
+            log_id: log_id.to_string(),
+            url: url.to_string(),
+        })
+    }
+}
+
+/// Submit `envelope` to every target in parallel, failing unless at least
+/// `quorum` logs accepted it. Receipts are returned in target order.
+pub async fn submit_to_quorum(
+    envelope: &Envelope,
+    targets: &[LogTarget],
+    quorum: usize,
+    timeout: Duration,
+    retries: u32,
+) -> Result<Vec<SubmitResponse>> {
+    let distinct: BTreeSet<&str> = targets.iter().map(|t| t.log_id.as_str()).collect();
+    if distinct.len() != targets.len() {
+        bail!("invalid input: each log may only be given once");
+    }
+    if targets.len() < quorum {
+        bail!("invalid input: {} logs given but the quorum is {}", targets.len(), quorum);
+    }
+
+    let leaf = tlog::Entry::from_envelope(envelope)
+        .context("invalid input: envelope cannot be logged")?
+        .leaf_hash()?;
+
+    let mut tasks = JoinSet::new();
+    for target in targets.iter().cloned() {
+        let envelope = envelope.clone();
+        tasks.spawn(async move {
+            let result = match LogClient::new(&target.url, timeout, retries) {
+                Ok(client) => client.submit(&envelope).await,
+                Err(e) => Err(e),
+            };
+            (target, result)
+        });
+    }
+
+    let mut accepted = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (target, result) = joined?;
+        match result.and_then(|response| check_receipt(&target, &leaf, response)) {
+            Ok(response) => {
+                info!("Log {} integrated the entry at index {}", target.log_id, response.entry.log_index);
+                accepted.push(response);
+            }
+            Err(e) => warn!("Log {} did not accept the entry: {:#}", target.log_id, e),
+        }
+    }
+
+    if accepted.len() < quorum {
+        bail!(
+            "QUORUM_NOT_MET: {} of {} logs accepted the entry, need {}",
+            accepted.len(), targets.len(), quorum
+        );
+    }
+
+    accepted.sort_by_key(|r| targets.iter().position(|t| t.log_id == r.entry.log_id));
+    Ok(accepted)
+}
+
+/// Check that a receipt is for our entry and internally consistent. Log
+/// signatures are left to verifiers, which hold the log keys.
+fn check_receipt(target: &LogTarget, leaf: &merkle::Hash, response: SubmitResponse) -> Result<SubmitResponse> {
+    let entry = &response.entry;
+    if entry.log_id != target.log_id {
+        bail!("Log answered as {} instead of {}", entry.log_id, target.log_id);
+    }
+
+    let proof = &entry.inclusion_proof;
+    let root = merkle::decode_hash(&proof.root_hash)?;
+    if proof.log_index != entry.log_index || !response.signed_tree_head.matches(&root, proof.tree_size) {
+        bail!("Inclusion proof does not match the returned entry and tree head");
+    }
+
+    let hashes = proof.hashes.iter().map(|h| merkle::decode_hash(h)).collect::<Result<Vec<_>>>()?;
+    merkle::verify_inclusion(leaf, proof.log_index, proof.tree_size, &hashes, &root)
+        .context("Inclusion proof does not cover the submitted entry")?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsse;
+    use crate::log_server;
+    use crate::signing::Signer;
+    use ed25519_dalek::SigningKey;
+
+    fn envelope() -> Envelope {
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "image", "digest": {"sha256": "cd".repeat(32)}}],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {}
+        });
+        dsse::sign_statement(&statement, &Signer::File(SigningKey::from_bytes(&[1u8; 32])), "builder").unwrap()
+    }
+
+    fn target(log_id: &str, url: &str) -> LogTarget {
+        LogTarget { log_id: log_id.to_string(), url: url.to_string() }
+    }
+
+    #[test]
+    fn test_log_target_parse() {
+        assert_eq!(LogTarget::parse("log-eu=http://127.0.0.1:3001").unwrap(), target("log-eu", "http://127.0.0.1:3001"));
+        assert!(LogTarget::parse("http://127.0.0.1:3001").is_err());
+        assert!(LogTarget::parse("log-eu=").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_submit_requires_quorum() {
+        let eu_dir = tempfile::tempdir().unwrap();
+        let us_dir = tempfile::tempdir().unwrap();
+        let eu = log_server::testing::spawn(eu_dir.path(), "log-eu", &SigningKey::from_bytes(&[2u8; 32])).await;
+        let us = log_server::testing::spawn(us_dir.path(), "log-us", &SigningKey::from_bytes(&[3u8; 32])).await;
+        let timeout = Duration::from_secs(2);
+
+        let targets = vec![
+            target("log-eu", &eu),
+            target("log-ap", "http://127.0.0.1:9"),
+            target("log-us", &us),
+        ];
+        let receipts = submit_to_quorum(&envelope(), &targets, 2, timeout, 0).await.unwrap();
+        let ids: Vec<_> = receipts.iter().map(|r| r.entry.log_id.as_str()).collect();
+        assert_eq!(ids, ["log-eu", "log-us"]);
+
+        let err = submit_to_quorum(&envelope(), &targets, 3, timeout, 0).await.unwrap_err();
+        assert!(err.to_string().contains("QUORUM_NOT_MET"));
+
+        // A log answering under another identity does not count
+        let mislabelled = vec![target("log-eu", &eu), target("log-ap", &us)];
+        assert!(submit_to_quorum(&envelope(), &mislabelled, 2, timeout, 0).await.is_err());
+    }
+}
@@ -0,0 +1,418 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// OpenTelemetry trace export
+//
+// A tracing layer that turns this crate's spans into OTLP spans, and an
+// exporter that sends them as OTLP/JSON (ExportTraceServiceRequest) to a
+// collector's `/v1/traces` endpoint and/or appends them to a file, one
+// request per line as read by the collector's `otlpjsonfile` receiver.
+// Events inside a span become span events; an `error` field, as recorded by
+// `#[instrument(err)]`, marks the span as failed.
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber, warn};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::Context as LayerContext;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::config::TelemetryConfig;
+
+/// OTLP `Status.code` of a failed span
+const STATUS_CODE_ERROR: u8 = 2;
+/// OTLP `SpanKind` for spans within the process
+const SPAN_KIND_INTERNAL: u8 = 1;
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+type TraceId = [u8; 16];
+type SpanId = [u8; 8];
+
+/// The layer to add to the subscriber, if export is configured. Only this
+/// crate's spans and events are exported, down to DEBUG.
+pub fn layer<S>(config: &TelemetryConfig) -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if config.otlp_endpoint.is_none() && config.otlp_file.is_none() {
+        return None;
+    }
+    let (exporter, layer) = Exporter::new(config, std::env::var("TRACEPARENT").ok().as_deref());
+    EXPORTER.set(exporter).ok()?;
+    Some(layer.with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::DEBUG)))
+}
+
+/// Export the spans that have ended so far. Failures are logged: tracing
+/// never affects a verification decision.
+pub async fn flush() {
+    if let Some(exporter) = EXPORTER.get() {
+        if let Err(e) = exporter.flush().await {
+            warn!("Failed to export traces: {:#}", e);
+        }
+    }
+}
+
+/// Trace and parent span of a W3C `traceparent` header value
+fn parse_traceparent(value: &str) -> Option<(TraceId, SpanId)> {
+    let mut parts = value.trim().split('-');
+    let (Some("00"), Some(trace), Some(span), Some(_flags)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let trace_id: TraceId = hex::decode(trace).ok()?.try_into().ok()?;
+    let span_id: SpanId = hex::decode(span).ok()?.try_into().ok()?;
+    (trace_id != [0; 16] && span_id != [0; 8]).then_some((trace_id, span_id))
+}
+
+/// A random, non-zero ID
+fn random_id<const N: usize>() -> [u8; N] {
+    let mut id = [0u8; N];
+    if getrandom::getrandom(&mut id).is_err() || id == [0u8; N] {
+        let nanos = unix_nanos(SystemTime::now()).to_le_bytes();
+        for (i, byte) in id.iter_mut().enumerate() {
+            *byte = nanos[i % nanos.len()] ^ (i as u8 + 1);
+        }
+    }
+    id
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_nanos())
+}
+
+fn key_value(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+/// OTLP attributes, in the order first recorded
+#[derive(Default)]
+struct FieldVisitor {
+    attributes: Vec<(String, Value)>,
+}
+
+impl FieldVisitor {
+    fn set(&mut self, field: &Field, value: Value) {
+        match self.attributes.iter_mut().find(|(key, _)| key == field.name()) {
+            Some((_, existing)) => *existing = value,
+            None => self.attributes.push((field.name().to_string(), value)),
+        }
+    }
+
+    /// Remove a field, returning it as a string
+    fn take(&mut self, name: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|(key, _)| key == name)?;
+        let (_, value) = self.attributes.remove(index);
+        Some(match value.get("stringValue").and_then(Value::as_str) {
+            Some(s) => s.to_string(),
+            None => value.to_string(),
+        })
+    }
+
+    fn to_json(&self) -> Vec<Value> {
+        self.attributes.iter().map(|(key, value)| key_value(key, value.clone())).collect()
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.set(field, json!({ "stringValue": format!("{:?}", value) }));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, json!({ "stringValue": value }));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.set(field, json!({ "stringValue": value.to_string() }));
+    }
+
+    // 64-bit integers are strings in the protobuf JSON mapping
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, json!({ "boolValue": value }));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, json!({ "doubleValue": value }));
+    }
+}
+
+/// A span being recorded, kept in the registry's span extensions
+struct SpanData {
+    trace_id: TraceId,
+    span_id: SpanId,
+    parent_span_id: Option<SpanId>,
+    name: &'static str,
+    start: SystemTime,
+    fields: FieldVisitor,
+    events: Vec<Value>,
+    error: Option<String>,
+}
+
+impl SpanData {
+    fn to_json(&self, end: SystemTime) -> Value {
+        let mut span = json!({
+            "traceId": hex::encode(self.trace_id),
+            "spanId": hex::encode(self.span_id),
+            "name": self.name,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": unix_nanos(self.start).to_string(),
+            "endTimeUnixNano": unix_nanos(end).to_string(),
+            "attributes": self.fields.to_json(),
+            "events": self.events,
+        });
+        if let Some(parent) = self.parent_span_id {
+            span["parentSpanId"] = json!(hex::encode(parent));
+        }
+        if let Some(error) = &self.error {
+            span["status"] = json!({ "code": STATUS_CODE_ERROR, "message": error });
+        }
+        span
+    }
+}
+
+pub struct OtlpLayer {
+    /// Ended spans not yet exported
+    finished: Arc<Mutex<Vec<Value>>>,
+    /// Parent of root spans, from TRACEPARENT
+    remote_parent: Option<(TraceId, SpanId)>,
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let parent = span.parent()
+            .and_then(|parent| parent.extensions().get::<SpanData>().map(|p| (p.trace_id, p.span_id)))
+            .or(self.remote_parent);
+        let mut data = SpanData {
+            trace_id: parent.map_or_else(random_id, |(trace_id, _)| trace_id),
+            span_id: random_id(),
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            name: attrs.metadata().name(),
+            start: SystemTime::now(),
+            fields: FieldVisitor::default(),
+            events: Vec::new(),
+            error: None,
+        };
+        attrs.record(&mut data.fields);
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(data) = extensions.get_mut::<SpanData>() {
+            values.record(&mut data.fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.event_span(event) else { return };
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<SpanData>() else { return };
+
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        if let Some(error) = fields.take("error") {
+            data.error = Some(error);
+            return;
+        }
+        let message = fields.take("message").unwrap_or_else(|| event.metadata().name().to_string());
+        let mut attributes = fields.to_json();
+        attributes.push(key_value("level", json!({ "stringValue": event.metadata().level().as_str() })));
+        data.events.push(json!({
+            "timeUnixNano": unix_nanos(SystemTime::now()).to_string(),
+            "name": message,
+            "attributes": attributes,
+        }));
+    }
+
+    fn on_close(&self, id: Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let data = span.extensions_mut().remove::<SpanData>();
+        if let Some(data) = data {
+            self.finished.lock().unwrap_or_else(|e| e.into_inner()).push(data.to_json(SystemTime::now()));
+        }
+    }
+}
+
+struct Exporter {
+    config: TelemetryConfig,
+    finished: Arc<Mutex<Vec<Value>>>,
+}
+
+impl Exporter {
+    fn new(config: &TelemetryConfig, traceparent: Option<&str>) -> (Self, OtlpLayer) {
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let layer = OtlpLayer {
+            finished: finished.clone(),
+            remote_parent: traceparent.and_then(parse_traceparent),
+        };
+        (Self { config: config.clone(), finished }, layer)
+    }
+
+    /// ExportTraceServiceRequest for `spans`
+    fn request(&self, spans: Vec<Value>) -> Value {
+        let resource = vec![
+            key_value("service.name", json!({ "stringValue": self.config.service_name })),
+            key_value("service.version", json!({ "stringValue": env!("CARGO_PKG_VERSION") })),
+            key_value("host.name", json!({ "stringValue": crate::audit::host_name() })),
+            key_value("process.pid", json!({ "intValue": std::process::id().to_string() })),
+        ];
+        json!({
+            "resourceSpans": [{
+                "resource": { "attributes": resource },
+                "scopeSpans": [{
+                    "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        })
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let spans = std::mem::take(&mut *self.finished.lock().unwrap_or_else(|e| e.into_inner()));
+        if spans.is_empty() {
+            return Ok(());
+        }
+        let request = self.request(spans);
+
+        if let Some(path) = &self.config.otlp_file {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create trace directory")?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)
+                .context(format!("Failed to open trace file {:?}", path))?;
+            writeln!(file, "{}", request).context(format!("Failed to write trace file {:?}", path))?;
+        }
+
+        if let Some(endpoint) = &self.config.otlp_endpoint {
+            let endpoint = endpoint.trim_end_matches('/');
+            let url = if endpoint.ends_with("/v1/traces") {
+                endpoint.to_string()
+            } else {
+                format!("{}/v1/traces", endpoint)
+            };
+            let response = reqwest::Client::new()
+                .post(&url)
+                .timeout(Duration::from_secs(self.config.timeout_secs))
+                .json(&request)
+                .send()
+                .await
+                .context(format!("Failed to send traces to {}", url))?;
+            if !response.status().is_success() {
+                bail!("Collector {} rejected traces: {}", url, response.status());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn attribute<'a>(span: &'a Value, key: &str) -> Option<&'a Value> {
+        span["attributes"].as_array()?.iter()
+            .find(|a| a["key"] == key)
+            .map(|a| &a["value"])
+    }
+
+    #[tokio::test]
+    async fn test_spans_nest_under_the_remote_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TelemetryConfig {
+            otlp_file: Some(dir.path().join("traces/spans.jsonl")),
+            ..TelemetryConfig::default()
+        };
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let (exporter, layer) = Exporter::new(&config, Some(traceparent));
+
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let root = tracing::info_span!("verify_bundle", digest = "sha256:aa", outcome = tracing::field::Empty);
+            let _root = root.enter();
+            {
+                let _signature = tracing::info_span!("verify_signature", keyid = "builder").entered();
+                tracing::info!(count = 2, "Signature verified");
+            }
+            {
+                let _log = tracing::info_span!("verify_log_entry", log_id = "log-a").entered();
+                tracing::error!(error = "SET_INVALID: bad timestamp");
+            }
+            root.record("outcome", "REJECT");
+        });
+        exporter.flush().await.unwrap();
+        // Nothing left to export
+        exporter.flush().await.unwrap();
+
+        let lines = std::fs::read_to_string(config.otlp_file.as_ref().unwrap()).unwrap();
+        assert_eq!(lines.lines().count(), 1);
+        let request: Value = serde_json::from_str(&lines).unwrap();
+        let resource = &request["resourceSpans"][0];
+        assert_eq!(attribute(&resource["resource"], "service.name").unwrap()["stringValue"], "verified-container-shim");
+        let spans = resource["scopeSpans"][0]["spans"].as_array().unwrap();
+        let names: Vec<&str> = spans.iter().map(|s| s["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["verify_signature", "verify_log_entry", "verify_bundle"]);
+
+        let (signature, log, root) = (&spans[0], &spans[1], &spans[2]);
+        for span in spans {
+            assert_eq!(span["traceId"], "0af7651916cd43dd8448eb211c80319c");
+        }
+        assert_eq!(root["parentSpanId"], "b7ad6b7169203331");
+        assert_eq!(signature["parentSpanId"], root["spanId"]);
+        assert_eq!(log["parentSpanId"], root["spanId"]);
+
+        assert_eq!(attribute(root, "digest").unwrap()["stringValue"], "sha256:aa");
+        assert_eq!(attribute(root, "outcome").unwrap()["stringValue"], "REJECT");
+        assert_eq!(attribute(signature, "keyid").unwrap()["stringValue"], "builder");
+        assert_eq!(signature["events"][0]["name"], "Signature verified");
+        assert_eq!(attribute(&signature["events"][0], "count").unwrap()["intValue"], "2");
+        assert!(signature.get("status").is_none());
+        assert_eq!(log["status"], json!({ "code": 2, "message": "SET_INVALID: bad timestamp" }));
+    }
+
+    #[tokio::test]
+    async fn test_spans_are_posted_to_the_collector() {
+        use axum::{Router, extract::State, routing::post};
+
+        let received: Arc<Mutex<Vec<Value>>> = Arc::default();
+        let app = Router::new()
+            .route("/v1/traces", post(|State(received): State<Arc<Mutex<Vec<Value>>>>, axum::Json(body): axum::Json<Value>| async move {
+                received.lock().unwrap().push(body);
+                axum::Json(json!({}))
+            }))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = TelemetryConfig { otlp_endpoint: Some(endpoint), ..TelemetryConfig::default() };
+        let (exporter, layer) = Exporter::new(&config, Some("not a traceparent"));
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info_span!("load_bundle").in_scope(|| {});
+        });
+        exporter.flush().await.unwrap();
+
+        let received = received.lock().unwrap();
+        let span = &received[0]["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["name"], "load_bundle");
+        assert!(span.get("parentSpanId").is_none());
+        assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+    }
+}
@@ -0,0 +1,459 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// C2SP tiled transparency logs (https://c2sp.org/tlog-tiles) and signed-note
+// checkpoints (https://c2sp.org/tlog-checkpoint, https://c2sp.org/signed-note)
+//
+// A tiled log publishes its Merkle tree as static files of 256 hashes each,
+// so inclusion and consistency proofs can be computed from a local copy or a
+// plain HTTP mirror without a proof API. Tile hashes use the same RFC 9162
+// hashing as the rest of the shim.
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::log_client::NetworkError;
+use crate::merkle::{self, Hash};
+
+/// Tile height: each full tile holds 2^8 hashes
+const TILE_HEIGHT: u32 = 8;
+const TILE_WIDTH: u64 = 1 << TILE_HEIGHT;
+
+/// Signature algorithm byte for Ed25519 note keys
+const NOTE_ALG_ED25519: u8 = 0x01;
+
+/// Where tiles and the checkpoint are read from
+pub enum TileSource {
+    Dir(PathBuf),
+    Http { url: String, http: reqwest::Client },
+}
+
+impl TileSource {
+    /// `http(s)://` URLs are mirrors, anything else a local directory
+    pub fn parse(spec: &str, timeout: Duration) -> Result<Self> {
+        if spec.starts_with("http://") || spec.starts_with("https://") {
+            Ok(Self::Http {
+                url: spec.trim_end_matches('/').to_string(),
+                http: reqwest::Client::builder().timeout(timeout).build()?,
+            })
+        } else {
+            Ok(Self::Dir(PathBuf::from(spec)))
+        }
+    }
+
+    /// Contents of `path` below the log root, or None if it does not exist
+    async fn fetch(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::Dir(dir) => match std::fs::read(dir.join(path)) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e).context(format!("Failed to read {}", path)),
+            },
+            Self::Http { url, http } => {
+                let response = http.get(format!("{}/{}", url, path)).send().await
+                    .map_err(|e| NetworkError(format!("tile mirror {} unavailable: {}", url, e)))?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                if !response.status().is_success() {
+                    let message = format!("tile mirror {} returned HTTP {} for {}", url, response.status(), path);
+                    return Err(NetworkError(message).into());
+                }
+                Ok(Some(response.bytes().await?.to_vec()))
+            }
+        }
+    }
+}
+
+/// A checkpoint whose note signature has been verified
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub origin: String,
+    pub tree_size: u64,
+    pub root_hash: Hash,
+}
+
+/// An Ed25519 signed-note verifier key, `<name>+<hash>+<base64 key>`
+#[derive(Debug, Clone)]
+pub struct NoteVerifier {
+    pub name: String,
+    key_hash: [u8; 4],
+    public_key: [u8; 32],
+}
+
+impl NoteVerifier {
+    pub fn parse(vkey: &str) -> Result<Self> {
+        let mut parts = vkey.splitn(3, '+');
+        let (Some(name), Some(hash), Some(key)) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("Note verifier key {:?} is not of the form <name>+<hash>+<key>", vkey);
+        };
+        let key = general_purpose::STANDARD.decode(key)
+            .context("Note verifier key is not valid base64")?;
+        let (&[NOTE_ALG_ED25519], public_key) = key.split_at_checked(1).context("Empty note verifier key")? else {
+            bail!("Note verifier key for {} is not an Ed25519 key", name);
+        };
+
+        let verifier = Self::new(name, public_key.try_into().context("Ed25519 note key must be 32 bytes")?);
+        if hex::encode(verifier.key_hash) != hash {
+            bail!("Note verifier key hash {} does not match the key for {}", hash, name);
+        }
+        Ok(verifier)
+    }
+
+    pub fn new(name: &str, public_key: [u8; 32]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(name.as_bytes());
+        hasher.update([b'\n', NOTE_ALG_ED25519]);
+        hasher.update(public_key);
+        let digest = hasher.finalize();
+        Self {
+            name: name.to_string(),
+            key_hash: digest[..4].try_into().unwrap(),
+            public_key,
+        }
+    }
+
+    /// Verify a signed checkpoint note for the log `origin` and parse its
+    /// body. At least one signature line must come from this key; others
+    /// are ignored.
+    pub fn verify_checkpoint(&self, note: &str, origin: &str) -> Result<Checkpoint> {
+        let split = note.find("\n\n").context("STH_INVALID: checkpoint note has no signatures")?;
+        let (text, signatures) = (&note[..split + 1], &note[split + 2..]);
+
+        let key = VerifyingKey::from_bytes(&self.public_key)?;
+        let signed = signatures.lines().any(|line| {
+            let Some((name, sig)) = line.strip_prefix("\u{2014} ").and_then(|l| l.split_once(' ')) else {
+                return false;
+            };
+            let Ok(sig) = general_purpose::STANDARD.decode(sig) else { return false };
+            name == self.name
+                && sig.len() == 68
+                && sig[..4] == self.key_hash
+                && key.verify(text.as_bytes(), &Signature::from_bytes(sig[4..].try_into().unwrap())).is_ok()
+        });
+        if !signed {
+            bail!("STH_INVALID: checkpoint is not signed by {}", self.name);
+        }
+
+        let mut lines = text.lines();
+        let (Some(origin_line), Some(size), Some(root)) = (lines.next(), lines.next(), lines.next()) else {
+            bail!("STH_INVALID: checkpoint body is truncated");
+        };
+        // One key may sign checkpoints for several logs
+        if origin_line != origin {
+            bail!("STH_INVALID: checkpoint is for log {:?}, expected {:?}", origin_line, origin);
+        }
+        let root = general_purpose::STANDARD.decode(root)
+            .context("STH_INVALID: checkpoint root hash is not valid base64")?;
+        Ok(Checkpoint {
+            origin: origin_line.to_string(),
+            tree_size: size.parse().context("STH_INVALID: checkpoint tree size is not a number")?,
+            root_hash: root.as_slice().try_into().context("STH_INVALID: checkpoint root hash must be 32 bytes")?,
+        })
+    }
+}
+
+/// Path of tile `index` at `level`, e.g. `tile/0/x001/234.p/5`
+fn tile_path(level: u32, index: u64, width: u64) -> String {
+    let digits = format!("{:03}", index);
+    let groups: Vec<_> = digits.as_bytes().rchunks(3).rev().map(|c| std::str::from_utf8(c).unwrap()).collect();
+    let mut path = format!("tile/{}/", level);
+    for group in &groups[..groups.len() - 1] {
+        path.push_str(&format!("x{:0>3}/", group));
+    }
+    path.push_str(&format!("{:0>3}", groups[groups.len() - 1]));
+    if width < TILE_WIDTH {
+        path.push_str(&format!(".p/{}", width));
+    }
+    path
+}
+
+/// Reads hashes from a tiled log at a fixed tree size
+pub struct TileReader {
+    source: TileSource,
+    tree_size: u64,
+    tiles: HashMap<String, Vec<Hash>>,
+}
+
+impl TileReader {
+    /// Fetch and verify the current checkpoint of the log `origin`,
+    /// returning a reader for the tree it commits to
+    pub async fn open(source: TileSource, verifier: &NoteVerifier, origin: &str) -> Result<(Checkpoint, Self)> {
+        let note = source.fetch("checkpoint").await?
+            .context("Tiled log has no checkpoint")?;
+        let checkpoint = verifier.verify_checkpoint(&String::from_utf8(note).context("Checkpoint is not UTF-8")?, origin)?;
+        let mut reader = Self::at(source, checkpoint.tree_size);
+
+        // Tiles that do not reproduce the signed root would yield proofs
+        // no verifier accepts; fail here rather than later
+        if reader.root().await? != checkpoint.root_hash {
+            bail!("LOG_PROOF_INVALID: tiles do not match the root of checkpoint {} at size {}",
+                checkpoint.origin, checkpoint.tree_size);
+        }
+        Ok((checkpoint, reader))
+    }
+
+    /// Reader for the tree of `tree_size` leaves, e.g. a stored checkpoint
+    pub fn at(source: TileSource, tree_size: u64) -> Self {
+        Self { source, tree_size, tiles: HashMap::new() }
+    }
+
+    /// Root hash of the tree, recomputed from tiles
+    pub async fn root(&mut self) -> Result<Hash> {
+        self.range_hash(0, self.tree_size).await
+    }
+
+    /// RFC 9162 inclusion proof for leaf `index`
+    pub async fn inclusion_proof(&mut self, index: u64) -> Result<Vec<Hash>> {
+        if index >= self.tree_size {
+            bail!("No entry {} in a tree of size {}", index, self.tree_size);
+        }
+        let mut ranges = Vec::new();
+        inclusion_ranges(index, 0, self.tree_size, &mut ranges);
+        self.resolve(ranges).await
+    }
+
+    /// RFC 9162 consistency proof from `first_size` to this tree
+    pub async fn consistency_proof(&mut self, first_size: u64) -> Result<Vec<Hash>> {
+        if first_size > self.tree_size {
+            bail!("Cannot prove consistency from size {} to {}", first_size, self.tree_size);
+        }
+        let mut ranges = Vec::new();
+        if first_size > 0 && first_size < self.tree_size {
+            consistency_ranges(first_size, 0, self.tree_size, true, &mut ranges);
+        }
+        self.resolve(ranges).await
+    }
+
+    async fn resolve(&mut self, ranges: Vec<(u64, u64)>) -> Result<Vec<Hash>> {
+        let mut hashes = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            hashes.push(self.range_hash(lo, hi).await?);
+        }
+        Ok(hashes)
+    }
+
+    /// Merkle tree hash of leaves `lo..hi`: the right fold of its perfect
+    /// subtrees, largest first (RFC 9162 Section 2.1.1)
+    async fn range_hash(&mut self, lo: u64, hi: u64) -> Result<Hash> {
+        if lo >= hi {
+            return Ok(merkle::tree_root(&[]));
+        }
+        let mut subtrees = Vec::new();
+        let mut start = lo;
+        while start < hi {
+            let height = 63 - (hi - start).leading_zeros();
+            subtrees.push(self.node(height, start >> height).await?);
+            start += 1 << height;
+        }
+        let mut hash = subtrees.pop().unwrap();
+        while let Some(left) = subtrees.pop() {
+            hash = merkle::node_hash(&left, &hash);
+        }
+        Ok(hash)
+    }
+
+    /// Hash of the perfect subtree of 2^height leaves at `index`
+    async fn node(&mut self, height: u32, index: u64) -> Result<Hash> {
+        let level = height / TILE_HEIGHT;
+        let span = 1u64 << (height % TILE_HEIGHT);
+        let first = index * span;
+
+        let tile_index = first / TILE_WIDTH;
+        let offset = (first % TILE_WIDTH) as usize;
+        let hashes = self.tile(level, tile_index).await?;
+        let Some(hashes) = hashes.get(offset..offset + span as usize) else {
+            bail!("Tile {}/{} is too short for node {} at height {}", level, tile_index, index, height);
+        };
+
+        Ok(merkle::tree_root(hashes))
+    }
+
+    /// Hashes of tile `index` at `level`, as wide as the tree size allows.
+    /// A partial tile may have been replaced by the full one.
+    async fn tile(&mut self, level: u32, index: u64) -> Result<&[Hash]> {
+        let width = ((self.tree_size >> (level * TILE_HEIGHT)) - index * TILE_WIDTH).min(TILE_WIDTH);
+        let path = tile_path(level, index, width);
+
+        if !self.tiles.contains_key(&path) {
+            debug!("Reading tile {}", path);
+            let mut bytes = self.source.fetch(&path).await?;
+            if bytes.is_none() && width < TILE_WIDTH {
+                bytes = self.source.fetch(&tile_path(level, index, TILE_WIDTH)).await?;
+            }
+            let bytes = bytes.context(format!("Tile {} not found", path))?;
+            if bytes.len() < width as usize * 32 || bytes.len() % 32 != 0 {
+                bail!("Tile {} has {} bytes, expected {} hashes", path, bytes.len(), width);
+            }
+            let hashes = bytes.chunks(32).take(width as usize).map(|h| h.try_into().unwrap()).collect();
+            self.tiles.insert(path.clone(), hashes);
+        }
+        Ok(&self.tiles[&path])
+    }
+}
+
+/// Leaf ranges whose hashes form the inclusion proof for `m` (RFC 9162 PATH)
+fn inclusion_ranges(m: u64, lo: u64, hi: u64, out: &mut Vec<(u64, u64)>) {
+    if hi - lo <= 1 {
+        return;
+    }
+    let k = merkle::split((hi - lo) as usize) as u64;
+    if m < lo + k {
+        inclusion_ranges(m, lo, lo + k, out);
+        out.push((lo + k, hi));
+    } else {
+        inclusion_ranges(m, lo + k, hi, out);
+        out.push((lo, lo + k));
+    }
+}
+
+/// Leaf ranges whose hashes form the consistency proof from size `m` (RFC 9162 SUBPROOF)
+fn consistency_ranges(m: u64, lo: u64, hi: u64, complete: bool, out: &mut Vec<(u64, u64)>) {
+    if m == hi {
+        if !complete {
+            out.push((lo, hi));
+        }
+        return;
+    }
+    let k = merkle::split((hi - lo) as usize) as u64;
+    if m <= lo + k {
+        consistency_ranges(m, lo, lo + k, complete, out);
+        out.push((lo + k, hi));
+    } else {
+        consistency_ranges(m, lo + k, hi, false, out);
+        out.push((lo, lo + k));
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::path::Path;
+
+    /// Write the tiles of a tree with `leaves` into `dir`, as a tiled log
+    /// would publish them, partial tiles included
+    pub fn write_tiles(dir: &Path, leaves: &[Hash]) {
+        let mut level_hashes = leaves.to_vec();
+        let mut level = 0;
+        while !level_hashes.is_empty() {
+            for (index, tile) in level_hashes.chunks(TILE_WIDTH as usize).enumerate() {
+                let path = dir.join(tile_path(level, index as u64, tile.len() as u64));
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, tile.concat()).unwrap();
+            }
+            level_hashes = level_hashes.chunks_exact(TILE_WIDTH as usize).map(merkle::tree_root).collect();
+            level += 1;
+        }
+    }
+
+    /// The `<name>+<hash>+<key>` verifier key for `key` under `name`
+    pub fn vkey(key: &SigningKey, name: &str) -> String {
+        let verifier = NoteVerifier::new(name, key.verifying_key().to_bytes());
+        let encoded = [&[NOTE_ALG_ED25519][..], &key.verifying_key().to_bytes()].concat();
+        format!("{}+{}+{}", name, hex::encode(verifier.key_hash), general_purpose::STANDARD.encode(encoded))
+    }
+
+    /// A checkpoint note for `root` signed by `key` under `name`
+    pub fn checkpoint_note(key: &SigningKey, name: &str, tree_size: u64, root: &Hash) -> String {
+        let text = format!("{}\n{}\n{}\n", name, tree_size, general_purpose::STANDARD.encode(root));
+        let verifier = NoteVerifier::new(name, key.verifying_key().to_bytes());
+        let sig = [&verifier.key_hash[..], &key.sign(text.as_bytes()).to_bytes()].concat();
+        format!("{}\n\u{2014} {} {}\n", text, name, general_purpose::STANDARD.encode(sig))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::reference::ReferenceTree;
+    use ed25519_dalek::SigningKey;
+
+    #[test]
+    fn test_tile_paths() {
+        assert_eq!(tile_path(0, 0, 256), "tile/0/000");
+        assert_eq!(tile_path(1, 1234, 256), "tile/1/x001/234");
+        assert_eq!(tile_path(0, 1234067, 5), "tile/0/x001/x234/067.p/5");
+    }
+
+    #[test]
+    fn test_checkpoint_note_verification() {
+        let key = SigningKey::from_bytes(&[21u8; 32]);
+        let verifier = NoteVerifier::new("example.com/log", key.verifying_key().to_bytes());
+        let root = [7u8; 32];
+        let note = testing::checkpoint_note(&key, "example.com/log", 42, &root);
+
+        let vkey = testing::vkey(&key, "example.com/log");
+        let checkpoint = NoteVerifier::parse(&vkey).unwrap().verify_checkpoint(&note, "example.com/log").unwrap();
+        assert_eq!(checkpoint, Checkpoint { origin: "example.com/log".to_string(), tree_size: 42, root_hash: root });
+
+        assert!(verifier.verify_checkpoint(&note.replace("\n42\n", "\n43\n"), "example.com/log").is_err());
+        let other = NoteVerifier::new("example.com/log", SigningKey::from_bytes(&[22u8; 32]).verifying_key().to_bytes());
+        assert!(other.verify_checkpoint(&note, "example.com/log").is_err());
+
+        // A valid checkpoint from another log under the same key
+        let err = verifier.verify_checkpoint(&note, "example.com/other").unwrap_err();
+        assert!(err.to_string().contains("expected \"example.com/other\""));
+    }
+
+    #[tokio::test]
+    async fn test_proofs_from_tiles_match_reference_tree() {
+        // Spans a full level-0 tile, a partial one and a level-1 tile
+        let tree = ReferenceTree::new(600);
+        let dir = tempfile::tempdir().unwrap();
+        testing::write_tiles(dir.path(), &tree.leaves[..300]);
+        testing::write_tiles(dir.path(), &tree.leaves);
+
+        let key = SigningKey::from_bytes(&[23u8; 32]);
+        std::fs::write(dir.path().join("checkpoint"), testing::checkpoint_note(&key, "log", 600, &tree.root())).unwrap();
+        let verifier = NoteVerifier::new("log", key.verifying_key().to_bytes());
+
+        let source = TileSource::Dir(dir.path().to_path_buf());
+        let (checkpoint, mut reader) = TileReader::open(source, &verifier, "log").await.unwrap();
+        assert_eq!(checkpoint.tree_size, 600);
+        assert_eq!(reader.root().await.unwrap(), tree.root());
+
+        for index in [0, 1, 255, 256, 511, 512, 599] {
+            assert_eq!(reader.inclusion_proof(index).await.unwrap(), tree.inclusion_proof(index), "index {}", index);
+        }
+        for size in [1, 7, 256, 300, 512, 599, 600] {
+            assert_eq!(reader.consistency_proof(size).await.unwrap(), tree.consistency_proof(size, 600), "size {}", size);
+        }
+
+        // An older tree size falls back to full tiles once the log has
+        // completed and deleted a partial one
+        std::fs::remove_file(dir.path().join("tile/0/001.p/44")).unwrap();
+        let mut older = TileReader::at(TileSource::Dir(dir.path().to_path_buf()), 300);
+        assert_eq!(older.root().await.unwrap(), tree.root_at(300));
+        assert_eq!(older.inclusion_proof(299).await.unwrap(), merkle::inclusion_proof(299, &tree.leaves[..300]));
+    }
+
+    #[tokio::test]
+    async fn test_tiles_from_http_mirror() {
+        use axum::{Router, extract::Path, http::StatusCode, routing::get};
+
+        let tree = ReferenceTree::new(20);
+        let dir = tempfile::tempdir().unwrap();
+        testing::write_tiles(dir.path(), &tree.leaves);
+        let root = dir.path().to_path_buf();
+
+        let app = Router::new().route("/log/{*path}", get(move |Path(path): Path<String>| {
+            let root = root.clone();
+            async move { std::fs::read(root.join(path)).map_err(|_| StatusCode::NOT_FOUND) }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/log", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut reader = TileReader::at(TileSource::parse(&url, Duration::from_secs(5)).unwrap(), 20);
+        assert_eq!(reader.inclusion_proof(13).await.unwrap(), tree.inclusion_proof(13));
+
+        // Missing tiles are reported, not mistaken for an empty tree
+        let mut beyond = TileReader::at(TileSource::parse(&url, Duration::from_secs(5)).unwrap(), 300);
+        assert!(beyond.root().await.is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// Transparency log entry format and Signed Entry Timestamps
+// (transparency-log.adoc Sections 5.2 and 5.4)
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+
+use crate::canonical;
+use crate::dsse::{Envelope, IN_TOTO_PAYLOAD_TYPE};
+use crate::merkle;
+
+/// Current log entry format version
+pub const ENTRY_VERSION: u32 = 1;
+
+/// DSSE payload type of a revocation submitted to a log, signed by the key
+/// its `revokedBy` names
+pub const REVOCATION_PAYLOAD_TYPE: &str = "application/vnd.verified-container.revocation+json";
+
+/// A transparency log entry as hashed into the Merkle tree.
+///
+/// The log-assigned `timestamp` is not part of the reconstructed entry: the
+/// verifier must be able to rebuild the exact leaf from the attestation alone.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Entry {
+    pub version: u32,
+    #[serde(flatten)]
+    pub body: EntryBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "entryType", content = "body", rename_all = "lowercase")]
+pub enum EntryBody {
+    Attestation(AttestationBody),
+    Revocation(RevocationBody),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AttestationBody {
+    #[serde(rename = "attestationDigest")]
+    pub attestation_digest: String,
+    #[serde(rename = "subjectDigest")]
+    pub subject_digest: String,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+}
+
+/// Revocation of a signing key or attestation digest (transparency-log.adoc Section 9.1)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RevocationBody {
+    /// Key ID or attestation digest being revoked
+    #[serde(rename = "revokedDigest")]
+    pub revoked_digest: String,
+    pub reason: String,
+    /// Key ID of the party revoking
+    #[serde(rename = "revokedBy")]
+    pub revoked_by: String,
+}
+
+impl Entry {
+    /// Build the attestation entry for a DSSE envelope
+    pub fn attestation(envelope: &Envelope, subject_digest: &str, predicate_type: &str) -> Result<Self> {
+        Ok(Self {
+            version: ENTRY_VERSION,
+            body: EntryBody::Attestation(AttestationBody {
+                attestation_digest: attestation_digest(envelope)?,
+                subject_digest: subject_digest.to_string(),
+                predicate_type: predicate_type.to_string(),
+            }),
+        })
+    }
+
+    /// Build a revocation entry
+    pub fn revocation(body: RevocationBody) -> Self {
+        Self {
+            version: ENTRY_VERSION,
+            body: EntryBody::Revocation(body),
+        }
+    }
+
+    /// Build the entry for a DSSE envelope: an attestation entry for an
+    /// in-toto statement, taking the subject and predicate type from the
+    /// statement, or a revocation entry for a signed revocation
+    pub fn from_envelope(envelope: &Envelope) -> Result<Self> {
+        if envelope.payload_type == REVOCATION_PAYLOAD_TYPE {
+            let body = serde_json::from_slice(&envelope.payload)
+                .context("Envelope payload is not a revocation")?;
+            return Ok(Self::revocation(body));
+        }
+        if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+            bail!("Unsupported payload type {}", envelope.payload_type);
+        }
+        let statement: serde_json::Value = serde_json::from_slice(&envelope.payload)
+            .context("Envelope payload is not a JSON statement")?;
+        let subject_digest = statement["subject"][0]["digest"]["sha256"].as_str()
+            .context("Statement has no sha256 subject digest")?;
+        let predicate_type = statement["predicateType"].as_str()
+            .context("Statement has no predicateType")?;
+
+        Self::attestation(envelope, &format!("sha256:{}", subject_digest), predicate_type)
+    }
+
+    /// Canonical bytes submitted to and hashed by the log
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>> {
+        let value = serde_json::to_value(self)
+            .context("Failed to serialize log entry")?;
+        Ok(canonical::canonicalize(&value).into_bytes())
+    }
+
+    /// Merkle leaf hash of this entry
+    pub fn leaf_hash(&self) -> Result<merkle::Hash> {
+        Ok(merkle::leaf_hash(&self.canonical_bytes()?))
+    }
+}
+
+/// The log's promise that an entry was integrated at a given index and time.
+///
+/// The `signedEntryTimestamp` of a bundle log entry is the log's Ed25519
+/// signature over the canonical JSON of these fields, with `integratedTime`
+/// exactly as it appears on the wire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryTimestamp {
+    pub log_id: String,
+    pub log_index: u64,
+    pub integrated_time: String,
+    pub entry_hash: merkle::Hash,
+}
+
+impl EntryTimestamp {
+    /// Bytes covered by the log's signature
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let value = serde_json::json!({
+            "entryHash": format!("sha256:{}", hex::encode(self.entry_hash)),
+            "integratedTime": self.integrated_time,
+            "logId": self.log_id,
+            "logIndex": self.log_index,
+        });
+        canonical::canonicalize(&value).into_bytes()
+    }
+
+    /// Verify the log's Ed25519 signature over this timestamp
+    pub fn verify(&self, log_public_key: &[u8], signature: &[u8]) -> Result<()> {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let public_key = VerifyingKey::from_bytes(
+            log_public_key.try_into()
+                .context("Invalid log public key length (expected 32 bytes)")?
+        )?;
+        let signature = Signature::from_bytes(
+            signature.try_into()
+                .context("Invalid SET signature length (expected 64 bytes)")?
+        );
+
+        public_key.verify(&self.signed_bytes(), &signature)
+            .context("SET signature verification failed")?;
+        Ok(())
+    }
+}
+
+/// `attestationDigest = sha256(canonicalize(dsseEnvelope))` (canonicalization.adoc Section 6.2)
+pub fn attestation_digest(envelope: &Envelope) -> Result<String> {
+    let value = serde_json::to_value(envelope)
+        .context("Failed to serialize DSSE envelope")?;
+    let digest = Sha256::digest(canonical::canonicalize(&value).as_bytes());
+    Ok(format!("sha256:{}", hex::encode(digest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsse::Signature;
+
+    fn envelope() -> Envelope {
+        Envelope {
+            payload_type: IN_TOTO_PAYLOAD_TYPE.to_string(),
+            payload: b"{\"_type\":\"https://in-toto.io/Statement/v1\"}".to_vec(),
+            signatures: vec![Signature { keyid: "builder".to_string(), sig: vec![0; 64] }],
+        }
+    }
+
+    #[test]
+    fn test_entry_canonical_form() {
+        let entry = Entry::attestation(&envelope(), "sha256:abc", "https://slsa.dev/provenance/v1").unwrap();
+        let canonical = String::from_utf8(entry.canonical_bytes().unwrap()).unwrap();
+
+        let digest = attestation_digest(&envelope()).unwrap();
+        assert_eq!(canonical, format!(
+            "{{\"body\":{{\"attestationDigest\":\"{}\",\"predicateType\":\"https://slsa.dev/provenance/v1\",\"subjectDigest\":\"sha256:abc\"}},\"entryType\":\"attestation\",\"version\":1}}",
+            digest
+        ));
+
+        let parsed: Entry = serde_json::from_str(&canonical).unwrap();
+        assert_eq!(parsed, entry);
+    }
+
+    #[test]
+    fn test_entry_timestamp_binds_every_field() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let key = SigningKey::from_bytes(&[2u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let ts = EntryTimestamp {
+            log_id: "log-eu".to_string(),
+            log_index: 7,
+            integrated_time: "2026-01-01T00:00:00Z".to_string(),
+            entry_hash: [1u8; 32],
+        };
+        let sig = key.sign(&ts.signed_bytes()).to_bytes();
+        assert!(ts.verify(&public, &sig).is_ok());
+
+        let variants = [
+            EntryTimestamp { log_id: "log-us".to_string(), ..ts.clone() },
+            EntryTimestamp { log_index: 8, ..ts.clone() },
+            EntryTimestamp { integrated_time: "2026-01-01T00:00:01Z".to_string(), ..ts.clone() },
+            EntryTimestamp { entry_hash: [2u8; 32], ..ts.clone() },
+        ];
+        for other in variants {
+            assert!(other.verify(&public, &sig).is_err());
+        }
+    }
+
+    #[test]
+    fn test_revocation_entry_form() {
+        let entry = Entry::revocation(RevocationBody {
+            revoked_digest: "sha256:abc".to_string(),
+            reason: "key_compromise".to_string(),
+            revoked_by: "sha256:def".to_string(),
+        });
+        let canonical = String::from_utf8(entry.canonical_bytes().unwrap()).unwrap();
+        assert_eq!(canonical,
+            "{\"body\":{\"reason\":\"key_compromise\",\"revokedBy\":\"sha256:def\",\"revokedDigest\":\"sha256:abc\"},\"entryType\":\"revocation\",\"version\":1}");
+        assert_ne!(entry.leaf_hash().unwrap(), Entry::attestation(&envelope(), "sha256:abc", "x").unwrap().leaf_hash().unwrap());
+    }
+
+    #[test]
+    fn test_attestation_digest_covers_signatures() {
+        let mut other = envelope();
+        other.signatures[0].sig[0] = 1;
+        assert_ne!(attestation_digest(&envelope()).unwrap(), attestation_digest(&other).unwrap());
+    }
+}
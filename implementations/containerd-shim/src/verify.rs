@@ -3,13 +3,31 @@
 
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use tracing::{info, warn};
+use tracing::{Instrument, info, info_span, instrument, warn};
 
+use crate::audit::{AuditRecord, AuditTrail, Outcome, StepResult, StepStatus};
+use crate::audit_sink::Auditor;
 use crate::bundle::CtpBundle;
+use crate::canonical;
+use crate::dsse::{Envelope, base64_bytes};
+use crate::merkle;
+use crate::cache::{CacheRecord, Decision, Lookup, VerificationCache};
+use crate::checkpoint::{CheckpointStore, Equivocation, Observation};
+use crate::config::{LogsConfig, RevocationConfig, ShimConfig};
+use crate::gossip;
+use crate::log_client::{LogClient, NetworkError};
+use crate::metrics::{self, CacheLookup};
+use crate::report::VerificationReport;
+use crate::revocation::RevocationSet;
+use crate::sth::{self, SignedTreeHead, VerifiedTreeHeads};
+use crate::tiles::{NoteVerifier, TileReader, TileSource};
+use crate::tlog;
 
 /// Verification modes (per runtime-integration.adoc Section 6.3)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VerificationMode {
     /// REJECT on any verification failure
     Strict,
@@ -35,21 +53,7 @@ struct Attestation {
     subject: Vec<Subject>,
     #[serde(rename = "predicateType")]
     predicate_type: String,
-    envelope: Option<DSSEEnvelope>,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct DSSEEnvelope {
-    #[serde(rename = "payloadType")]
-    payload_type: String,
-    payload: Vec<u8>,
-    signatures: Vec<DSSESignature>,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct DSSESignature {
-    keyid: String,
-    sig: Vec<u8>,
+    envelope: Option<Envelope>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -62,17 +66,21 @@ struct DigestSet {
     sha256: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 struct LogEntry {
     #[serde(rename = "logId")]
     log_id: String,
+    #[serde(rename = "logIndex")]
+    log_index: Option<u64>,
+    #[serde(rename = "integratedTime")]
+    integrated_time: Option<String>,
     #[serde(rename = "signedEntryTimestamp")]
     signed_entry_timestamp: String,
     #[serde(rename = "inclusionProof")]
     inclusion_proof: Option<MerkleProof>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 struct MerkleProof {
     #[serde(rename = "logIndex")]
     log_index: u64,
@@ -85,15 +93,22 @@ struct MerkleProof {
 
 /// Trust Store
 #[derive(Debug, Deserialize, Serialize)]
-struct TrustStore {
+pub struct TrustStore {
     keys: Vec<TrustedKey>,
     threshold_groups: Vec<ThresholdGroup>,
+    /// Transparency log operators keyed by log ID (trust-store.adoc Section 6.7)
+    #[serde(default)]
+    pub logs: BTreeMap<String, TrustedLog>,
+    /// SHA-256 of the canonicalized trust store document; changes with any
+    /// key, validity window, role, threshold or log
+    #[serde(skip)]
+    pub version: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-struct TrustedKey {
+pub struct TrustedKey {
     keyid: String,
-    key_bytes: Vec<u8>,
+    pub key_bytes: Vec<u8>,
     algorithm: String,
     valid_from: Option<chrono::DateTime<chrono::Utc>>,
     valid_until: Option<chrono::DateTime<chrono::Utc>>,
@@ -108,8 +123,53 @@ struct ThresholdGroup {
     member_keyids: Vec<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrustedLog {
+    operator: String,
+    /// Raw or SubjectPublicKeyInfo DER Ed25519 key, base64-encoded
+    #[serde(rename = "publicKey", with = "base64_bytes")]
+    public_key: Vec<u8>,
+    /// Base URL of the log's API (transparency-log.adoc Section 7)
+    #[serde(default)]
+    pub url: Option<String>,
+    algorithm: String,
+    /// C2SP tiled copy of the log that inclusion proofs can be computed from
+    #[serde(default)]
+    tiles: Option<TrustedTiles>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TrustedTiles {
+    /// Local tile directory or `http(s)://` mirror
+    location: String,
+    /// Signed-note verifier key for the log's checkpoints, `<name>+<hash>+<key>`
+    #[serde(rename = "noteKey")]
+    note_key: String,
+    /// Checkpoint origin line; defaults to the note key name
+    #[serde(default)]
+    origin: Option<String>,
+}
+
+impl TrustedLog {
+    /// The log's raw 32-byte Ed25519 public key
+    fn key(&self) -> Result<&[u8]> {
+        if self.algorithm != "ed25519" {
+            bail!("Unsupported log key algorithm {} for operator {}", self.algorithm, self.operator);
+        }
+        match self.public_key.len() {
+            32 => Ok(&self.public_key),
+            // SubjectPublicKeyInfo DER: fixed 12-byte prefix, then the raw key
+            44 if self.public_key.starts_with(&ED25519_SPKI_PREFIX) => Ok(&self.public_key[12..]),
+            n => bail!("Invalid public key for log operator {} ({} bytes)", self.operator, n),
+        }
+    }
+}
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo (RFC 8410)
+const ED25519_SPKI_PREFIX: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
 impl TrustStore {
-    fn load() -> Result<Self> {
+    pub fn load() -> Result<Self> {
         // Try to load from default location
         let path = std::env::var("TRUST_STORE_PATH")
             .unwrap_or_else(|_| "/etc/verified-container/trust-store.json".to_string());
@@ -119,73 +179,328 @@ fn load() -> Result<Self> {
             return Ok(Self {
                 keys: vec![],
                 threshold_groups: vec![],
+                logs: BTreeMap::new(),
+                version: trust_store_version(&serde_json::json!({})),
             });
         }
 
-        let content = std::fs::read_to_string(&path)
-            .context(format!("Failed to read trust store from {}", path))?;
+        Self::load_from(std::path::Path::new(&path))
+    }
+
+    pub fn load_from(path: &std::path::Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read trust store from {}", path.display()))?;
 
-        serde_json::from_str(&content)
-            .context("Failed to parse trust store JSON")
+        let document: serde_json::Value = serde_json::from_str(&content)
+            .context("Failed to parse trust store JSON")?;
+        let mut trust_store: Self = serde_json::from_value(document.clone())
+            .context("Failed to parse trust store JSON")?;
+        trust_store.version = trust_store_version(&document);
+        Ok(trust_store)
     }
 
-    fn get_key(&self, keyid: &str) -> Option<&TrustedKey> {
+    pub fn get_key(&self, keyid: &str) -> Option<&TrustedKey> {
         self.keys.iter().find(|k| k.keyid == keyid)
     }
 
+    fn get_log(&self, log_id: &str) -> Option<&TrustedLog> {
+        self.logs.get(log_id)
+    }
+
+    /// Ed25519 key of a trusted log, if the log is known and its key usable
+    pub fn log_key(&self, log_id: &str) -> Option<&[u8]> {
+        self.get_log(log_id)?.key().ok()
+    }
+
     fn get_threshold_group(&self, name: &str) -> Option<&ThresholdGroup> {
         self.threshold_groups.iter().find(|g| g.name == name)
     }
 }
 
 /// Verify a CTP bundle following verification-protocol.adoc
+#[instrument(skip_all, fields(
+    digest = %bundle.manifest.image_digest,
+    mode = ?mode,
+    outcome = tracing::field::Empty,
+    cached = tracing::field::Empty,
+))]
 pub async fn verify_bundle(bundle: &CtpBundle, mode: VerificationMode) -> Result<()> {
     info!("Starting verification (mode: {:?})", mode);
 
+    let mut trail = AuditTrail::default();
+    let result = check_bundle(bundle, mode, &mut trail).await;
+
+    // Record the decision, including failures the mode lets through (Section 6.8)
+    let outcome = Outcome::decide(result.is_ok(), mode);
+    let span = tracing::Span::current();
+    span.record("outcome", tracing::field::debug(outcome));
+    span.record("cached", trail.cached);
+    let auditor = Auditor::load();
+    let audited = record_verification_result(&auditor, bundle, mode, outcome, trail, result.as_ref().err());
+    match audited {
+        // In strict mode an unaudited ALLOW may be refused
+        Err(e) if result.is_ok() && auditor.is_fatal(mode) => Err(e),
+        Err(e) => {
+            warn!("Failed to audit {:?} decision: {:#}", outcome, e);
+            result
+        }
+        Ok(()) => result,
+    }
+}
+
+/// Audit a bundle that could not be loaded; it is rejected in every mode
+pub fn record_load_failure(path: &std::path::Path, mode: VerificationMode, error: &anyhow::Error) {
+    let trail = AuditTrail {
+        steps: vec![StepResult {
+            step: "load_bundle".to_string(),
+            status: StepStatus::Failed,
+            detail: Some(format!("{:#}", error)),
+        }],
+        ..AuditTrail::default()
+    };
+    let record = AuditRecord::new(&path.display().to_string(), "", mode, Outcome::Reject, trail)
+        .with_error(error);
+    metrics::record_decision(&record);
+    if let Err(e) = Auditor::load().record(record) {
+        warn!("Failed to audit rejected bundle {:?}: {:#}", path, e);
+    }
+}
+
+/// Verify an attestation bundle on its own, as the cli-contract.json
+/// `verify` command does. Nothing is cached or audited; a failed step is a
+/// REJECT in the report, not an error.
+pub async fn verify_attestation_bundle(
+    input: &AttestationInput,
+    trust_store: &TrustStore,
+    mode: VerificationMode,
+) -> Result<VerificationReport> {
+    let config = ShimConfig::load()?;
+    let mut revocations = load_revocations(&config.revocation, trust_store)?;
+    let mut trail = AuditTrail {
+        trust_store_version: Some(trust_store.version.clone()),
+        ..AuditTrail::default()
+    };
+    let result = verify_attestations(input, trust_store, &config, &mut revocations, &mut trail).await;
+    Ok(VerificationReport::new(
+        &input.path.display().to_string(),
+        mode,
+        input.image_digest.as_deref(),
+        trail,
+        result.err().as_ref(),
+    ))
+}
+
+async fn check_bundle(bundle: &CtpBundle, mode: VerificationMode, trail: &mut AuditTrail) -> Result<()> {
     // Load trust store
     let trust_store = TrustStore::load()
         .context("Failed to load trust store")?;
+    let config = ShimConfig::load()?;
+    let cache = VerificationCache::from_env(&config.cache);
+    let digest = &bundle.manifest.image_digest;
+    let ts_version = &trust_store.version;
+    if let Err(e) = cache.sync_trust_store(ts_version) {
+        warn!("Failed to invalidate cached results for the new trust store: {:#}", e);
+    }
+    let mut revocations = load_revocations(&config.revocation, &trust_store)?;
+    if !revocations.is_empty() {
+        cache.purge_revoked(&revocations);
+    }
+    trail.trust_store_version = Some(ts_version.clone());
+
+    // Concurrent starts of the same image wait here for the first one to
+    // finish and then use its cached result
+    let _lock = cache.lock_digest(digest, ts_version).await;
+
+    // Check cache first, re-checking what the cached result depends on
+    match cache.lookup(digest, ts_version) {
+        Lookup::Hit(record) if record.decision == Decision::Reject => {
+            metrics::record_cache_lookup(CacheLookup::Hit);
+            bail!(
+                "{} (cached until {})",
+                record.error.as_deref().unwrap_or("verification failed"),
+                record.expires_at
+            );
+        }
+        Lookup::Hit(record) => {
+            if config.logs.online {
+                let depends_on: Vec<String> = record.depends_on().map(str::to_string).collect();
+                query_revocations(&mut revocations, &depends_on, &trust_store, &config.logs).await?;
+            }
+            match revocations.check(record.depends_on()) {
+                Ok(()) => {
+                    info!("Using cached verification result from {}", record.verified_at);
+                    metrics::record_cache_lookup(CacheLookup::Hit);
+                    trail.keyids = record.keyids;
+                    trail.log_ids = record.log_ids;
+                    trail.cached = true;
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Discarding cached verification result: {:#}", e);
+                    cache.remove(digest, ts_version);
+                    metrics::record_cache_lookup(CacheLookup::Miss);
+                }
+            }
+        }
+        Lookup::Invalid(e) => {
+            warn!("Ignoring cached verification result: {:#}", e);
+            metrics::record_cache_lookup(CacheLookup::Invalid);
+            let cache_trail = AuditTrail {
+                trust_store_version: trail.trust_store_version.clone(),
+                ..AuditTrail::default()
+            };
+            if let Err(e) = record_verification_result(&Auditor::load(), bundle, mode, Outcome::CacheInvalid, cache_trail, Some(&e)) {
+                warn!("Failed to audit rejected cache record: {:#}", e);
+            }
+        }
+        Lookup::Miss => metrics::record_cache_lookup(CacheLookup::Miss),
+    }
 
-    // Check cache first
-    if let Some(cached_result) = check_cache(bundle, &trust_store).await? {
-        info!("Using cached verification result");
-        return Ok(cached_result);
+    let input = AttestationInput::from_ctp(bundle);
+    let record = match verify_attestations(&input, &trust_store, &config, &mut revocations, trail).await {
+        Ok(verified) => CacheRecord::allow(
+            digest,
+            ts_version,
+            verified.keyids,
+            verified.log_ids,
+            verified.attestation_digests,
+            config.cache.ttl(),
+        ),
+        Err(e) => {
+            // Network failures are transient and not worth remembering
+            if config.cache.negative_ttl_secs > 0 && e.downcast_ref::<NetworkError>().is_none() {
+                let record = CacheRecord::reject(digest, ts_version, &format!("{:#}", e), config.cache.negative_ttl());
+                if let Err(e) = cache.store(&record) {
+                    warn!("Failed to cache verification failure: {:#}", e);
+                }
+            }
+            return Err(e);
+        }
+    };
+    // The bundle verified; failing to remember that must not reject it
+    if let Err(e) = cache.store(&record) {
+        warn!("Failed to cache verification result: {:#}", e);
     }
 
+    Ok(())
+}
+
+/// An attestation bundle to verify, with the tree heads shipped alongside it
+pub struct AttestationInput {
+    pub path: std::path::PathBuf,
+    /// Image the attestations must be about; subjects are not checked
+    /// without one
+    pub image_digest: Option<String>,
+    /// Directory of signed tree heads (`*.proof`)
+    pub tree_heads: Option<std::path::PathBuf>,
+}
+
+impl AttestationInput {
+    fn from_ctp(bundle: &CtpBundle) -> Self {
+        Self {
+            path: bundle.attestation_bundle_path(),
+            image_digest: Some(bundle.manifest.image_digest.clone()),
+            tree_heads: Some(bundle.signatures_path.join("logs")),
+        }
+    }
+}
+
+/// What the cache needs to know about a verified bundle
+struct Verified {
+    keyids: Vec<String>,
+    log_ids: Vec<String>,
+    attestation_digests: Vec<String>,
+}
+
+/// Verification steps 1-5. Each step's outcome is added to `trail`.
+async fn verify_attestations(
+    input: &AttestationInput,
+    trust_store: &TrustStore,
+    config: &ShimConfig,
+    revocations: &mut RevocationSet,
+    trail: &mut AuditTrail,
+) -> Result<Verified> {
+    trail.start_timing();
+
     // Step 1: Parse attestation bundle (Section 6.3 of verification-protocol.adoc)
-    let attestation_bundle = parse_attestation_bundle(bundle)?;
+    let mut attestation_bundle = trail.step("parse_bundle", parse_attestation_bundle(&input.path))?;
+    let log_ids: BTreeSet<String> = attestation_bundle.log_entries.iter()
+        .map(|e| e.log_id.clone())
+        .collect();
+    trail.log_ids = log_ids.into_iter().collect();
 
     // Step 2: Verify subject match (Section 6.4)
-    verify_subject_match(bundle, &attestation_bundle)?;
+    match &input.image_digest {
+        Some(digest) => trail.step("subject_match", verify_subject_match(digest, &attestation_bundle))?,
+        None => {
+            trail.steps.push(StepResult {
+                step: "subject_match".to_string(),
+                status: StepStatus::Skipped,
+                detail: Some("no image digest given".to_string()),
+            });
+            trail.warnings.push("No image digest given; attestation subjects were not checked".to_string());
+        }
+    }
 
     // Step 3: Verify signatures (Section 6.5)
-    verify_signatures(&attestation_bundle, &trust_store)?;
+    let dependencies = revocation_dependencies(&attestation_bundle);
+    if let Ok((keyids, _)) = &dependencies {
+        trail.keyids = keyids.clone();
+    }
+    trail.attestations_verified = trail.step("signatures", verify_signatures(&attestation_bundle, trust_store))?;
+
+    // Step 3a: Check signing keys and attestations against revocations
+    // (transparency-log.adoc Section 9.2)
+    let revocation_check = async {
+        let (keyids, attestation_digests) = dependencies?;
+        let depends_on: Vec<String> = keyids.iter().chain(&attestation_digests).cloned().collect();
+        if config.logs.online {
+            query_revocations(revocations, &depends_on, trust_store, &config.logs).await?;
+        }
+        revocations.check(depends_on.iter().map(String::as_str))?;
+        Ok((keyids, attestation_digests))
+    };
+    let revocation_check = revocation_check.instrument(info_span!("verify_revocations"));
+    let (keyids, attestation_digests) = trail.step("revocation", revocation_check.await)?;
 
     // Step 4: Verify log inclusion (Section 6.6)
-    verify_log_inclusion(&attestation_bundle, &trust_store).await?;
+    let log_inclusion = async {
+        let store = CheckpointStore::from_env();
+        let mut tree_heads = load_tree_heads(input.tree_heads.as_deref(), trust_store, &store, &config.logs).await?;
+        if config.logs.online {
+            refresh_from_logs(&mut attestation_bundle, trust_store, &store, &mut tree_heads, &config.logs).await?;
+        }
+        gossip::exchange(&config.gossip, &tree_heads, |log_id| trust_store.log_key(log_id)).await?;
+        verify_log_inclusion(&attestation_bundle, trust_store, &tree_heads, &config.logs).await
+    };
+    let coverage = trail.step("log_inclusion", log_inclusion.await)?;
+    trail.log_entries_verified = coverage.entries_verified;
+    trail.warnings.extend(coverage.warnings);
 
     // Step 5: Verify threshold (Section 6.7)
-    verify_threshold(&attestation_bundle, &trust_store)?;
+    let group = trail.step("threshold", verify_threshold(&attestation_bundle, trust_store))?;
+    trail.thresholds_met.push(group);
 
     info!("Verification completed successfully");
 
-    // Cache result (1 hour TTL per spec)
-    cache_result(bundle, &trust_store, true).await?;
-
-    // Record result (Section 6.8)
-    record_verification_result(bundle, "ALLOW").await?;
-
-    Ok(())
+    let log_ids: BTreeSet<String> = attestation_bundle.log_entries.iter()
+        .map(|e| e.log_id.clone())
+        .filter(|log_id| trust_store.get_log(log_id).is_some())
+        .collect();
+    Ok(Verified {
+        keyids,
+        log_ids: log_ids.into_iter().collect(),
+        attestation_digests,
+    })
 }
 
-fn parse_attestation_bundle(bundle: &CtpBundle) -> Result<AttestationBundle> {
-    let bundle_path = bundle.attestation_bundle_path();
-
+#[instrument(skip_all, err(level = "debug"))]
+fn parse_attestation_bundle(bundle_path: &std::path::Path) -> Result<AttestationBundle> {
     if !bundle_path.exists() {
         bail!("Attestation bundle not found: {:?} (MISSING_ATTESTATION)", bundle_path);
     }
 
-    let content = fs::read_to_string(&bundle_path)
+    let content = fs::read_to_string(bundle_path)
         .context("Failed to read attestation bundle")?;
 
     let attestation_bundle: AttestationBundle = serde_json::from_str(&content)
@@ -199,15 +514,14 @@ fn parse_attestation_bundle(bundle: &CtpBundle) -> Result<AttestationBundle> {
     Ok(attestation_bundle)
 }
 
-fn verify_subject_match(bundle: &CtpBundle, attestation: &AttestationBundle) -> Result<()> {
+#[instrument(skip_all, err(level = "debug"), fields(digest = %expected_digest))]
+fn verify_subject_match(expected_digest: &str, attestation: &AttestationBundle) -> Result<()> {
     info!("Verifying subject match");
 
-    let expected_digest = &bundle.manifest.image_digest;
-
     for att in &attestation.attestations {
         for subject in &att.subject {
             let subject_digest = format!("sha256:{}", subject.digest.sha256);
-            if subject_digest != *expected_digest {
+            if subject_digest != expected_digest {
                 bail!(
                     "Subject mismatch: expected {}, found {} (SUBJECT_MISMATCH)",
                     expected_digest,
@@ -220,16 +534,19 @@ fn verify_subject_match(bundle: &CtpBundle, attestation: &AttestationBundle) ->
     Ok(())
 }
 
-fn verify_signatures(attestation: &AttestationBundle, trust_store: &TrustStore) -> Result<()> {
+/// Returns the number of attestations verified
+#[instrument(skip_all, err(level = "debug"))]
+fn verify_signatures(attestation: &AttestationBundle, trust_store: &TrustStore) -> Result<usize> {
     info!("Verifying signatures");
 
     for att in &attestation.attestations {
         // Extract DSSE envelope signature
         let dsse_envelope = att.envelope.as_ref()
-            .context("Missing DSSE envelope in attestation")?;
+            .context("Missing DSSE envelope in attestation (MALFORMED_BUNDLE)")?;
 
         for signature in &dsse_envelope.signatures {
             let keyid = &signature.keyid;
+            let _span = info_span!("verify_signature", keyid = %keyid).entered();
 
             // Look up key in trust store (Section 6.5 step 2)
             let public_key = trust_store.get_key(keyid)
@@ -248,9 +565,9 @@ fn verify_signatures(attestation: &AttestationBundle, trust_store: &TrustStore)
                 }
             }
 
-            // Verify Ed25519 signature (Section 6.5 step 6)
+            // Verify Ed25519 signature over the DSSE PAE (Section 6.5 step 6)
             verify_ed25519_signature(
-                &dsse_envelope.payload,
+                &dsse_envelope.signed_bytes(),
                 &signature.sig,
                 &public_key.key_bytes
             ).context("INVALID_SIGNATURE: Ed25519 verification failed")?;
@@ -259,10 +576,10 @@ fn verify_signatures(attestation: &AttestationBundle, trust_store: &TrustStore)
         }
     }
 
-    Ok(())
+    Ok(attestation.attestations.len())
 }
 
-fn verify_ed25519_signature(payload: &[u8], signature: &[u8], public_key_bytes: &[u8]) -> Result<()> {
+pub fn verify_ed25519_signature(payload: &[u8], signature: &[u8], public_key_bytes: &[u8]) -> Result<()> {
     use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 
     let public_key = VerifyingKey::from_bytes(
@@ -281,200 +598,483 @@ fn verify_ed25519_signature(payload: &[u8], signature: &[u8], public_key_bytes:
     Ok(())
 }
 
-async fn verify_log_inclusion(attestation: &AttestationBundle, trust_store: &TrustStore) -> Result<()> {
-    info!("Verifying log inclusion");
-
-    // Check for at least 2 distinct log entries (federated requirement)
-    let unique_logs: std::collections::HashSet<_> = attestation
-        .log_entries
-        .iter()
-        .map(|e| &e.log_id)
-        .collect();
+/// Collect and verify Signed Tree Heads embedded in `signatures/logs/*.proof`
+/// and held in the local checkpoint store (transparency-log.adoc Section 5.3).
+///
+/// Bundle tree heads are checked against the stored checkpoints so that a
+/// log presenting an inconsistent view fails with LOG_EQUIVOCATION.
+async fn load_tree_heads(
+    dir: Option<&std::path::Path>,
+    trust_store: &TrustStore,
+    store: &CheckpointStore,
+    config: &LogsConfig,
+) -> Result<VerifiedTreeHeads> {
+    let log_key = |log_id: &str| trust_store.log_key(log_id);
+
+    // A checkpoint that cannot be read or verified is skipped, here and when
+    // observing, so a broken store never blocks verification
+    let stored = store.load_all().unwrap_or_else(|e| {
+        warn!("Failed to read checkpoints: {:#}", e);
+        vec![]
+    });
+    let mut tree_heads = VerifiedTreeHeads::default();
+    for (log_id, head) in stored {
+        match VerifiedTreeHeads::verify(vec![(log_id.clone(), head)], log_key) {
+            Ok(verified) => tree_heads.extend(verified),
+            Err(e) => warn!("Skipping checkpoint for log {}: {:#}", log_id, e),
+        }
+    }
+    let Some(dir) = dir else {
+        return Ok(tree_heads);
+    };
+    let bundle_heads = VerifiedTreeHeads::verify(sth::load_dir(dir)?, log_key)?;
 
-    if unique_logs.len() < 2 {
-        bail!(
-            "Insufficient log coverage: {} logs, need 2+ (INSUFFICIENT_LOG_COVERAGE)",
-            unique_logs.len()
-        );
+    for (log_id, head) in bundle_heads.iter() {
+        observe_checkpoint(store, log_id, head, trust_store, config).await?;
     }
 
-    // Verify each log entry (Section 6.6 step 3)
-    for log_entry in &attestation.log_entries {
-        // Look up log public key in trust store (step 3a)
-        let log_key = trust_store.get_key(&log_entry.log_id)
-            .context(format!("Log {} not in trust store", log_entry.log_id))?;
+    tree_heads.extend(bundle_heads);
+    Ok(tree_heads)
+}
 
-        // Verify signedEntryTimestamp signature (step 3b)
-        // RFC 6962 Section 3.2: Signed Certificate Timestamp
-        verify_set_signature(&log_entry.signed_entry_timestamp, log_key, attestation)
-            .context(format!("SET_INVALID: Signed Entry Timestamp verification failed for log {}", log_entry.log_id))?;
+/// Check a verified tree head against the stored checkpoint for its log.
+///
+/// Only a consistency proof, carried by the larger tree head or, online,
+/// fetched from the log, advances the checkpoint. Offline a bundle cannot
+/// carry a proof from a checkpoint size only this host knows, so an
+/// unproven tree head still counts as evidence of inclusion but leaves the
+/// checkpoint where it is.
+async fn observe_checkpoint(
+    store: &CheckpointStore,
+    log_id: &str,
+    head: &SignedTreeHead,
+    trust_store: &TrustStore,
+    config: &LogsConfig,
+) -> Result<()> {
+    // A shim running in parallel may advance the checkpoint between reading
+    // it and observing, leaving the proof for the wrong size; retry once
+    for _ in 0..2 {
+        let stored = match store.get(log_id) {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("Failed to read checkpoint for log {}: {:#}", log_id, e);
+                return Ok(());
+            }
+        };
+        let proof = match &stored {
+            Some(stored) => consistency_proof_between(log_id, head, stored, trust_store, config).await?
+                .map(|proof| (stored.tree_size, proof)),
+            None => None,
+        };
+
+        match store.observe(log_id, head, proof.as_ref().map(|(size, proof)| (*size, proof.as_slice()))) {
+            Ok(Observation::Unproven) if proof.is_some() => continue,
+            Ok(Observation::Unproven) => {
+                warn!("No consistency proof between tree size {} and the checkpoint for log {}; not advancing it",
+                    head.tree_size, log_id);
+                return Ok(());
+            }
+            Ok(_) => return Ok(()),
+            Err(e) if e.downcast_ref::<Equivocation>().is_some() => return Err(e),
+            Err(e) => {
+                // An unwritable store (e.g. rootless) must not block verification
+                warn!("Failed to update checkpoint for log {}: {:#}", log_id, e);
+                return Ok(());
+            }
+        }
+    }
 
-        info!("Verified SET signature for log: {}", log_entry.log_id);
+    bail!(
+        "LOG_PROOF_INVALID: no consistency proof between tree size {} and the checkpoint for log {}",
+        head.tree_size, log_id
+    )
+}
 
-        // Verify Merkle inclusion proof (step 3c)
-        if let Some(proof) = &log_entry.inclusion_proof {
-            verify_merkle_proof(proof)
-                .context(format!("LOG_PROOF_INVALID: Merkle proof failed for log {}", log_entry.log_id))?;
-        } else {
-            warn!("No inclusion proof for log {}, skipping Merkle verification", log_entry.log_id);
-        }
+/// Fetch each log's current tree head and a fresh inclusion proof against it
+/// from the log's trust store `url` (transparency-log.adoc Section 7).
+///
+/// The fresh proof replaces the one shipped in the bundle. An unreachable log
+/// is a network error, so strict mode blocks instead of trusting stale proofs.
+#[instrument(skip_all, err(level = "debug"))]
+async fn refresh_from_logs(
+    attestation: &mut AttestationBundle,
+    trust_store: &TrustStore,
+    store: &CheckpointStore,
+    tree_heads: &mut VerifiedTreeHeads,
+    config: &LogsConfig,
+) -> Result<()> {
+    let timeout = std::time::Duration::from_secs(config.timeout_secs);
+
+    for log_entry in &mut attestation.log_entries {
+        let Some(url) = trust_store.get_log(&log_entry.log_id).and_then(|log| log.url.as_deref()) else {
+            warn!("No url for log {} in the trust store, using the bundled proof", log_entry.log_id);
+            continue;
+        };
+        let Some(log_index) = log_entry.log_index else {
+            continue;
+        };
+
+        info!("Refreshing tree head and proof for entry {} from log {} at {}", log_index, log_entry.log_id, url);
+        let client = LogClient::new(url, timeout, config.retries)?;
+
+        let from_size = store.get(&log_entry.log_id)?.map(|stored| stored.tree_size);
+        let sth = client.get_sth(from_size).await
+            .context(format!("Failed to fetch tree head from log {}", log_entry.log_id))?;
+        let fresh = VerifiedTreeHeads::verify(
+            vec![(log_entry.log_id.clone(), sth)],
+            |log_id| trust_store.log_key(log_id),
+        )?;
+        let Some((_, head)) = fresh.iter().next() else {
+            continue;
+        };
+
+        require_fresh_tree_head(&log_entry.log_id, head, config.max_sth_age_secs)?;
+        observe_checkpoint(store, &log_entry.log_id, head, trust_store, config).await?;
+
+        let proof = client.get_proof(log_index, head.tree_size).await
+            .context(format!("Failed to fetch inclusion proof for entry {} from log {}", log_index, log_entry.log_id))?;
+        log_entry.inclusion_proof = Some(MerkleProof {
+            log_index: proof.log_index,
+            root_hash: proof.root_hash,
+            tree_size: proof.tree_size,
+            hashes: proof.hashes,
+        });
+        tree_heads.extend(fresh);
     }
 
     Ok(())
 }
 
-fn verify_merkle_proof(proof: &MerkleProof) -> Result<()> {
-    use sha2::{Sha256, Digest};
+fn require_fresh_tree_head(log_id: &str, head: &SignedTreeHead, max_age_secs: u64) -> Result<()> {
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&head.timestamp)?;
+    let age = chrono::Utc::now().signed_duration_since(timestamp);
+    if age > chrono::Duration::seconds(max_age_secs as i64) {
+        bail!(
+            "LOG_STALE: latest tree head from log {} is {}s old (limit {}s)",
+            log_id, age.num_seconds(), max_age_secs
+        );
+    }
+    Ok(())
+}
 
-    info!("Verifying Merkle inclusion proof (log_index: {}, tree_size: {})",
-        proof.log_index, proof.tree_size);
+/// Consistency proof between two tree heads, carried by the larger one or,
+/// in online mode, fetched from the log's trust store `url`
+async fn consistency_proof_between(
+    log_id: &str,
+    a: &SignedTreeHead,
+    b: &SignedTreeHead,
+    trust_store: &TrustStore,
+    config: &LogsConfig,
+) -> Result<Option<Vec<merkle::Hash>>> {
+    let (smaller, larger) = if a.tree_size <= b.tree_size { (a, b) } else { (b, a) };
+    if let Some(proof) = larger.consistency_from(smaller.tree_size) {
+        return proof.decoded_hashes().map(Some);
+    }
+    if smaller.tree_size == larger.tree_size || !config.online {
+        return Ok(None);
+    }
+    let Some(url) = trust_store.get_log(log_id).and_then(|log| log.url.as_deref()) else {
+        return Ok(None);
+    };
+
+    info!("Fetching consistency proof from {} to {} from log {}", smaller.tree_size, larger.tree_size, log_id);
+    let client = LogClient::new(url, std::time::Duration::from_secs(config.timeout_secs), config.retries)?;
+    let proof = client.get_consistency(smaller.tree_size, larger.tree_size).await
+        .context(format!("Failed to fetch consistency proof from log {}", log_id))?;
+    if (proof.from_size, proof.to_size) != (smaller.tree_size, larger.tree_size) {
+        bail!("LOG_PROOF_INVALID: log {} returned a consistency proof for the wrong tree sizes", log_id);
+    }
+    proof.hashes.iter().map(|h| merkle::decode_hash(h)).collect::<Result<Vec<_>>>().map(Some)
+}
+
+/// Log entries whose timestamps and proofs were verified, and why others
+/// were not
+#[derive(Debug, Default)]
+struct LogCoverage {
+    entries_verified: usize,
+    warnings: Vec<String>,
+}
 
-    // RFC 6962 Section 2.1.1: Merkle Audit Paths
-    // Verify that log_index < tree_size
-    if proof.log_index >= proof.tree_size {
-        bail!("Invalid proof: log_index ({}) >= tree_size ({})",
-            proof.log_index, proof.tree_size);
+impl LogCoverage {
+    fn warn(&mut self, message: String) {
+        warn!("{}", message);
+        self.warnings.push(message);
     }
+}
 
-    // Decode root hash from hex
-    let expected_root = decode_hex(&proof.root_hash)
-        .context("Failed to decode root hash")?;
+#[instrument(skip_all, err(level = "debug"))]
+async fn verify_log_inclusion(
+    attestation: &AttestationBundle,
+    trust_store: &TrustStore,
+    tree_heads: &VerifiedTreeHeads,
+    config: &LogsConfig,
+) -> Result<LogCoverage> {
+    info!("Verifying log inclusion");
 
-    // Decode audit path hashes from hex
-    let audit_path: Result<Vec<Vec<u8>>> = proof.hashes
+    let mut coverage = LogCoverage::default();
+    config.validate()?;
+    if config.quorum < 2 {
+        coverage.warn(format!("Log quorum of {} is below the 2 operators required by transparency-log.adoc Section 8.2",
+            config.quorum));
+    }
+
+    // Only logs in the trust store count, and each operator counts once
+    // however many logs it runs (transparency-log.adoc Sections 8.2, 8.3)
+    let operators: BTreeSet<&str> = attestation.log_entries
         .iter()
-        .map(|h| decode_hex(h).context(format!("Failed to decode audit path hash: {}", h)))
+        .filter_map(|e| trust_store.get_log(&e.log_id))
+        .map(|log| log.operator.as_str())
         .collect();
-    let audit_path = audit_path?;
 
-    // RFC 6962 Section 2.1.1: Reconstruct root hash from leaf and audit path
-    // Start with the leaf hash (first hash in the proof is the leaf)
-    if audit_path.is_empty() {
-        bail!("Empty audit path in Merkle proof");
+    if operators.len() < config.quorum {
+        bail!(
+            "Insufficient log coverage: {} trusted log operators, need {} (INSUFFICIENT_LOG_COVERAGE)",
+            operators.len(), config.quorum
+        );
     }
 
-    let mut current_hash = audit_path[0].clone();
-    let mut index = proof.log_index;
-    let mut tree_size = proof.tree_size;
+    // Reconstruct the log entry leaf for each attestation (transparency-log.adoc Section 5.2)
+    let leaves = attestation_leaf_hashes(attestation)?;
+    let mut covered: Vec<BTreeSet<&str>> = vec![BTreeSet::new(); leaves.len()];
 
-    // Process each hash in the audit path
-    for sibling_hash in audit_path.iter().skip(1) {
-        // RFC 6962 Section 2.1: Merkle Hash Tree
-        // MTH({d(0)}) = SHA-256(0x00 || d(0))  -- leaf
-        // MTH(D[n]) = SHA-256(0x01 || MTH(D[0:k]) || MTH(D[k:n]))  -- node
+    // Verify each log entry (Section 6.6 step 3)
+    for log_entry in &attestation.log_entries {
+        let _span = info_span!("verify_log_entry", log_id = %log_entry.log_id).entered();
 
-        // Determine if sibling is on the left or right
-        // If index is even, sibling is on the right
-        // If index is odd, sibling is on the left
-        let is_right_node = index % 2 == 0;
+        // Look up log public key in trust store (step 3a)
+        let Some(log) = trust_store.get_log(&log_entry.log_id) else {
+            coverage.warn(format!("Ignoring entry from log {} which is not in the trust store", log_entry.log_id));
+            continue;
+        };
 
-        let mut hasher = Sha256::new();
-        hasher.update(&[0x01]); // RFC 6962: 0x01 prefix for internal nodes
+        // Verify signedEntryTimestamp and find the attestation it covers (step 3b)
+        let index = verify_entry_timestamp(log_entry, log.key()?, &leaves, config.clock_skew_secs)
+            .context(format!("SET_INVALID: Signed Entry Timestamp verification failed for log {}", log_entry.log_id))?;
 
-        if is_right_node {
-            // Current is left, sibling is right
-            hasher.update(&current_hash);
-            hasher.update(sibling_hash);
+        info!("Verified SET from log {} for attestation {}", log_entry.log_id, index);
+
+        // Verify Merkle inclusion proof for that same entry (step 3c)
+        if let Some(proof) = &log_entry.inclusion_proof {
+            if Some(proof.log_index) != log_entry.log_index {
+                bail!("LOG_PROOF_INVALID: inclusion proof index {} for log {} does not match the entry's logIndex",
+                    proof.log_index, log_entry.log_id);
+            }
+            verify_merkle_proof(proof, std::slice::from_ref(&leaves[index]))
+                .context(format!("LOG_PROOF_INVALID: Merkle proof failed for log {}", log_entry.log_id))?;
+
+            // The proof's root is only meaningful if the log signed it (step 3c)
+            require_signed_root(tree_heads, &log_entry.log_id, proof)?;
+            covered[index].insert(&log.operator);
+            coverage.entries_verified += 1;
+        } else if let (Some(tiles), Some(log_index)) = (&log.tiles, log_entry.log_index) {
+            // The checkpoint's note signature stands in for a signed tree head
+            let proof = tile_inclusion_proof(tiles, log_index, config).await
+                .context(format!("LOG_PROOF_INVALID: no inclusion proof from the tiles of log {}", log_entry.log_id))?;
+            verify_merkle_proof(&proof, std::slice::from_ref(&leaves[index]))
+                .context(format!("LOG_PROOF_INVALID: Merkle proof from tiles failed for log {}", log_entry.log_id))?;
+            covered[index].insert(&log.operator);
+            coverage.entries_verified += 1;
         } else {
-            // Sibling is left, current is right
-            hasher.update(sibling_hash);
-            hasher.update(&current_hash);
+            coverage.warn(format!("No inclusion proof for log {}, skipping Merkle verification", log_entry.log_id));
         }
+    }
 
-        current_hash = hasher.finalize().to_vec();
+    require_log_quorum(&covered, config.quorum)?;
+    Ok(coverage)
+}
 
-        // Move up the tree
-        index /= 2;
-        tree_size = (tree_size + 1) / 2;
+/// Inclusion proof for entry `log_index` computed from the log's tiles at
+/// its current checkpoint. Mirrors are only read in online mode.
+async fn tile_inclusion_proof(tiles: &TrustedTiles, log_index: u64, config: &LogsConfig) -> Result<MerkleProof> {
+    let source = TileSource::parse(&tiles.location, std::time::Duration::from_secs(config.timeout_secs))?;
+    if matches!(source, TileSource::Http { .. }) && !config.online {
+        bail!("tile mirror {} is not read in offline mode", tiles.location);
     }
+    let verifier = NoteVerifier::parse(&tiles.note_key)?;
+    let origin = tiles.origin.as_deref().unwrap_or(&verifier.name);
+
+    let (checkpoint, mut reader) = TileReader::open(source, &verifier, origin).await?;
+    let hashes = reader.inclusion_proof(log_index).await?;
+    Ok(MerkleProof {
+        log_index,
+        root_hash: hex::encode(checkpoint.root_hash),
+        tree_size: checkpoint.tree_size,
+        hashes: hashes.iter().map(hex::encode).collect(),
+    })
+}
 
-    // Verify computed root matches expected root
-    if current_hash != expected_root {
-        bail!("Merkle proof verification failed: computed root {} != expected {}",
-            hex::encode(&current_hash), hex::encode(&expected_root));
+/// Require a verified STH from `log_id` for the proof's root and tree size
+fn require_signed_root(tree_heads: &VerifiedTreeHeads, log_id: &str, proof: &MerkleProof) -> Result<()> {
+    let root = merkle::decode_hash(&proof.root_hash)?;
+    if tree_heads.find(log_id, &root, proof.tree_size).is_none() {
+        bail!(
+            "LOG_PROOF_INVALID: root {} at tree size {} for log {} does not match a verified signed tree head",
+            proof.root_hash, proof.tree_size, log_id
+        );
     }
-
-    info!("Merkle proof verified successfully");
     Ok(())
 }
 
-/// Decode a hexadecimal string to bytes
-fn decode_hex(s: &str) -> Result<Vec<u8>> {
-    hex::decode(s).context("Invalid hex string")
+/// Merkle leaf hash of the log entry for each attestation, in bundle order
+fn attestation_leaf_hashes(attestation: &AttestationBundle) -> Result<Vec<merkle::Hash>> {
+    attestation.attestations
+        .iter()
+        .enumerate()
+        .map(|(i, att)| {
+            let envelope = att.envelope.as_ref()
+                .context(format!("Missing DSSE envelope in attestation {} (MALFORMED_BUNDLE)", i))?;
+            let subject = att.subject.first()
+                .context(format!("Attestation {} has no subject (MALFORMED_BUNDLE)", i))?;
+            let entry = tlog::Entry::attestation(
+                envelope,
+                &format!("sha256:{}", subject.digest.sha256),
+                &att.predicate_type,
+            )?;
+            entry.leaf_hash()
+        })
+        .collect()
 }
 
-/// Verify Signed Entry Timestamp (SET) signature
-/// RFC 6962 Section 3.2: Signed Certificate Timestamp
-fn verify_set_signature(
-    set_b64: &str,
-    log_key: &TrustedKey,
-    _attestation: &AttestationBundle,
-) -> Result<()> {
-    use base64::{Engine as _, engine::general_purpose};
+/// Require verified inclusion proofs from `quorum` distinct operators for every attestation
+fn require_log_quorum(covered: &[BTreeSet<&str>], quorum: usize) -> Result<()> {
+    for (i, operators) in covered.iter().enumerate() {
+        if operators.len() < quorum {
+            bail!(
+                "Attestation {} has verified inclusion proofs from {} log operators, need {} (INSUFFICIENT_LOG_COVERAGE)",
+                i, operators.len(), quorum
+            );
+        }
+    }
+    Ok(())
+}
 
-    // Decode base64-encoded SET
-    let set_bytes = general_purpose::STANDARD
-        .decode(set_b64)
-        .context("Failed to decode signedEntryTimestamp from base64")?;
+/// Verify a Merkle inclusion proof against the reconstructed attestation leaves,
+/// returning the index of the attestation it proves.
+///
+/// The leaf is always derived from an entry we reconstructed ourselves
+/// (`SHA256(0x00 || entry)`), never taken from the proof.
+fn verify_merkle_proof(proof: &MerkleProof, leaves: &[merkle::Hash]) -> Result<usize> {
+    info!("Verifying Merkle inclusion proof (log_index: {}, tree_size: {})",
+        proof.log_index, proof.tree_size);
 
-    // RFC 6962 SET format (simplified):
-    // - Version (1 byte)
-    // - Signature type (1 byte)
-    // - Timestamp (8 bytes, milliseconds since epoch)
-    // - Entry data (variable)
-    // - Signature (variable, depends on algorithm)
+    let expected_root = merkle::decode_hash(&proof.root_hash)
+        .context("Failed to decode root hash")?;
 
-    if set_bytes.len() < 74 {
-        bail!("SET too short: {} bytes (expected >= 74)", set_bytes.len());
+    let audit_path = proof.hashes
+        .iter()
+        .map(|h| merkle::decode_hash(h).context(format!("Failed to decode audit path hash: {}", h)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // RFC 9162 Section 2.1.3.2: the path length is fixed by index and tree size,
+    // so a root computed for one entry cannot be replayed for another
+    let mut last_error = None;
+    for (i, leaf) in leaves.iter().enumerate() {
+        match merkle::verify_inclusion(leaf, proof.log_index, proof.tree_size, &audit_path, &expected_root) {
+            Ok(()) => {
+                info!("Merkle proof verified successfully for attestation {}", i);
+                return Ok(i);
+            }
+            Err(e) => last_error = Some(e),
+        }
     }
 
-    // Extract signature (last 64 bytes for Ed25519)
-    let signature_start = set_bytes.len() - 64;
-    let signed_data = &set_bytes[..signature_start];
-    let signature_bytes = &set_bytes[signature_start..];
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No attestation entries to verify proof against")))
+}
 
-    // Verify Ed25519 signature using log's public key
-    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+/// Verify a log entry's Signed Entry Timestamp (transparency-log.adoc Section 5.4),
+/// returning the index of the attestation whose entry it covers.
+///
+/// The SET must be the log's signature over this log ID, the entry's
+/// `logIndex` and `integratedTime`, and the leaf hash of an entry rebuilt
+/// from the bundle, so a SET issued for any other entry does not verify.
+fn verify_entry_timestamp(
+    log_entry: &LogEntry,
+    log_public_key: &[u8],
+    leaves: &[merkle::Hash],
+    clock_skew_secs: u64,
+) -> Result<usize> {
+    use base64::{Engine as _, engine::general_purpose};
 
-    let public_key = VerifyingKey::from_bytes(
-        log_key.key_bytes
-            .as_slice()
-            .try_into()
-            .context("Invalid log public key length (expected 32 bytes)")?
-    )?;
+    let log_index = log_entry.log_index
+        .context("Log entry has no logIndex (MALFORMED_BUNDLE)")?;
+    let integrated_time = log_entry.integrated_time.as_deref()
+        .context("Log entry has no integratedTime (MALFORMED_BUNDLE)")?;
 
-    let signature = Signature::from_bytes(
-        signature_bytes
-            .try_into()
-            .context("Invalid SET signature length (expected 64 bytes)")?
-    );
+    let signature = general_purpose::STANDARD
+        .decode(&log_entry.signed_entry_timestamp)
+        .context("Failed to decode signedEntryTimestamp from base64")?;
+
+    let integrated = chrono::DateTime::parse_from_rfc3339(integrated_time)
+        .context(format!("Invalid integratedTime: {}", integrated_time))?;
+    let latest = chrono::Utc::now() + chrono::Duration::seconds(clock_skew_secs as i64);
+    if integrated > latest {
+        bail!("integratedTime {} is more than {}s in the future", integrated_time, clock_skew_secs);
+    }
 
-    public_key.verify(signed_data, &signature)
-        .context("SET signature verification failed: invalid signature from transparency log")?;
-
-    // Additional validation: check timestamp is recent (within 1 week)
-    if set_bytes.len() >= 10 {
-        let timestamp_bytes: [u8; 8] = set_bytes[2..10]
-            .try_into()
-            .context("Failed to extract timestamp from SET")?;
-        let timestamp_ms = u64::from_be_bytes(timestamp_bytes);
-        let timestamp_secs = timestamp_ms / 1000;
-
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs();
-
-        // Allow 1 week grace period (604800 seconds)
-        // NOTE: In production, this should be configurable
-        if timestamp_secs > now + 604800 {
-            bail!("SET timestamp is in the future: {}", timestamp_secs);
+    let mut last_error = None;
+    for (i, leaf) in leaves.iter().enumerate() {
+        let timestamp = tlog::EntryTimestamp {
+            log_id: log_entry.log_id.clone(),
+            log_index,
+            integrated_time: integrated_time.to_string(),
+            entry_hash: *leaf,
+        };
+        match timestamp.verify(log_public_key, &signature) {
+            Ok(()) => return Ok(i),
+            Err(e) => last_error = Some(e),
         }
     }
 
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No attestation entries to verify SET against")))
+}
+
+/// Load the configured revocation snapshot, signed by a trust store key
+fn load_revocations(config: &RevocationConfig, trust_store: &TrustStore) -> Result<RevocationSet> {
+    match &config.snapshot {
+        Some(path) => RevocationSet::load_snapshot(path, config, |keyid| {
+            trust_store.get_key(keyid).map(|k| k.key_bytes.as_slice())
+        }),
+        None => Ok(RevocationSet::default()),
+    }
+}
+
+/// Everything whose revocation invalidates this verification: the key IDs of
+/// all signatures and the digest of every attestation envelope
+fn revocation_dependencies(attestation: &AttestationBundle) -> Result<(Vec<String>, Vec<String>)> {
+    let mut keyids = BTreeSet::new();
+    let mut attestation_digests = BTreeSet::new();
+    for att in &attestation.attestations {
+        let envelope = att.envelope.as_ref()
+            .context("Missing DSSE envelope in attestation (MALFORMED_BUNDLE)")?;
+        attestation_digests.insert(tlog::attestation_digest(envelope)?);
+        keyids.extend(envelope.signatures.iter().map(|s| s.keyid.clone()));
+    }
+    Ok((keyids.into_iter().collect(), attestation_digests.into_iter().collect()))
+}
+
+/// Ask every trusted log with a known URL for revocations of `digests`.
+/// Only revocations signed by the trust store key that issued them, and
+/// proven to be in the log, are accepted.
+async fn query_revocations(
+    revocations: &mut RevocationSet,
+    digests: &[String],
+    trust_store: &TrustStore,
+    config: &LogsConfig,
+) -> Result<()> {
+    let timeout = std::time::Duration::from_secs(config.timeout_secs);
+    for (log_id, log) in &trust_store.logs {
+        let Some(url) = &log.url else { continue };
+        let client = LogClient::new(url, timeout, config.retries)?;
+        let trusted_key = |keyid: &str| trust_store.get_key(keyid).map(|k| k.key_bytes.as_slice());
+        revocations.query_log(&client, log_id, log.key()?, digests, trusted_key).await
+            .context(format!("Failed to query revocations from log {}", log_id))?;
+    }
     Ok(())
 }
 
-fn verify_threshold(attestation: &AttestationBundle, trust_store: &TrustStore) -> Result<()> {
+/// Returns the name of the threshold group that was met
+#[instrument(skip_all, err(level = "debug"), fields(group = "release-signers", signatures = tracing::field::Empty))]
+fn verify_threshold(attestation: &AttestationBundle, trust_store: &TrustStore) -> Result<String> {
     info!("Verifying threshold signature");
 
     // Try to find threshold group (Section 6.7 step 1)
@@ -500,6 +1100,7 @@ fn verify_threshold(attestation: &AttestationBundle, trust_store: &TrustStore) -
         }
     }
 
+    tracing::Span::current().record("signatures", valid_signature_count);
     info!("Found {} valid signatures from threshold group (need {} of {})",
         valid_signature_count, threshold_group.k, threshold_group.n);
 
@@ -513,102 +1114,427 @@ fn verify_threshold(attestation: &AttestationBundle, trust_store: &TrustStore) -
         );
     }
 
-    Ok(())
+    Ok(threshold_group.name.clone())
 }
 
-async fn check_cache(bundle: &CtpBundle, trust_store: &TrustStore) -> Result<Option<()>> {
-    // Cache key: image digest + trust store version
-    let cache_key = format!("{}-{}",
-        bundle.manifest.image_digest,
-        trust_store_version(trust_store)
-    );
-
-    let cache_dir = std::env::var("CACHE_DIR")
-        .unwrap_or_else(|_| "/var/cache/verified-container".to_string());
-    let cache_file = format!("{}/{}.cache", cache_dir, cache_key);
+/// Cache key component for a trust store: the hash of the full canonical
+/// document (canonicalization.adoc), so any change invalidates cached results
+fn trust_store_version(document: &serde_json::Value) -> String {
+    use sha2::{Sha256, Digest};
+    hex::encode(Sha256::digest(canonical::canonicalize(document).as_bytes()))
+}
 
-    if !std::path::Path::new(&cache_file).exists() {
-        return Ok(None);
+fn record_verification_result(
+    auditor: &Auditor,
+    bundle: &CtpBundle,
+    mode: VerificationMode,
+    outcome: Outcome,
+    trail: AuditTrail,
+    error: Option<&anyhow::Error>,
+) -> Result<()> {
+    // Log to audit file (per runtime-integration.adoc Section 8.2)
+    let mut record = AuditRecord::new(&bundle.manifest.name, &bundle.manifest.image_digest, mode, outcome, trail);
+    if let Some(error) = error {
+        record = record.with_error(error);
     }
+    metrics::record_decision(&record);
+    auditor.record(record)
+}
 
-    // Check if cache is still valid (1 hour TTL per spec Section 8)
-    let metadata = std::fs::metadata(&cache_file)?;
-    let modified = metadata.modified()?;
-    let age = std::time::SystemTime::now()
-        .duration_since(modified)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::reference::ReferenceTree;
+
+    fn test_bundle() -> AttestationBundle {
+        serde_json::from_value(serde_json::json!({
+            "mediaType": "application/vnd.verified-container.bundle+json",
+            "version": "0.1.0",
+            "attestations": [{
+                "subject": [{"digest": {"sha256": "ab".repeat(32)}}],
+                "predicateType": "https://slsa.dev/provenance/v1",
+                "envelope": {
+                    "payloadType": "application/vnd.in-toto+json",
+                    "payload": "e30=",
+                    "signatures": [{"keyid": "builder", "sig": "AAAA"}]
+                }
+            }],
+            "logEntries": []
+        })).unwrap()
+    }
 
-    if age > std::time::Duration::from_secs(3600) {
-        // Cache expired
-        std::fs::remove_file(&cache_file).ok();
-        return Ok(None);
+    fn proof_for(tree: &ReferenceTree, index: u64) -> MerkleProof {
+        MerkleProof {
+            log_index: index,
+            root_hash: hex::encode(tree.root()),
+            tree_size: tree.leaves.len() as u64,
+            hashes: tree.inclusion_proof(index).iter().map(hex::encode).collect(),
+        }
     }
 
-    info!("Cache hit for bundle {}", bundle.manifest.image_digest);
-    Ok(Some(()))
-}
+    #[test]
+    fn test_proof_binds_to_reconstructed_entry() {
+        let bundle = test_bundle();
+        let leaves = attestation_leaf_hashes(&bundle).unwrap();
 
-async fn cache_result(bundle: &CtpBundle, trust_store: &TrustStore, success: bool) -> Result<()> {
-    if !success {
-        return Ok(()); // Don't cache failures
+        let mut tree = ReferenceTree::new(6);
+        tree.leaves[3] = leaves[0];
+        assert_eq!(verify_merkle_proof(&proof_for(&tree, 3), &leaves).unwrap(), 0);
+
+        // A valid proof for some unrelated entry in the same log must not count
+        assert!(verify_merkle_proof(&proof_for(&tree, 2), &leaves).is_err());
     }
 
-    let cache_key = format!("{}-{}",
-        bundle.manifest.image_digest,
-        trust_store_version(trust_store)
-    );
+    #[test]
+    fn test_proof_root_must_match_verified_sth() {
+        use ed25519_dalek::SigningKey;
+
+        let log_key = SigningKey::from_bytes(&[5u8; 32]);
+        let public = log_key.verifying_key().to_bytes();
+        let tree = ReferenceTree::new(6);
+        let proof = proof_for(&tree, 2);
+
+        let heads = VerifiedTreeHeads::verify(
+            vec![("log-eu".to_string(), sth::testing::signed(&log_key, 6, &tree.root()))],
+            |_| Some(public.as_slice()),
+        ).unwrap();
+        assert!(require_signed_root(&heads, "log-eu", &proof).is_ok());
+        assert!(require_signed_root(&heads, "log-us", &proof).is_err());
+
+        // A self-consistent proof against a root the log never signed
+        let forged = ReferenceTree::new(7);
+        let err = require_signed_root(&heads, "log-eu", &proof_for(&forged, 2)).unwrap_err();
+        assert!(err.to_string().contains("LOG_PROOF_INVALID"));
+    }
 
-    let cache_dir = std::env::var("CACHE_DIR")
-        .unwrap_or_else(|_| "/var/cache/verified-container".to_string());
-    std::fs::create_dir_all(&cache_dir)?;
+    fn signed_log_entry(key: &ed25519_dalek::SigningKey, log_id: &str, leaf: merkle::Hash, integrated_time: &str) -> LogEntry {
+        use base64::{Engine as _, engine::general_purpose};
+        use ed25519_dalek::Signer;
+
+        let timestamp = tlog::EntryTimestamp {
+            log_id: log_id.to_string(),
+            log_index: 3,
+            integrated_time: integrated_time.to_string(),
+            entry_hash: leaf,
+        };
+        LogEntry {
+            log_id: log_id.to_string(),
+            log_index: Some(3),
+            integrated_time: Some(integrated_time.to_string()),
+            signed_entry_timestamp: general_purpose::STANDARD.encode(key.sign(&timestamp.signed_bytes()).to_bytes()),
+            inclusion_proof: None,
+        }
+    }
 
-    let cache_file = format!("{}/{}.cache", cache_dir, cache_key);
-    std::fs::write(&cache_file, "VERIFIED")?;
+    #[test]
+    fn test_set_binds_to_attestation_and_integrated_time() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let leaves = attestation_leaf_hashes(&test_bundle()).unwrap();
+
+        let entry = signed_log_entry(&key, "log-eu", leaves[0], "2026-01-01T00:00:00Z");
+        assert_eq!(verify_entry_timestamp(&entry, &public, &leaves, 300).unwrap(), 0);
+
+        // A valid SET for some other entry in the same log
+        let other = signed_log_entry(&key, "log-eu", [9u8; 32], "2026-01-01T00:00:00Z");
+        assert!(verify_entry_timestamp(&other, &public, &leaves, 300).is_err());
+
+        // integratedTime changed after signing
+        let mut retimed = entry.clone();
+        retimed.integrated_time = Some("2026-01-02T00:00:00Z".to_string());
+        assert!(verify_entry_timestamp(&retimed, &public, &leaves, 300).is_err());
+
+        // Signed, but beyond the allowed clock skew
+        let ahead = (chrono::Utc::now() + chrono::Duration::seconds(600)).to_rfc3339();
+        let future = signed_log_entry(&key, "log-eu", leaves[0], &ahead);
+        assert!(verify_entry_timestamp(&future, &public, &leaves, 300).is_err());
+        assert!(verify_entry_timestamp(&future, &public, &leaves, 900).is_ok());
+    }
 
-    info!("Cached verification result for bundle {}", bundle.manifest.image_digest);
-    Ok(())
-}
+    #[tokio::test]
+    async fn test_quorum_counts_trusted_operators_not_log_ids() {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let public = key.verifying_key().to_bytes();
+        let raw = general_purpose::STANDARD.encode(public);
+        let spki = general_purpose::STANDARD.encode([&ED25519_SPKI_PREFIX[..], &public].concat());
+
+        let trust_store: TrustStore = serde_json::from_value(serde_json::json!({
+            "keys": [],
+            "threshold_groups": [],
+            "logs": {
+                "log-eu-1": {"operator": "EU Operator", "publicKey": raw, "algorithm": "ed25519"},
+                "log-eu-2": {"operator": "EU Operator", "publicKey": spki, "algorithm": "ed25519"},
+                "log-us": {"operator": "US Operator", "publicKey": raw, "algorithm": "ed25519"}
+            }
+        })).unwrap();
+
+        let mut bundle = test_bundle();
+        let leaves = attestation_leaf_hashes(&bundle).unwrap();
+        let mut tree = ReferenceTree::new(6);
+        tree.leaves[3] = leaves[0];
+
+        let heads = VerifiedTreeHeads::verify(
+            ["log-eu-1", "log-eu-2", "log-us", "log-unknown"]
+                .map(|id| (id.to_string(), sth::testing::signed(&key, 6, &tree.root())))
+                .to_vec(),
+            |id| trust_store.log_key(id),
+        ).unwrap();
+        let entry = |log_id: &str| LogEntry {
+            inclusion_proof: Some(proof_for(&tree, 3)),
+            ..signed_log_entry(&key, log_id, leaves[0], "2026-01-01T00:00:00Z")
+        };
+        let config = LogsConfig::default();
+
+        // One operator under two log IDs, plus a log the trust store does not know
+        bundle.log_entries = vec![entry("log-eu-1"), entry("log-eu-2"), entry("log-unknown")];
+        let err = verify_log_inclusion(&bundle, &trust_store, &heads, &config).await.unwrap_err();
+        assert!(err.to_string().contains("INSUFFICIENT_LOG_COVERAGE"));
+
+        bundle.log_entries = vec![entry("log-eu-2"), entry("log-us")];
+        verify_log_inclusion(&bundle, &trust_store, &heads, &config).await.unwrap();
+
+        let single = LogsConfig { quorum: 1, allow_single_operator: true, ..LogsConfig::default() };
+        bundle.log_entries = vec![entry("log-eu-1")];
+        verify_log_inclusion(&bundle, &trust_store, &heads, &single).await.unwrap();
+
+        // An entry without an inclusion proof is not counted as verified
+        let unproven = LogEntry { inclusion_proof: None, ..entry("log-us") };
+        bundle.log_entries = vec![entry("log-eu-1"), unproven];
+        let coverage = verify_log_inclusion(&bundle, &trust_store, &heads, &single).await.unwrap();
+        assert_eq!(coverage.entries_verified, 1);
+
+        // A quorum of zero would skip the coverage check
+        let none = LogsConfig { quorum: 0, allow_single_operator: true, ..LogsConfig::default() };
+        bundle.log_entries = vec![];
+        assert!(verify_log_inclusion(&bundle, &trust_store, &heads, &none).await.is_err());
+    }
 
-fn trust_store_version(trust_store: &TrustStore) -> String {
-    // Simple version based on number of keys
-    // Production should use actual versioning
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    for key in &trust_store.keys {
-        hasher.update(&key.keyid);
+    #[tokio::test]
+    async fn test_inclusion_proved_from_local_tiles() {
+        use base64::{Engine as _, engine::general_purpose};
+        use crate::tiles::testing;
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let note_key = ed25519_dalek::SigningKey::from_bytes(&[6u8; 32]);
+        let mut bundle = test_bundle();
+        let leaves = attestation_leaf_hashes(&bundle).unwrap();
+        let mut tree = ReferenceTree::new(300);
+        tree.leaves[3] = leaves[0];
+
+        let dir = tempfile::tempdir().unwrap();
+        testing::write_tiles(dir.path(), &tree.leaves);
+        std::fs::write(dir.path().join("checkpoint"), testing::checkpoint_note(&note_key, "tiles.example/log", 300, &tree.root())).unwrap();
+        let vkey = testing::vkey(&note_key, "tiles.example/log");
+
+        let trust_store = |origin: Option<&str>| -> TrustStore {
+            serde_json::from_value(serde_json::json!({
+                "keys": [],
+                "threshold_groups": [],
+                "logs": {"log-tiled": {
+                    "operator": "Tiled Operator",
+                    "publicKey": general_purpose::STANDARD.encode(key.verifying_key().to_bytes()),
+                    "algorithm": "ed25519",
+                    "tiles": {"location": dir.path(), "noteKey": vkey, "origin": origin}
+                }}
+            })).unwrap()
+        };
+        bundle.log_entries = vec![signed_log_entry(&key, "log-tiled", leaves[0], "2026-01-01T00:00:00Z")];
+        let config = LogsConfig { quorum: 1, allow_single_operator: true, ..LogsConfig::default() };
+        let heads = VerifiedTreeHeads::default();
+
+        // No bundled proof and no tree head: the quorum is met from the tiles alone
+        verify_log_inclusion(&bundle, &trust_store(None), &heads, &config).await.unwrap();
+
+        // The checkpoint must be for this log, not another one under the same key
+        let err = verify_log_inclusion(&bundle, &trust_store(Some("tiles.example/other")), &heads, &config)
+            .await.unwrap_err();
+        assert!(format!("{:#}", err).contains("LOG_PROOF_INVALID"));
     }
-    format!("{:x}", hasher.finalize())[..8].to_string()
-}
 
-async fn record_verification_result(bundle: &CtpBundle, outcome: &str) -> Result<()> {
-    // Log to audit file (per runtime-integration.adoc Section 8.2)
-    let log_entry = format!(
-        "{{\"timestamp\":\"{}\",\"bundle\":\"{}\",\"digest\":\"{}\",\"outcome\":\"{}\"}}",
-        chrono::Utc::now().to_rfc3339(),
-        bundle.manifest.name,
-        bundle.manifest.image_digest,
-        outcome
-    );
+    #[tokio::test]
+    async fn test_online_refresh_fetches_proof_from_log() {
+        use crate::log_server::{self, SubmitRequest, SubmitResponse};
+        use crate::signing::Signer;
+        use ed25519_dalek::SigningKey;
+
+        let log_key = SigningKey::from_bytes(&[7u8; 32]);
+        let log_dir = tempfile::tempdir().unwrap();
+        let url = log_server::testing::spawn(log_dir.path(), "log-dev", &log_key).await;
+
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "image", "digest": {"sha256": "ab".repeat(32)}}],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {}
+        });
+        let envelope = crate::dsse::sign_statement(&statement, &Signer::File(SigningKey::from_bytes(&[1u8; 32])), "builder").unwrap();
+        let mut bundle = test_bundle();
+        bundle.attestations[0].envelope = Some(envelope.clone());
+
+        let response: SubmitResponse = reqwest::Client::new()
+            .post(format!("{}/api/v1/entries", url))
+            .json(&SubmitRequest::Attestation { attestation: serde_json::to_vec(&envelope).unwrap() })
+            .send().await.unwrap()
+            .json().await.unwrap();
+        let mut entry: LogEntry = serde_json::from_value(serde_json::to_value(&response).unwrap()).unwrap();
+        entry.inclusion_proof = None;
+        bundle.log_entries = vec![entry];
+
+        let trust_store = |url: &str| -> TrustStore {
+            use base64::{Engine as _, engine::general_purpose};
+            serde_json::from_value(serde_json::json!({
+                "keys": [],
+                "threshold_groups": [],
+                "logs": {"log-dev": {
+                    "operator": "Dev Operator",
+                    "publicKey": general_purpose::STANDARD.encode(log_key.verifying_key().to_bytes()),
+                    "url": url,
+                    "algorithm": "ed25519"
+                }}
+            })).unwrap()
+        };
+        let checkpoints = tempfile::tempdir().unwrap();
+        let store = CheckpointStore::open(checkpoints.path());
+        let config = LogsConfig { quorum: 1, allow_single_operator: true, online: true, retries: 0, ..LogsConfig::default() };
+
+        let mut heads = VerifiedTreeHeads::default();
+        refresh_from_logs(&mut bundle, &trust_store(&url), &store, &mut heads, &config).await.unwrap();
+        assert!(bundle.log_entries[0].inclusion_proof.is_some());
+        assert_eq!(store.get("log-dev").unwrap().unwrap().tree_size, 1);
+        verify_log_inclusion(&bundle, &trust_store(&url), &heads, &config).await.unwrap();
+
+        // An unreachable log is reported as a network error
+        let err = refresh_from_logs(&mut bundle, &trust_store("http://127.0.0.1:9"), &store, &mut heads, &config)
+            .await.unwrap_err();
+        assert!(format!("{:#}", err).contains("network error"));
+        assert!(err.downcast_ref::<NetworkError>().is_some());
+    }
 
-    // Ensure log directory exists
-    std::fs::create_dir_all("/var/log/verified-container")
-        .context("Failed to create audit log directory")?;
+    #[tokio::test]
+    async fn test_checkpoint_advances_only_with_consistency_proof() {
+        use crate::log_server::{self, SubmitRequest, SubmitResponse};
+        use crate::signing::Signer;
+        use base64::{Engine as _, engine::general_purpose};
+        use ed25519_dalek::SigningKey;
+
+        let log_key = SigningKey::from_bytes(&[7u8; 32]);
+        let log_dir = tempfile::tempdir().unwrap();
+        let url = log_server::testing::spawn(log_dir.path(), "log-dev", &log_key).await;
+
+        let mut heads = Vec::new();
+        for n in 0..3u8 {
+            let statement = serde_json::json!({
+                "_type": "https://in-toto.io/Statement/v1",
+                "subject": [{"name": "image", "digest": {"sha256": hex::encode([n; 32])}}],
+                "predicateType": "https://slsa.dev/provenance/v1",
+                "predicate": {}
+            });
+            let envelope = crate::dsse::sign_statement(&statement, &Signer::File(SigningKey::from_bytes(&[1u8; 32])), "builder").unwrap();
+            let response: SubmitResponse = reqwest::Client::new()
+                .post(format!("{}/api/v1/entries", url))
+                .json(&SubmitRequest::Attestation { attestation: serde_json::to_vec(&envelope).unwrap() })
+                .send().await.unwrap()
+                .json().await.unwrap();
+            heads.push(response.signed_tree_head);
+        }
+
+        let trust_store: TrustStore = serde_json::from_value(serde_json::json!({
+            "keys": [],
+            "threshold_groups": [],
+            "logs": {"log-dev": {
+                "operator": "Dev Operator",
+                "publicKey": general_purpose::STANDARD.encode(log_key.verifying_key().to_bytes()),
+                "url": url,
+                "algorithm": "ed25519"
+            }}
+        })).unwrap();
+        let checkpoints = tempfile::tempdir().unwrap();
+        let store = CheckpointStore::open(checkpoints.path());
+        let offline = LogsConfig { retries: 0, ..LogsConfig::default() };
+        let online = LogsConfig { online: true, retries: 0, ..LogsConfig::default() };
+
+        observe_checkpoint(&store, "log-dev", &heads[0], &trust_store, &offline).await.unwrap();
+
+        // Offline, an unproven tree head is usable but does not advance the checkpoint
+        observe_checkpoint(&store, "log-dev", &heads[2], &trust_store, &offline).await.unwrap();
+        assert_eq!(store.get("log-dev").unwrap().unwrap().tree_size, 1);
+
+        // Online, the proof is fetched from the log
+        observe_checkpoint(&store, "log-dev", &heads[2], &trust_store, &online).await.unwrap();
+        assert_eq!(store.get("log-dev").unwrap().unwrap().tree_size, 3);
+        observe_checkpoint(&store, "log-dev", &heads[1], &trust_store, &online).await.unwrap();
+
+        // An older bundle still verifies offline against the newer checkpoint
+        observe_checkpoint(&store, "log-dev", &heads[1], &trust_store, &offline).await.unwrap();
+        assert_eq!(store.get("log-dev").unwrap().unwrap().tree_size, 3);
+    }
 
-    // Append to audit log
-    use std::io::Write;
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/var/log/verified-container/audit.log")
-        .context("Failed to open audit log")?;
+    #[test]
+    fn test_stale_tree_head_rejected() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let mut head = sth::testing::signed(&key, 1, &[0u8; 32]);
+        head.timestamp = (chrono::Utc::now() - chrono::Duration::hours(2)).to_rfc3339();
+        assert!(require_fresh_tree_head("log-eu", &head, 3600).unwrap_err().to_string().contains("LOG_STALE"));
+        assert!(require_fresh_tree_head("log-eu", &head, 86400).is_ok());
+    }
 
-    writeln!(file, "{}", log_entry)?;
+    #[test]
+    fn test_every_attestation_needs_inclusion_proof() {
+        let both: BTreeSet<&str> = ["EU Operator", "US Operator"].into();
+        let one: BTreeSet<&str> = ["EU Operator"].into();
+        assert!(require_log_quorum(&[both.clone(), both.clone()], 2).is_ok());
+        let err = require_log_quorum(&[both, one.clone()], 2).unwrap_err();
+        assert!(err.to_string().contains("INSUFFICIENT_LOG_COVERAGE"));
+        assert!(require_log_quorum(&[one], 1).is_ok());
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_revocation_covers_keys_and_attestations() {
+        let attestation = test_bundle();
+        let (keyids, attestation_digests) = revocation_dependencies(&attestation).unwrap();
+        let digest = tlog::attestation_digest(attestation.attestations[0].envelope.as_ref().unwrap()).unwrap();
+        assert_eq!(keyids, ["builder"]);
+        assert_eq!(attestation_digests, vec![digest.clone()]);
+
+        let mut revocations = RevocationSet::default();
+        revocations.add(tlog::RevocationBody {
+            revoked_digest: digest,
+            reason: "key_compromise".to_string(),
+            revoked_by: "security".to_string(),
+        });
+        let depends_on = keyids.iter().chain(&attestation_digests).map(String::as_str);
+        let err = revocations.check(depends_on).unwrap_err();
+        assert!(err.to_string().contains("REVOKED"));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_trust_store_version_covers_full_document() {
+        let store = serde_json::json!({
+            "version": 1,
+            "keys": [{"keyid": "builder", "key_bytes": [1, 2, 3], "valid_until": "2027-01-01T00:00:00Z"}],
+            "threshold_groups": [{"name": "release-signers", "k": 1}]
+        });
+        let version = trust_store_version(&store);
+        assert_eq!(version.len(), 64);
+
+        // Member order within objects is irrelevant
+        let reordered: serde_json::Value = serde_json::from_str(
+            r#"{"threshold_groups":[{"k":1,"name":"release-signers"}],"keys":[{"valid_until":"2027-01-01T00:00:00Z","key_bytes":[1,2,3],"keyid":"builder"}],"version":1}"#
+        ).unwrap();
+        assert_eq!(trust_store_version(&reordered), version);
+
+        for (pointer, value) in [
+            ("/keys/0/key_bytes/0", serde_json::json!(9)),
+            ("/keys/0/valid_until", serde_json::json!("2028-01-01T00:00:00Z")),
+            ("/threshold_groups/0/k", serde_json::json!(2)),
+        ] {
+            let mut changed = store.clone();
+            *changed.pointer_mut(pointer).unwrap() = value;
+            assert_ne!(trust_store_version(&changed), version, "{}", pointer);
+        }
+    }
 
     #[test]
     fn test_verification_mode_is_strict_by_default() {